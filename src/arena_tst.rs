@@ -0,0 +1,576 @@
+use std::str::Chars;
+use std::cmp::Ordering::Less;
+use std::cmp::Ordering::Equal;
+use std::cmp::Ordering::Greater;
+use std::io::Write;
+use std::option;
+use std::mem;
+
+use Stats;
+use LabelOrder;
+use CodePointOrder;
+use query::{Search, Query};
+use walk::{self, Walk, WalkNode};
+
+
+/// A map with the same methods as `Tst`, whose nodes are stored side by side in a `Vec`.
+///
+/// Nodes are linked by `u32` indices instead of boxes, which saves allocations and keeps nodes
+/// close in memory. Slots of removed nodes are kept in a free list and reused by later inserts,
+/// and `stat` reports how the arena is used. Labels are compared by code point, unless the map
+/// is created `with_order`.
+pub struct ArenaTst<T, O = CodePointOrder> {
+
+    nodes: Vec<ArenaNode<T>>,
+    free: Vec<u32>,
+    root: u32,
+    count: usize,
+    order: O
+}
+
+
+//Index of a missing node
+const NIL: u32 = u32::MAX;
+
+
+pub struct ArenaNode<T> {
+
+    label: char,
+    value: Option<T>,
+    left: u32,
+    middle: u32,
+    right: u32
+}
+
+
+impl<T> ArenaNode<T> {
+
+    fn is_dead(&self) -> bool {
+
+        self.value.is_none() && self.left == NIL && self.middle == NIL && self.right == NIL
+    }
+}
+
+
+//Node of an arena, as read by the shared walks
+pub struct ArenaRef<'a, T: 'a> {
+
+    nodes: &'a [ArenaNode<T>],
+    index: u32
+}
+
+
+impl<'a, T> Clone for ArenaRef<'a, T> {
+
+    fn clone(&self) -> Self {
+
+        *self
+    }
+}
+
+
+impl<'a, T> Copy for ArenaRef<'a, T> {}
+
+
+impl<'a, T> ArenaRef<'a, T> {
+
+    fn new(nodes: &'a [ArenaNode<T>], index: u32) -> Option<Self> {
+
+        if index == NIL { None } else { Some(ArenaRef { nodes, index }) }
+    }
+
+
+    fn node(&self) -> &'a ArenaNode<T> {
+
+        &self.nodes[self.index as usize]
+    }
+}
+
+
+impl<'a, T> WalkNode<'a> for ArenaRef<'a, T> {
+
+    type Value = T;
+    type Label = option::IntoIter<char>;
+
+    fn label(&self) -> Self::Label {
+
+        Some(self.node().label).into_iter()
+    }
+
+
+    fn value(&self) -> Option<&'a T> {
+
+        self.node().value.as_ref()
+    }
+
+
+    fn left(&self) -> Option<Self> {
+
+        ArenaRef::new(self.nodes, self.node().left)
+    }
+
+
+    fn middle(&self) -> Option<Self> {
+
+        ArenaRef::new(self.nodes, self.node().middle)
+    }
+
+
+    fn right(&self) -> Option<Self> {
+
+        ArenaRef::new(self.nodes, self.node().right)
+    }
+
+
+    fn id(&self) -> usize {
+
+        self.index as usize
+    }
+}
+
+
+fn visit_r_mut<'b, T, O, S, C>(nodes: &mut [ArenaNode<T>], index: u32, search: &S, state: &S::State, order: &O, callback: &mut C)
+where O: LabelOrder, S: Search<'b>, C: FnMut (&mut T, &S::State) {
+
+    if index == NIL {
+
+        return;
+    }
+
+    let (label, left, middle, right) = {
+
+        let node = &nodes[index as usize];
+        (node.label, node.left, node.middle, node.right)
+    };
+
+    if search.may_go(state, label, Less, order) {
+
+        visit_r_mut(nodes, left, search, state, order, callback);
+    }
+
+    if let Some(new_state) = search.step(state.clone(), label) {
+
+        if let Some(ref mut value) = nodes[index as usize].value {
+
+            if search.accepts(&new_state) {
+
+                callback(value, &new_state);
+            }
+        }
+
+        visit_r_mut(nodes, middle, search, &new_state, order, callback);
+    }
+
+    if search.may_go(state, label, Greater, order) {
+
+        visit_r_mut(nodes, right, search, state, order, callback);
+    }
+}
+
+
+impl<T> ArenaTst<T> {
+
+    pub fn new() -> Self {
+
+        ArenaTst::with_capacity(0)
+    }
+
+
+    /// Creates an empty map with room for `capacity` nodes (i.e. chars of keys which share no
+    /// prefix) before the arena has to grow.
+    pub fn with_capacity(capacity: usize) -> Self {
+
+        ArenaTst { nodes: Vec::with_capacity(capacity), free: Vec::new(), root: NIL, count: 0, order: CodePointOrder }
+    }
+}
+
+
+impl<T, O: LabelOrder> ArenaTst<T, O> {
+
+    pub fn with_order(order: O) -> Self {
+
+        ArenaTst { nodes: Vec::new(), free: Vec::new(), root: NIL, count: 0, order }
+    }
+
+
+    pub fn order(&self) -> &O {
+
+        &self.order
+    }
+
+
+    fn root_node(&self) -> Option<ArenaRef<'_, T>> {
+
+        ArenaRef::new(&self.nodes, self.root)
+    }
+
+
+    fn visit_mut<'b, S, C>(&mut self, search: S, state: Option<S::State>, mut callback: C)
+    where S: Search<'b>, C: FnMut (&mut T, &S::State) {
+
+        if let Some(state) = state {
+
+            visit_r_mut(&mut self.nodes, self.root, &search, &state, &self.order, &mut callback);
+        }
+    }
+
+
+    fn alloc(&mut self, label: char) -> u32 {
+
+        let node = ArenaNode { label, value: None, left: NIL, middle: NIL, right: NIL };
+
+        match self.free.pop() {
+
+            Some(index) => {
+
+                self.nodes[index as usize] = node;
+                index
+            }
+
+            None => {
+
+                assert!(self.nodes.len() < NIL as usize, "too many nodes for u32 indices");
+
+                self.nodes.push(node);
+                (self.nodes.len() - 1) as u32
+            }
+        }
+    }
+
+
+    fn insert_r(&mut self, index: u32, label: char, mut key_tail: Chars, value: T) -> (u32, Option<T>) {
+
+        let index = if index == NIL { self.alloc(label) } else { index };
+        let i = index as usize;
+
+        let old_value = match self.order.compare(label, self.nodes[i].label) {
+
+            Less => {
+
+                let (left, old_value) = self.insert_r(self.nodes[i].left, label, key_tail, value);
+                self.nodes[i].left = left;
+                old_value
+            }
+
+            Greater => {
+
+                let (right, old_value) = self.insert_r(self.nodes[i].right, label, key_tail, value);
+                self.nodes[i].right = right;
+                old_value
+            }
+
+            Equal => match key_tail.next() {
+
+                None => self.nodes[i].value.replace(value),
+
+                Some(label) => {
+
+                    let (middle, old_value) = self.insert_r(self.nodes[i].middle, label, key_tail, value);
+                    self.nodes[i].middle = middle;
+                    old_value
+                }
+            }
+        };
+
+        (index, old_value)
+    }
+
+
+    /// Same as `Tst::insert`.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the arena needs a new slot while it already holds `u32::MAX` nodes, the most
+    /// that `u32` indices can link.
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        let mut key_tail = key.chars();
+
+        match key_tail.next() {
+
+            None => Some(value),
+
+            Some(label) => {
+
+                let (root, old_value) = self.insert_r(self.root, label, key_tail, value);
+                self.root = root;
+
+                if old_value.is_none() {
+
+                    self.count += 1;
+                }
+
+                old_value
+            }
+        }
+    }
+
+
+    //Index of the node where `key` ends
+    fn find(&self, key: &str) -> Option<usize> {
+
+        let mut key_tail = key.chars();
+
+        let mut label = key_tail.next()?;
+
+        let mut index = self.root;
+
+        while index != NIL {
+
+            let node = &self.nodes[index as usize];
+
+            index = match self.order.compare(label, node.label) {
+
+                Less => node.left,
+
+                Greater => node.right,
+
+                Equal => match key_tail.next() {
+
+                    None => return Some(index as usize),
+
+                    Some(next) => {
+
+                        label = next;
+                        node.middle
+                    }
+                }
+            };
+        }
+
+        None
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        match self.find(key) {
+
+            None => None,
+
+            Some(i) => self.nodes[i].value.as_ref()
+        }
+    }
+
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+
+        match self.find(key) {
+
+            None => None,
+
+            Some(i) => self.nodes[i].value.as_mut()
+        }
+    }
+
+
+    //Removes the value of `key` below `index`, and tells whether the node at `index` is left useless
+    fn remove_r(&mut self, index: u32, label: char, key_tail: &mut Chars) -> (bool, Option<T>) {
+
+        if index == NIL {
+
+            return (false, None);
+        }
+
+        let i = index as usize;
+
+        match self.order.compare(label, self.nodes[i].label) {
+
+            Less => {
+
+                let left = self.nodes[i].left;
+                let (prune, old_value) = self.remove_r(left, label, key_tail);
+
+                if prune {
+
+                    self.nodes[i].left = NIL;
+                    self.free.push(left);
+                }
+
+                (self.nodes[i].is_dead(), old_value)
+            }
+
+            Greater => {
+
+                let right = self.nodes[i].right;
+                let (prune, old_value) = self.remove_r(right, label, key_tail);
+
+                if prune {
+
+                    self.nodes[i].right = NIL;
+                    self.free.push(right);
+                }
+
+                (self.nodes[i].is_dead(), old_value)
+            }
+
+            Equal => match key_tail.next() {
+
+                None => {
+
+                    let old_value = self.nodes[i].value.take();
+
+                    (old_value.is_some() && self.nodes[i].is_dead(), old_value)
+                }
+
+                Some(label) => {
+
+                    let middle = self.nodes[i].middle;
+                    let (prune, old_value) = self.remove_r(middle, label, key_tail);
+
+                    if prune {
+
+                        self.nodes[i].middle = NIL;
+                        self.free.push(middle);
+                    }
+
+                    (self.nodes[i].is_dead(), old_value)
+                }
+            }
+        }
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        let mut key_tail = key.chars();
+
+        let (prune, old_value) = match key_tail.next() {
+
+            None => (false, None),
+
+            Some(label) => self.remove_r(self.root, label, &mut key_tail)
+        };
+
+        if prune {
+
+            self.free.push(self.root);
+            self.root = NIL;
+        }
+
+        if old_value.is_some() {
+
+            self.count -= 1;
+        }
+
+        old_value
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.count
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    /// Same statistics as `Tst::stat`, plus the count of slots in the arena, of free slots
+    /// waiting for reuse, and the capacity of the arena. The total size covers the whole arena.
+    pub fn stat(&self) -> Stats {
+
+        let empty_stats: Stats = Default::default();
+
+        let mut stats = walk::stat_r(empty_stats, self.root_node(), 0, 0, 0);
+
+        stats.arena.slots = self.nodes.len();
+        stats.arena.free = self.free.len();
+        stats.arena.capacity = self.nodes.capacity();
+
+        stats.bytes.node = mem::size_of::<ArenaNode<T>>();
+        stats.bytes.total += mem::size_of::<ArenaTst<T, O>>() + stats.arena.capacity*stats.bytes.node
+            + self.free.capacity()*mem::size_of::<u32>();
+
+        stats
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.nodes.clear();
+        self.free.clear();
+        self.root = NIL;
+        self.count = 0;
+    }
+
+
+    /// Moves the nodes so that the arena has no free slot left, and gives back unused memory.
+    pub fn shrink_to_fit(&mut self) {
+
+        let mut nodes = Vec::with_capacity(self.nodes.len() - self.free.len());
+        let mut old_nodes: Vec<Option<ArenaNode<T>>> = self.nodes.drain(..).map(Some).collect();
+
+        //Nodes are moved in depth first order, and links are rewritten as nodes land
+        let mut todo = vec![self.root];
+        let mut moved = vec![NIL; old_nodes.len()];
+
+        while let Some(index) = todo.pop() {
+
+            if index == NIL || moved[index as usize] != NIL {
+
+                continue;
+            }
+
+            let node = old_nodes[index as usize].take().unwrap();
+
+            moved[index as usize] = nodes.len() as u32;
+            todo.push(node.right);
+            todo.push(node.middle);
+            todo.push(node.left);
+            nodes.push(node);
+        }
+
+        for node in nodes.iter_mut() {
+
+            for link in [&mut node.left, &mut node.middle, &mut node.right].iter_mut() {
+
+                if **link != NIL {
+
+                    **link = moved[**link as usize];
+                }
+            }
+        }
+
+        if self.root != NIL {
+
+            self.root = 0;
+        }
+
+        self.nodes = nodes;
+        self.free = Vec::new();
+    }
+
+
+    gen_search_api!(ArenaTstIterator);
+    gen_search_api_mut!();
+}
+
+
+impl<T> Default for ArenaTst<T> {
+
+    fn default() -> Self {
+
+        ArenaTst::new()
+    }
+}
+
+
+impl<'a, T, O: LabelOrder> IntoIterator for &'a ArenaTst<T, O> {
+
+    type Item = &'a T;
+    type IntoIter = ArenaTstIterator<'a, 'static, T, O>;
+
+    fn into_iter(self) -> Self::IntoIter {
+
+        self.iter()
+    }
+}
+
+
+/// Iterator of all the searches of an `ArenaTst`, with the same methods as the iterators of `Tst`
+/// (such as `current_key`, or `current_distance` for fuzzy completions).
+pub type ArenaTstIterator<'a, 'b, T, O = CodePointOrder, S = Query> = Walk<'a, 'b, ArenaRef<'a, T>, O, S>;
@@ -0,0 +1,455 @@
+use std::cmp::Ordering::Less;
+use std::cmp::max;
+use std::error::Error;
+use std::fmt;
+
+use Link;
+use Node;
+use LabelOrder;
+use Balancing;
+use CodePointOrder;
+use Tst;
+use walk;
+
+
+/// Error of `Tst::from_sorted_iter`, when a key is not strictly greater than the one before it.
+#[derive(Debug, PartialEq)]
+pub struct NotSortedError {
+
+    /// Position of the key in the input, counting from 0.
+    pub index: usize,
+    pub key: String
+}
+
+
+impl fmt::Display for NotSortedError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        write!(f, "key \"{}\" at index {} is not greater than the previous one", self.key, self.index)
+    }
+}
+
+
+impl Error for NotSortedError {}
+
+
+type SortedItems<T> = Vec<(Vec<char>, Option<T>)>;
+
+
+//Builds the sibling tree of the sorted keys, which are all longer than `depth` and share their
+//first `depth` chars. Each sibling tree gets the median label as root, then its halves on each side
+fn build_r<T>(items: &mut [(Vec<char>, Option<T>)], depth: usize) -> Link<T> {
+
+    if items.is_empty() {
+
+        return None;
+    }
+
+    let starts: Vec<usize> = (0..items.len()).filter(|&i| i == 0 || items[i].0[depth] != items[i-1].0[depth]).collect();
+
+    let mid = starts.len() / 2;
+    let end = if mid+1 < starts.len() { starts[mid+1] } else { items.len() };
+
+    let (left, rest) = items.split_at_mut(starts[mid]);
+    let (group, right) = rest.split_at_mut(end - starts[mid]);
+
+    let label = group[0].0[depth];
+
+    //The key ending here, if any, is the first one of its group
+    let (value, middle) = if group[0].0.len() == depth+1 {
+
+        let value = group[0].1.take();
+        (value, &mut group[1..])
+
+    } else {
+
+        (None, group)
+    };
+
+    Some(Box::new(Node {
+
+        label,
+        value,
+        left: build_r(left, depth),
+        middle: build_r(middle, depth+1),
+        right: build_r(right, depth)
+    }))
+}
+
+
+/// How far the sibling trees of a `Tst` are from a balanced shape, as given by `balance_report`.
+#[derive(Default, PartialEq, Debug)]
+pub struct BalanceReport {
+
+    /// Count of left/right sibling trees (one per distinct prefix followed by some char).
+    pub sibling_trees: usize,
+
+    /// Count of sibling trees taller than a balanced tree of the same size.
+    pub unbalanced_trees: usize,
+
+    /// Sum over all sibling trees of their levels above the balanced height.
+    pub extra_levels: usize,
+
+    /// Height of the tallest sibling tree.
+    pub max_height: usize,
+
+    /// Nodes left with no value and no middle child by removals.
+    pub dead_nodes: usize
+}
+
+
+impl BalanceReport {
+
+    /// Whether `optimize` would be worth running : some dead nodes can be dropped, or more than
+    /// one sibling tree in ten is taller than needed.
+    pub fn is_worth_optimizing(&self) -> bool {
+
+        self.dead_nodes > 0 || self.unbalanced_trees * 10 > self.sibling_trees
+    }
+}
+
+
+//Size and height of the sibling tree at `link`, while reporting on the sibling trees below it
+fn shape_r<T>(link: &Link<T>, report: &mut BalanceReport) -> (usize, usize) {
+
+    match *link {
+
+        None => (0, 0),
+
+        Some(ref node) => {
+
+            let (left_size, left_height) = shape_r(&node.left, report);
+            let (right_size, right_height) = shape_r(&node.right, report);
+
+            if node.value.is_none() && node.middle.is_none() {
+
+                report.dead_nodes += 1;
+            }
+
+            report_r(&node.middle, report);
+
+            (left_size+right_size+1, max(left_height, right_height)+1)
+        }
+    }
+}
+
+
+fn report_r<T>(link: &Link<T>, report: &mut BalanceReport) {
+
+    if link.is_none() {
+
+        return;
+    }
+
+    let (size, height) = shape_r(link, report);
+
+    //Height of a balanced binary tree of this size
+    let balanced_height = (0usize.count_zeros() - size.leading_zeros()) as usize;
+
+    report.sibling_trees += 1;
+    report.max_height = max(report.max_height, height);
+
+    if height > balanced_height {
+
+        report.unbalanced_trees += 1;
+        report.extra_levels += height - balanced_height;
+    }
+}
+
+
+//Detaches the nodes of a sibling tree, in order
+fn flatten_r<T>(link: Link<T>, nodes: &mut Vec<Box<Node<T>>>) {
+
+    if let Some(mut node) = link {
+
+        let left = node.left.take();
+        let right = node.right.take();
+
+        flatten_r(left, nodes);
+        nodes.push(node);
+        flatten_r(right, nodes);
+    }
+}
+
+
+//Relinks the sorted nodes with the median as root, or the node with the highest priority for a treap
+fn rebuild_r<T>(nodes: &mut [Option<Box<Node<T>>>], treap: bool) -> Link<T> {
+
+    if nodes.is_empty() {
+
+        return None;
+    }
+
+    let root = if treap {
+
+        (0..nodes.len()).max_by_key(|&i| nodes[i].as_ref().map(|node| priority(node.label))).unwrap()
+
+    } else {
+
+        nodes.len() / 2
+    };
+
+    let (left, rest) = nodes.split_at_mut(root);
+    let (node, right) = rest.split_first_mut().unwrap();
+
+    let mut node = node.take().unwrap();
+
+    node.left = rebuild_r(left, treap);
+    node.right = rebuild_r(right, treap);
+
+    Some(node)
+}
+
+
+fn optimize_r<T>(link: &mut Link<T>, treap: bool) {
+
+    let mut nodes = Vec::new();
+
+    flatten_r(link.take(), &mut nodes);
+
+    for node in nodes.iter_mut() {
+
+        optimize_r(&mut node.middle, treap);
+    }
+
+    nodes.retain(|node| node.value.is_some() || node.middle.is_some());
+
+    let mut nodes: Vec<_> = nodes.into_iter().map(Some).collect();
+
+    *link = rebuild_r(&mut nodes, treap);
+}
+
+
+//Treap priority of a label. The mix of bits is a bijection, so that labels never share a priority
+fn priority(label: char) -> u32 {
+
+    let mut h = label as u32;
+
+    h ^= h >> 16;
+    h = h.wrapping_mul(0x85eb_ca6b);
+    h ^= h >> 13;
+    h = h.wrapping_mul(0xc2b2_ae35);
+    h ^= h >> 16;
+
+    h
+}
+
+
+fn rotate_right<T>(link: &mut Link<T>) {
+
+    let mut node = link.take().unwrap();
+    let mut left = node.left.take().unwrap();
+
+    node.left = left.right.take();
+    left.right = Some(node);
+
+    *link = Some(left);
+}
+
+
+fn rotate_left<T>(link: &mut Link<T>) {
+
+    let mut node = link.take().unwrap();
+    let mut right = node.right.take().unwrap();
+
+    node.right = right.left.take();
+    right.left = Some(node);
+
+    *link = Some(right);
+}
+
+
+//Moves up a left or right child with a higher priority than its parent, after an insert below it
+pub fn lift_child<T>(link: &mut Link<T>) {
+
+    let (lift_left, lift_right) = match *link {
+
+        None => return,
+
+        Some(ref node) => {
+
+            let p = priority(node.label);
+
+            (node.left.as_ref().filter(|left| priority(left.label) > p).is_some(),
+             node.right.as_ref().filter(|right| priority(right.label) > p).is_some())
+        }
+    };
+
+    if lift_left {
+
+        rotate_right(link);
+
+    } else if lift_right {
+
+        rotate_left(link);
+    }
+}
+
+
+//Takes the node out of its sibling tree, rotating it down until it has at most one left or right child
+pub fn unlink_r<T>(link: &mut Link<T>) {
+
+    let lift_left = match *link {
+
+        None => return,
+
+        Some(ref mut node) => match (node.left.is_some(), node.right.is_some()) {
+
+            (false, _) => {
+
+                let right = node.right.take();
+                *link = right;
+                return;
+            }
+
+            (_, false) => {
+
+                let left = node.left.take();
+                *link = left;
+                return;
+            }
+
+            (true, true) => {
+
+                let left = node.left.as_ref().unwrap();
+                let right = node.right.as_ref().unwrap();
+
+                priority(left.label) > priority(right.label)
+            }
+        }
+    };
+
+    if lift_left {
+
+        rotate_right(link);
+        unlink_r(&mut link.as_mut().unwrap().right);
+
+    } else {
+
+        rotate_left(link);
+        unlink_r(&mut link.as_mut().unwrap().left);
+    }
+}
+
+
+impl<T> Tst<T> {
+
+    /// Builds a tree from keys sorted by code point (as `str` sorts them), with balanced sibling
+    /// trees. Fails on the first key which is not greater than the one before it.
+    pub fn from_sorted_iter<I, K>(items: I) -> Result<Self, NotSortedError>
+    where I: IntoIterator<Item = (K, T)>, K: AsRef<str> {
+
+        Tst::from_sorted_iter_with_order(items, CodePointOrder)
+    }
+
+
+    /// Builds a tree from keys in any order, with balanced sibling trees. When a key comes more
+    /// than once, its last value is kept, as with repeated inserts.
+    pub fn build_balanced<I, K>(items: I) -> Self
+    where I: IntoIterator<Item = (K, T)>, K: AsRef<str> {
+
+        Tst::build_balanced_with_order(items, CodePointOrder)
+    }
+}
+
+
+impl<T, O: LabelOrder> Tst<T, O> {
+
+    /// Same as `from_sorted_iter`, for keys sorted with `order`.
+    pub fn from_sorted_iter_with_order<I, K>(items: I, order: O) -> Result<Self, NotSortedError>
+    where I: IntoIterator<Item = (K, T)>, K: AsRef<str> {
+
+        let mut sorted: SortedItems<T> = Vec::new();
+
+        for (index, (key, value)) in items.into_iter().enumerate() {
+
+            let key: Vec<char> = key.as_ref().chars().collect();
+
+            if let Some((previous, _)) = sorted.last() {
+
+                if walk::compare_keys(&order, previous.iter().cloned(), key.iter().cloned()) != Less {
+
+                    return Err(NotSortedError { index, key: key.into_iter().collect() });
+                }
+            }
+
+            //Empty keys are never stored, as with `insert`
+            if !key.is_empty() {
+
+                sorted.push((key, Some(value)));
+            }
+        }
+
+        Ok(Tst::from_sorted_vec(sorted, order))
+    }
+
+
+    /// Same as `build_balanced`, with labels ordered by `order`.
+    pub fn build_balanced_with_order<I, K>(items: I, order: O) -> Self
+    where I: IntoIterator<Item = (K, T)>, K: AsRef<str> {
+
+        let mut sorted: SortedItems<T> = items.into_iter()
+            .map(|(key, value)| (key.as_ref().chars().collect::<Vec<char>>(), Some(value)))
+            .filter(|(key, _)| !key.is_empty())
+            .collect();
+
+        //Stable sort, so that the last value of a key comes last among its duplicates
+        sorted.sort_by(|a, b| walk::compare_keys(&order, a.0.iter().cloned(), b.0.iter().cloned()));
+
+        let mut deduped: SortedItems<T> = Vec::with_capacity(sorted.len());
+
+        for item in sorted {
+
+            if let Some(last) = deduped.last_mut() {
+
+                if last.0 == item.0 {
+
+                    *last = item;
+                    continue;
+                }
+            }
+
+            deduped.push(item);
+        }
+
+        Tst::from_sorted_vec(deduped, order)
+    }
+
+
+    fn from_sorted_vec(mut sorted: SortedItems<T>, order: O) -> Self {
+
+        let mut tst = Tst::with_order(order);
+
+        tst.count = sorted.len();
+        tst.root = build_r(&mut sorted, 0);
+
+        tst
+    }
+}
+
+
+impl<T, O: LabelOrder, B: Balancing> Tst<T, O, B> {
+
+    /// Rebuilds every left/right sibling tree into a balanced shape, and drops the nodes left
+    /// useless by removals. Keys, values and the order of iterations do not change.
+    ///
+    /// A self-balancing tree keeps its treap shape instead of the perfectly balanced one.
+    pub fn optimize(&mut self) {
+
+        let treap = self.is_self_balancing();
+
+        optimize_r(&mut self.root, treap);
+    }
+
+
+    /// Measures how far the sibling trees are from the shape `optimize` would give them.
+    pub fn balance_report(&self) -> BalanceReport {
+
+        let mut report = BalanceReport::default();
+
+        report_r(&self.root, &mut report);
+
+        report
+    }
+}
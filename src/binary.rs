@@ -0,0 +1,571 @@
+use std::io::{self, Read, Write, ErrorKind};
+use std::str;
+use std::cmp::Ordering::Less;
+
+use Tst;
+use Node;
+use Link;
+use LabelOrder;
+use Balancing;
+use CodePointOrder;
+
+
+const MAGIC: &[u8; 4] = b"TSTB";
+const VERSION: u8 = 1;
+
+const HAS_VALUE: u8 = 1;
+const HAS_LEFT: u8 = 2;
+const HAS_MIDDLE: u8 = 4;
+const HAS_RIGHT: u8 = 8;
+
+
+/// Encoding of the values of a `Tst` in its binary format.
+///
+/// Numbers are written in little endian, strings and vectors as their length followed by their
+/// content. `decode` must read exactly what `encode` wrote.
+pub trait Codec: Sized {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()>;
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self>;
+}
+
+
+fn invalid_data(message: &str) -> io::Error {
+
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+
+fn write_len<W: Write>(writer: &mut W, mut len: u64) -> io::Result<()> {
+
+    loop {
+
+        let byte = (len & 0x7f) as u8;
+
+        len >>= 7;
+
+        if len == 0 {
+
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+
+fn read_len<R: Read>(reader: &mut R) -> io::Result<u64> {
+
+    let mut len = 0u64;
+
+    for shift in (0..64).step_by(7) {
+
+        let mut byte = [0u8];
+
+        reader.read_exact(&mut byte)?;
+
+        len |= u64::from(byte[0] & 0x7f) << shift;
+
+        if byte[0] & 0x80 == 0 {
+
+            return Ok(len);
+        }
+    }
+
+    Err(invalid_data("length too long"))
+}
+
+
+//Reads `len` bytes, without trusting `len` to allocate them up front
+fn read_bytes<R: Read>(reader: &mut R, len: u64) -> io::Result<Vec<u8>> {
+
+    let mut bytes = Vec::new();
+
+    reader.take(len).read_to_end(&mut bytes)?;
+
+    if (bytes.len() as u64) < len {
+
+        return Err(io::Error::new(ErrorKind::UnexpectedEof, "truncated data"));
+    }
+
+    Ok(bytes)
+}
+
+
+macro_rules! number_codec {
+
+    ($($ty:ty),*) => { $(
+
+        impl Codec for $ty {
+
+            fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+                writer.write_all(&self.to_le_bytes())
+            }
+
+
+            fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+                let mut bytes = [0u8; std::mem::size_of::<$ty>()];
+
+                reader.read_exact(&mut bytes)?;
+
+                Ok(<$ty>::from_le_bytes(bytes))
+            }
+        }
+
+    )* }
+}
+
+
+number_codec!(u8, u16, u32, u64, i8, i16, i32, i64, f32, f64);
+
+
+impl Codec for usize {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        (*self as u64).encode(writer)
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        let n = u64::decode(reader)?;
+
+        if n > usize::MAX as u64 {
+
+            return Err(invalid_data("usize out of range"));
+        }
+
+        Ok(n as usize)
+    }
+}
+
+
+impl Codec for bool {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        (*self as u8).encode(writer)
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        match u8::decode(reader)? {
+
+            0 => Ok(false),
+
+            1 => Ok(true),
+
+            _ => Err(invalid_data("invalid bool"))
+        }
+    }
+}
+
+
+impl Codec for char {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        (*self as u32).encode(writer)
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        std::char::from_u32(u32::decode(reader)?).ok_or_else(|| invalid_data("invalid char"))
+    }
+}
+
+
+impl Codec for () {
+
+    fn encode<W: Write>(&self, _writer: &mut W) -> io::Result<()> {
+
+        Ok(())
+    }
+
+
+    fn decode<R: Read>(_reader: &mut R) -> io::Result<Self> {
+
+        Ok(())
+    }
+}
+
+
+impl Codec for String {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        write_len(writer, self.len() as u64)?;
+        writer.write_all(self.as_bytes())
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        let len = read_len(reader)?;
+        let bytes = read_bytes(reader, len)?;
+
+        String::from_utf8(bytes).map_err(|_| invalid_data("invalid UTF-8 in string"))
+    }
+}
+
+
+impl<T: Codec> Codec for Vec<T> {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        write_len(writer, self.len() as u64)?;
+
+        for item in self.iter() {
+
+            item.encode(writer)?;
+        }
+
+        Ok(())
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        let len = read_len(reader)?;
+        let mut items = Vec::new();
+
+        for _ in 0..len {
+
+            items.push(T::decode(reader)?);
+        }
+
+        Ok(items)
+    }
+}
+
+
+impl<T: Codec> Codec for Option<T> {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        match *self {
+
+            None => false.encode(writer),
+
+            Some(ref value) => {
+
+                true.encode(writer)?;
+                value.encode(writer)
+            }
+        }
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        if bool::decode(reader)? { Ok(Some(T::decode(reader)?)) } else { Ok(None) }
+    }
+}
+
+
+impl<A: Codec, B: Codec> Codec for (A, B) {
+
+    fn encode<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        self.0.encode(writer)?;
+        self.1.encode(writer)
+    }
+
+
+    fn decode<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        Ok((A::decode(reader)?, B::decode(reader)?))
+    }
+}
+
+
+fn write_r<T: Codec, W: Write>(link: &Link<T>, writer: &mut W) -> io::Result<()> {
+
+    if let Some(ref node) = *link {
+
+        write_r(&node.left, writer)?;
+        write_r(&node.middle, writer)?;
+        write_r(&node.right, writer)?;
+
+        let mut label = [0u8; 4];
+
+        writer.write_all(node.label.encode_utf8(&mut label).as_bytes())?;
+        writer.write_all(&[node_flags(node)])?;
+
+        if let Some(ref value) = node.value {
+
+            value.encode(writer)?;
+        }
+    }
+
+    Ok(())
+}
+
+
+//Which of its value and children a node has, as stored with each node
+pub fn node_flags<T>(node: &Node<T>) -> u8 {
+
+    let mut flags = 0;
+
+    if node.value.is_some() { flags |= HAS_VALUE; }
+    if node.left.is_some() { flags |= HAS_LEFT; }
+    if node.middle.is_some() { flags |= HAS_MIDDLE; }
+    if node.right.is_some() { flags |= HAS_RIGHT; }
+
+    flags
+}
+
+
+//Subtree with the lowest and highest labels of its sibling tree
+type Subtree<T> = (Box<Node<T>>, char, char);
+
+
+//Rebuilds a tree from its nodes given in post-order, children before their parent, checking
+//that each sibling tree is sorted in the order the tree will be searched with
+pub struct NodeStack<'o, T, O: 'o> {
+
+    subtrees: Vec<Subtree<T>>,
+    values: u64,
+    order: &'o O
+}
+
+
+impl<'o, T, O: LabelOrder> NodeStack<'o, T, O> {
+
+    pub fn new(order: &'o O) -> Self {
+
+        NodeStack { subtrees: Vec::new(), values: 0, order }
+    }
+
+
+    pub fn push(&mut self, label: char, flags: u8, value: Option<T>) -> Result<(), &'static str> {
+
+        if flags & !(HAS_VALUE | HAS_LEFT | HAS_MIDDLE | HAS_RIGHT) != 0 || (flags & HAS_VALUE != 0) != value.is_some() {
+
+            return Err("invalid node flags");
+        }
+
+        if value.is_some() {
+
+            self.values += 1;
+        }
+
+        //Subtrees were pushed in left, middle, right order
+        let subtrees = &mut self.subtrees;
+
+        let mut pop = |flag: u8| -> Result<Option<Subtree<T>>, &'static str> {
+
+            if flags & flag == 0 {
+
+                return Ok(None);
+            }
+
+            match subtrees.pop() {
+
+                None => Err("missing child node"),
+
+                Some(child) => Ok(Some(child))
+            }
+        };
+
+        let right = pop(HAS_RIGHT)?;
+        let middle = pop(HAS_MIDDLE)?;
+        let left = pop(HAS_LEFT)?;
+
+        //Left labels come before this one, and right labels after it
+        let mut lowest = label;
+        let mut highest = label;
+
+        if let Some((_, left_lowest, left_highest)) = left {
+
+            if self.order.compare(left_highest, label) != Less {
+
+                return Err("labels out of order");
+            }
+
+            lowest = left_lowest;
+        }
+
+        if let Some((_, right_lowest, right_highest)) = right {
+
+            if self.order.compare(label, right_lowest) != Less {
+
+                return Err("labels out of order");
+            }
+
+            highest = right_highest;
+        }
+
+        let node = Node {
+
+            label, value,
+            left: left.map(|(child, _, _)| child),
+            middle: middle.map(|(child, _, _)| child),
+            right: right.map(|(child, _, _)| child)
+        };
+
+        subtrees.push((Box::new(node), lowest, highest));
+
+        Ok(())
+    }
+
+
+    //Root of the tree, with its count of values
+    pub fn finish(mut self) -> Result<(Link<T>, u64), &'static str> {
+
+        if self.subtrees.len() > 1 {
+
+            return Err("nodes without a parent");
+        }
+
+        Ok((self.subtrees.pop().map(|(root, _, _)| root), self.values))
+    }
+}
+
+
+fn read_label<R: Read>(reader: &mut R) -> io::Result<char> {
+
+    let mut bytes = [0u8; 4];
+
+    reader.read_exact(&mut bytes[..1])?;
+
+    let len = match bytes[0] {
+
+        0x00..=0x7f => 1,
+
+        0xc0..=0xdf => 2,
+
+        0xe0..=0xef => 3,
+
+        0xf0..=0xf7 => 4,
+
+        _ => return Err(invalid_data("invalid UTF-8 in label"))
+    };
+
+    reader.read_exact(&mut bytes[1..len])?;
+
+    match str::from_utf8(&bytes[..len]) {
+
+        Ok(s) => Ok(s.chars().next().unwrap()),
+
+        Err(_) => Err(invalid_data("invalid UTF-8 in label"))
+    }
+}
+
+
+fn read_nodes<T: Codec, R: Read, O: LabelOrder>(reader: &mut R, node_count: u64, order: &O) -> io::Result<(Link<T>, u64)> {
+
+    let mut stack = NodeStack::new(order);
+
+    for _ in 0..node_count {
+
+        let label = read_label(reader)?;
+
+        let mut flags = [0u8];
+
+        reader.read_exact(&mut flags)?;
+
+        let value = if flags[0] & HAS_VALUE != 0 { Some(T::decode(reader)?) } else { None };
+
+        stack.push(label, flags[0], value).map_err(invalid_data)?;
+    }
+
+    stack.finish().map_err(invalid_data)
+}
+
+
+impl<T: Codec> Tst<T> {
+
+    /// Reads a tree written by `write_to`. Fails with `ErrorKind::InvalidData` on a wrong magic
+    /// header, an unknown version or inconsistent data, such as sibling labels out of order.
+    pub fn read_from<R: Read>(reader: &mut R) -> io::Result<Self> {
+
+        Tst::read_from_with_order(reader, CodePointOrder)
+    }
+}
+
+
+impl<T: Codec, O: LabelOrder, B: Balancing> Tst<T, O, B> {
+
+    /// Writes the tree in a compact binary format which keeps its node structure, so that
+    /// `read_from` gets it back without inserting keys one by one.
+    ///
+    /// All numbers are little endian, and lengths are unsigned LEB128 varints.
+    ///
+    /// ```text
+    /// magic     4 bytes, "TSTB"
+    /// version   1 byte, currently 1
+    /// nodes     varint, count of nodes
+    /// values    varint, count of values
+    /// node*     the nodes in post-order : left, middle and right subtrees, then the node itself
+    /// ```
+    ///
+    /// Each node is its label in UTF-8, then one byte of flags telling which of the value (1),
+    /// left (2), middle (4) and right (8) children it has, then its value encoded with `Codec`
+    /// when there is one. As children come before their parent, a reader rebuilds the tree with
+    /// a stack of subtrees, whatever its depth.
+    pub fn write_to<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        let stats = self.stat();
+
+        writer.write_all(MAGIC)?;
+        writer.write_all(&[VERSION])?;
+
+        write_len(writer, stats.count.nodes as u64)?;
+        write_len(writer, self.count as u64)?;
+
+        write_r(&self.root, writer)
+    }
+}
+
+
+impl<T: Codec, O: LabelOrder> Tst<T, O> {
+
+    /// Same as `read_from`, for a tree written with the same `order`, as the node structure is
+    /// read back as it was.
+    ///
+    /// A self-balancing tree is read back as a plain one, see `into_self_balancing`.
+    pub fn read_from_with_order<R: Read>(reader: &mut R, order: O) -> io::Result<Self> {
+
+        let mut magic = [0u8; 4];
+
+        reader.read_exact(&mut magic)?;
+
+        if &magic != MAGIC {
+
+            return Err(invalid_data("not a ternary tree file"));
+        }
+
+        let mut version = [0u8];
+
+        reader.read_exact(&mut version)?;
+
+        if version[0] != VERSION {
+
+            return Err(invalid_data(&format!("unsupported version {}", version[0])));
+        }
+
+        let node_count = read_len(reader)?;
+        let count = read_len(reader)?;
+
+        let (root, value_count) = read_nodes(reader, node_count, &order)?;
+
+        if value_count != count {
+
+            return Err(invalid_data("count of values does not match the header"));
+        }
+
+        let mut tst = Tst::with_order(order);
+
+        tst.root = root;
+        tst.count = count as usize;
+
+        Ok(tst)
+    }
+}
@@ -0,0 +1,532 @@
+use std::str::Chars;
+use std::cmp::Ordering::Less;
+use std::cmp::Ordering::Equal;
+use std::cmp::Ordering::Greater;
+use std::io::Write;
+use std::mem;
+
+use Stats;
+use LabelOrder;
+use CodePointOrder;
+use query::{Search, Query};
+use walk::{self, Walk, WalkNode};
+
+
+/// A map with the same searches as `Tst`, where chains of middle nodes are compressed.
+///
+/// A node label is a run of chars instead of a single char : a key inserted alone takes a single
+/// node, which is split when another key diverges from it, and merged back with its middle child
+/// when removals leave it with no value and a single follower. Labels are compared by code point,
+/// unless the map is created `with_order`.
+pub struct CompressedTst<T, O = CodePointOrder> {
+
+    root: CompressedLink<T>,
+    count: usize,
+    order: O
+}
+
+
+type CompressedLink<T> = Option<Box<CompressedNode<T>>>;
+
+
+//Label chars after the first one have no siblings, and only the last one may hold a value
+pub struct CompressedNode<T> {
+
+    label: String,
+    value: Option<T>,
+    left: CompressedLink<T>,
+    middle: CompressedLink<T>,
+    right: CompressedLink<T>
+}
+
+
+impl<T> CompressedNode<T> {
+
+    fn first_label(&self) -> char {
+
+        self.label.chars().next().unwrap()
+    }
+}
+
+
+impl<'a, T> WalkNode<'a> for &'a CompressedNode<T> {
+
+    type Value = T;
+    type Label = Chars<'a>;
+
+    fn label(&self) -> Chars<'a> {
+
+        self.label.chars()
+    }
+
+
+    fn value(&self) -> Option<&'a T> {
+
+        self.value.as_ref()
+    }
+
+
+    fn left(&self) -> Option<Self> {
+
+        self.left.as_deref()
+    }
+
+
+    fn middle(&self) -> Option<Self> {
+
+        self.middle.as_deref()
+    }
+
+
+    fn right(&self) -> Option<Self> {
+
+        self.right.as_deref()
+    }
+
+
+    fn id(&self) -> usize {
+
+        *self as *const CompressedNode<T> as usize
+    }
+
+
+    fn label_capacity(&self) -> usize {
+
+        self.label.capacity()
+    }
+}
+
+
+//Lengths in bytes of the common prefix of a key and a label, in the key then in the label. Chars
+//are the same when the order finds them equal, which it may do for chars with different lengths
+fn common_prefix_len<O: LabelOrder>(key: &str, label: &str, order: &O) -> (usize, usize) {
+
+    let mut lens = (0, 0);
+
+    for (k, l) in key.chars().zip(label.chars()) {
+
+        if order.compare(k, l) != Equal {
+
+            break;
+        }
+
+        lens.0 += k.len_utf8();
+        lens.1 += l.len_utf8();
+    }
+
+    lens
+}
+
+
+fn insert_r<T, O: LabelOrder>(link: &mut CompressedLink<T>, key: &str, value: T, order: &O) -> Option<T> {
+
+    let node = match *link {
+
+        None => {
+
+            *link = Some(Box::new(CompressedNode { label: key.to_string(), value: Some(value), left: None, middle: None, right: None }));
+            return None;
+        }
+
+        Some(ref mut node) => node
+    };
+
+    match order.compare(key.chars().next().unwrap(), node.first_label()) {
+
+        Less => insert_r(&mut node.left, key, value, order),
+
+        Greater => insert_r(&mut node.right, key, value, order),
+
+        Equal => {
+
+            let (key_len, label_len) = common_prefix_len(key, &node.label, order);
+
+            //The key leaves the label before its end, so split the label there
+            if label_len < node.label.len() {
+
+                let tail = CompressedNode {
+
+                    label: node.label[label_len..].to_string(),
+                    value: node.value.take(),
+                    left: None,
+                    middle: node.middle.take(),
+                    right: None
+                };
+
+                node.label.truncate(label_len);
+                node.middle = Some(Box::new(tail));
+            }
+
+            if key_len == key.len() {
+
+                node.value.replace(value)
+
+            } else {
+
+                insert_r(&mut node.middle, &key[key_len..], value, order)
+            }
+        }
+    }
+}
+
+
+fn get_r<'a, T, O: LabelOrder>(link: &'a CompressedLink<T>, key: &str, order: &O) -> Option<&'a CompressedNode<T>> {
+
+    match *link {
+
+        None => None,
+
+        Some(ref node) => match order.compare(key.chars().next().unwrap(), node.first_label()) {
+
+            Less => get_r(&node.left, key, order),
+
+            Greater => get_r(&node.right, key, order),
+
+            Equal => {
+
+                let (key_len, label_len) = common_prefix_len(key, &node.label, order);
+
+                if label_len < node.label.len() {
+
+                    None
+
+                } else if key_len == key.len() {
+
+                    Some(node)
+
+                } else {
+
+                    get_r(&node.middle, &key[key_len..], order)
+                }
+            }
+        }
+    }
+}
+
+
+fn get_r_mut<'a, T, O: LabelOrder>(link: &'a mut CompressedLink<T>, key: &str, order: &O) -> Option<&'a mut CompressedNode<T>> {
+
+    match *link {
+
+        None => None,
+
+        Some(ref mut node) => match order.compare(key.chars().next().unwrap(), node.first_label()) {
+
+            Less => get_r_mut(&mut node.left, key, order),
+
+            Greater => get_r_mut(&mut node.right, key, order),
+
+            Equal => {
+
+                let (key_len, label_len) = common_prefix_len(key, &node.label, order);
+
+                if label_len < node.label.len() {
+
+                    None
+
+                } else if key_len == key.len() {
+
+                    Some(node)
+
+                } else {
+
+                    get_r_mut(&mut node.middle, &key[key_len..], order)
+                }
+            }
+        }
+    }
+}
+
+
+//Restores the compressed shape of a node once a value below it is gone
+fn normalize<T>(link: &mut CompressedLink<T>) {
+
+    let replacement = match *link {
+
+        None => return,
+
+        Some(ref mut node) => {
+
+            if node.value.is_some() {
+
+                return;
+            }
+
+            match node.middle {
+
+                //No key goes through the node any more, so a single sibling can take its place
+                None => match (node.left.is_some(), node.right.is_some()) {
+
+                    (false, false) => None,
+
+                    (true, false) => node.left.take(),
+
+                    (false, true) => node.right.take(),
+
+                    (true, true) => {
+
+                        //Only the first char of the label still orders the siblings
+                        let first_len = node.first_label().len_utf8();
+                        node.label.truncate(first_len);
+
+                        return;
+                    }
+                },
+
+                Some(ref mut child) => {
+
+                    if child.left.is_some() || child.right.is_some() {
+
+                        return;
+                    }
+
+                    node.label.push_str(&child.label);
+                    node.value = child.value.take();
+
+                    let grandchild = child.middle.take();
+                    node.middle = grandchild;
+
+                    return;
+                }
+            }
+        }
+    };
+
+    *link = replacement;
+}
+
+
+fn remove_r<T, O: LabelOrder>(link: &mut CompressedLink<T>, key: &str, order: &O) -> Option<T> {
+
+    let old_value = match *link {
+
+        None => None,
+
+        Some(ref mut node) => match order.compare(key.chars().next().unwrap(), node.first_label()) {
+
+            Less => remove_r(&mut node.left, key, order),
+
+            Greater => remove_r(&mut node.right, key, order),
+
+            Equal => {
+
+                let (key_len, label_len) = common_prefix_len(key, &node.label, order);
+
+                if label_len < node.label.len() {
+
+                    None
+
+                } else if key_len == key.len() {
+
+                    node.value.take()
+
+                } else {
+
+                    remove_r(&mut node.middle, &key[key_len..], order)
+                }
+            }
+        }
+    };
+
+    if old_value.is_some() {
+
+        normalize(link);
+    }
+
+    old_value
+}
+
+
+fn visit_r_mut<'b, T, O, S, C>(link: &mut CompressedLink<T>, search: &S, state: &S::State, order: &O, callback: &mut C)
+where O: LabelOrder, S: Search<'b>, C: FnMut (&mut T, &S::State) {
+
+    if let Some(ref mut node) = *link {
+
+        let first = node.first_label();
+
+        if search.may_go(state, first, Less, order) {
+
+            visit_r_mut(&mut node.left, search, state, order, callback);
+        }
+
+        if let Some(new_state) = search.step_label(state, node.label.chars()) {
+
+            if let Some(ref mut value) = node.value {
+
+                if search.accepts(&new_state) {
+
+                    callback(value, &new_state);
+                }
+            }
+
+            visit_r_mut(&mut node.middle, search, &new_state, order, callback);
+        }
+
+        if search.may_go(state, first, Greater, order) {
+
+            visit_r_mut(&mut node.right, search, state, order, callback);
+        }
+    }
+}
+
+
+impl<T> CompressedTst<T> {
+
+    pub fn new() -> Self {
+
+        CompressedTst::with_order(CodePointOrder)
+    }
+}
+
+
+impl<T, O: LabelOrder> CompressedTst<T, O> {
+
+    pub fn with_order(order: O) -> Self {
+
+        CompressedTst { root: None, count: 0, order }
+    }
+
+
+    pub fn order(&self) -> &O {
+
+        &self.order
+    }
+
+
+    fn root_node(&self) -> Option<&CompressedNode<T>> {
+
+        self.root.as_deref()
+    }
+
+
+    fn visit_mut<'b, S, C>(&mut self, search: S, state: Option<S::State>, mut callback: C)
+    where S: Search<'b>, C: FnMut (&mut T, &S::State) {
+
+        if let Some(state) = state {
+
+            visit_r_mut(&mut self.root, &search, &state, &self.order, &mut callback);
+        }
+    }
+
+
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        if key.is_empty() {
+
+            return Some(value);
+        }
+
+        let old_value = insert_r(&mut self.root, key, value, &self.order);
+
+        if old_value.is_none() {
+
+            self.count += 1;
+        }
+
+        old_value
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        if key.is_empty() {
+
+            return None;
+        }
+
+        get_r(&self.root, key, &self.order).and_then(|node| node.value.as_ref())
+    }
+
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+
+        if key.is_empty() {
+
+            return None;
+        }
+
+        get_r_mut(&mut self.root, key, &self.order).and_then(|node| node.value.as_mut())
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        if key.is_empty() {
+
+            return None;
+        }
+
+        let old_value = remove_r(&mut self.root, key, &self.order);
+
+        if old_value.is_some() {
+
+            self.count -= 1;
+        }
+
+        old_value
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.count
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    /// Same statistics as `Tst::stat`, where each node counts once whatever the length of its
+    /// label, and the total size includes the labels.
+    pub fn stat(&self) -> Stats {
+
+        let empty_stats: Stats = Default::default();
+
+        let mut stats = walk::stat_r(empty_stats, self.root_node(), 0, 0, 0);
+
+        stats.bytes.node = mem::size_of::<CompressedNode<T>>();
+        stats.bytes.total += mem::size_of::<CompressedTst<T, O>>()+stats.count.nodes*stats.bytes.node;
+
+        stats
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.root = None;
+        self.count = 0;
+    }
+
+
+    gen_search_api!(CompressedTstIterator);
+    gen_search_api_mut!();
+}
+
+
+impl<T> Default for CompressedTst<T> {
+
+    fn default() -> Self {
+
+        CompressedTst::new()
+    }
+}
+
+
+impl<'a, T, O: LabelOrder> IntoIterator for &'a CompressedTst<T, O> {
+
+    type Item = &'a T;
+    type IntoIter = CompressedTstIterator<'a, 'static, T, O>;
+
+    fn into_iter(self) -> Self::IntoIter {
+
+        self.iter()
+    }
+}
+
+
+/// Iterator of all the searches of a `CompressedTst`, with the same methods as the iterators of
+/// `Tst`. The keys it gives back are made of whole labels.
+pub type CompressedTstIterator<'a, 'b, T, O = CodePointOrder, S = Query> = Walk<'a, 'b, &'a CompressedNode<T>, O, S>;
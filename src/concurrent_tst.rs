@@ -0,0 +1,172 @@
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use Tst;
+
+
+/// A map shared between threads, split into shards by the first char of the keys, each behind
+/// its own lock.
+///
+/// Writers on keys of different shards do not wait for each other, and readers of a shard only
+/// wait for its writers. All methods take `&self`, so the map is shared with an `Arc`. As values
+/// cannot be borrowed out of a lock, `get` returns a clone of them and `get_with` gives them to a
+/// closure.
+pub struct ConcurrentTst<T> {
+
+    //Shard `i` holds the keys beginning with a char in `boundaries[i-1]..boundaries[i]`
+    boundaries: Vec<char>,
+    shards: Vec<RwLock<Tst<T>>>
+}
+
+
+impl<T> ConcurrentTst<T> {
+
+    /// Splits the keys into 8 shards: one for digits and other chars below 'A', two for the
+    /// uppercase Latin letters, four for the lowercase ones, and the last one for non-ASCII chars.
+    pub fn new() -> Self {
+
+        ConcurrentTst::with_boundaries(&['A', 'N', 'a', 'h', 'o', 'u', '\u{80}'])
+    }
+
+
+    /// One shard for the keys beginning below the first boundary, then one for each boundary up
+    /// to the next one.
+    pub fn with_boundaries(boundaries: &[char]) -> Self {
+
+        let mut boundaries = boundaries.to_vec();
+
+        boundaries.sort();
+        boundaries.dedup();
+
+        let shards = (0..boundaries.len()+1).map(|_| RwLock::new(Tst::new())).collect();
+
+        ConcurrentTst { boundaries, shards }
+    }
+
+
+    pub fn shard_count(&self) -> usize {
+
+        self.shards.len()
+    }
+
+
+    //Shard of the keys beginning like `key`, the empty key going to the first one
+    fn shard_index(&self, key: &str) -> usize {
+
+        match key.chars().next() {
+
+            None => 0,
+
+            Some(first) => self.boundaries.partition_point(|&b| b <= first)
+        }
+    }
+
+
+    //A poisoned lock means a writer panicked, which is passed on to the caller
+    fn read<'a>(&'a self, index: usize) -> RwLockReadGuard<'a, Tst<T>> {
+
+        self.shards[index].read().unwrap()
+    }
+
+
+    fn write<'a>(&'a self, index: usize) -> RwLockWriteGuard<'a, Tst<T>> {
+
+        self.shards[index].write().unwrap()
+    }
+
+
+    pub fn insert(&self, key: &str, value: T) -> Option<T> {
+
+        self.write(self.shard_index(key)).insert(key, value)
+    }
+
+
+    /// Calls `f` with the value of `key`, while its shard is locked for reading.
+    pub fn get_with<R, F>(&self, key: &str, f: F) -> Option<R>
+    where F: FnOnce (&T) -> R {
+
+        self.read(self.shard_index(key)).get(key).map(f)
+    }
+
+
+    pub fn remove(&self, key: &str) -> Option<T> {
+
+        self.write(self.shard_index(key)).remove(key)
+    }
+
+
+    /// Sum of the lengths of the shards, each read in turn, so that concurrent writes may or
+    /// may not be counted.
+    pub fn len(&self) -> usize {
+
+        (0..self.shards.len()).map(|i| self.read(i).len()).sum()
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    pub fn clear(&self) {
+
+        for i in 0..self.shards.len() {
+
+            self.write(i).clear();
+        }
+    }
+
+
+    /// Calls `callback` with each key beginning with `prefix` (but not `prefix` itself, as with
+    /// `Tst::iter_complete`) and its value, in key order. Only the shard of the first char of
+    /// `prefix` is locked, or each shard in turn for an empty prefix.
+    ///
+    /// The shard stays locked for reading while `callback` runs, so the callback must not write
+    /// to this map, which would deadlock : `complete` gives the entries to update instead.
+    pub fn visit_complete<C>(&self, prefix: &str, mut callback: C)
+    where C: FnMut (&str, &T) {
+
+        let shards = if prefix.is_empty() { 0..self.shards.len() } else { let i = self.shard_index(prefix); i..i+1 };
+
+        for i in shards {
+
+            let shard = self.read(i);
+            let mut it = shard.iter_complete(prefix);
+
+            while let Some(value) = it.next() {
+
+                callback(&it.current_key(), value);
+            }
+        }
+    }
+}
+
+
+impl<T> Default for ConcurrentTst<T> {
+
+    fn default() -> Self {
+
+        ConcurrentTst::new()
+    }
+}
+
+
+impl<T: Clone> ConcurrentTst<T> {
+
+    pub fn get(&self, key: &str) -> Option<T> {
+
+        self.get_with(key, T::clone)
+    }
+
+
+    /// Keys beginning with `prefix`, with a clone of their values, in key order. Locks as
+    /// `visit_complete` does.
+    pub fn complete(&self, prefix: &str) -> Vec<(String, T)> {
+
+        let mut found = Vec::new();
+
+        self.visit_complete(prefix, |key, value| found.push((key.to_string(), value.clone())));
+
+        found
+    }
+}
@@ -0,0 +1,196 @@
+use std::io;
+use std::collections::HashMap;
+
+use Tst;
+use LabelOrder;
+use Balancing;
+use frozen_tst::{FrozenTst, FrozenTstIterator, write_layout, check_nodes, to_u32, NIL};
+
+
+//Label, whether a key ends there, then left, middle and right children
+type NodeKey = (char, bool, u32, u32, u32);
+
+
+//Builds the sibling tree of the sorted keys (all longer than `depth`, and sharing their first
+//`depth` chars) with the median label as root, as `Tst::build_balanced` does. Identical subtrees
+//get the same id, given in post-order, so that children have lower ids than their parents
+fn minimize_r(keys: &[Vec<char>], depth: usize, ids: &mut HashMap<NodeKey, u32>, nodes: &mut Vec<NodeKey>) -> io::Result<u32> {
+
+    if keys.is_empty() {
+
+        return Ok(NIL);
+    }
+
+    let starts: Vec<usize> = (0..keys.len()).filter(|&i| i == 0 || keys[i][depth] != keys[i-1][depth]).collect();
+
+    let mid = starts.len() / 2;
+    let end = if mid+1 < starts.len() { starts[mid+1] } else { keys.len() };
+
+    let group = &keys[starts[mid]..end];
+    let ends_here = group[0].len() == depth+1;
+
+    let left = minimize_r(&keys[..starts[mid]], depth, ids, nodes)?;
+    let middle = minimize_r(if ends_here { &group[1..] } else { group }, depth+1, ids, nodes)?;
+    let right = minimize_r(&keys[end..], depth, ids, nodes)?;
+
+    let node = (group[0][depth], ends_here, left, middle, right);
+
+    if let Some(&id) = ids.get(&node) {
+
+        return Ok(id);
+    }
+
+    let id = to_u32(nodes.len())?;
+
+    ids.insert(node, id);
+    nodes.push(node);
+
+    Ok(id)
+}
+
+
+impl<T, O: LabelOrder, B: Balancing> Tst<T, O, B> {
+
+    /// Builds the minimal graph of the keys of the tree, without their values, where identical
+    /// subtrees (e.g. for common suffixes) are stored once. The bytes are read by `FrozenSet`.
+    ///
+    /// Sibling trees are rebuilt balanced, so that the result only depends on the keys.
+    pub fn minimize(&self) -> io::Result<Vec<u8>> {
+
+        let mut keys: Vec<Vec<char>> = Vec::with_capacity(self.len());
+        let mut it = self.iter();
+
+        while it.next().is_some() {
+
+            keys.push(it.current_key().chars().collect());
+        }
+
+        //Frozen queries compare labels by code point
+        keys.sort();
+
+        let mut ids = HashMap::new();
+        let mut nodes = Vec::new();
+
+        let root = minimize_r(&keys, 0, &mut ids, &mut nodes)?;
+
+        //Renumbered from the root down, as the layout wants children after their parents
+        let n = nodes.len() as u32;
+        let index = |id: u32| if id == NIL { NIL } else { n - 1 - id };
+
+        let layout: Vec<[u32; 5]> = nodes.iter().rev()
+            .map(|&(label, ends_here, left, middle, right)| [label as u32, index(left), index(middle), index(right), if ends_here { 0 } else { NIL }])
+            .collect();
+
+        //Every key shares the same empty value
+        let values: &[u8] = if keys.is_empty() { &[] } else { &[0; 4] };
+
+        let mut bytes = Vec::new();
+
+        write_layout(&mut bytes, &layout, keys.len(), index(root), values)?;
+
+        Ok(bytes)
+    }
+}
+
+
+/// A read-only set of keys queried straight from the bytes written by `Tst::minimize`, where
+/// keys sharing a suffix share its nodes.
+#[derive(Clone, Copy)]
+pub struct FrozenSet<'a> {
+
+    tst: FrozenTst<'a>
+}
+
+
+impl<'a> FrozenSet<'a> {
+
+    /// Checks the header of `bytes`, as `FrozenTst::new` does.
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+
+        Ok(FrozenSet { tst: FrozenTst::new(bytes)? })
+    }
+
+
+    /// Checks every node, as `FrozenTst::check` does, except that nodes may have several parents.
+    /// Crafted bytes sharing nodes on purpose may then still make a query take exponential time.
+    pub fn check(&self) -> io::Result<()> {
+
+        check_nodes(&self.tst, true)
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.tst.len()
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    /// Count of nodes of the graph, each shared by all the keys going through it.
+    pub fn node_count(&self) -> usize {
+
+        self.tst.node_count()
+    }
+
+
+    pub fn contains(&self, key: &str) -> bool {
+
+        self.tst.get(key).is_some()
+    }
+
+
+    pub fn iter(&self) -> FrozenSetIterator<'a, 'static> {
+
+        FrozenSetIterator { it: self.tst.iter() }
+    }
+
+
+    pub fn iter_complete<'b>(&self, prefix: &'b str) -> FrozenSetIterator<'a, 'b> {
+
+        FrozenSetIterator { it: self.tst.iter_complete(prefix) }
+    }
+
+
+    pub fn iter_neighbor<'b>(&self, key: &'b str, range: usize) -> FrozenSetIterator<'a, 'b> {
+
+        FrozenSetIterator { it: self.tst.iter_neighbor(key, range) }
+    }
+
+
+    pub fn iter_crossword<'b>(&self, key: &'b str, joker: char) -> FrozenSetIterator<'a, 'b> {
+
+        FrozenSetIterator { it: self.tst.iter_crossword(key, joker) }
+    }
+}
+
+
+/// Iterator of all the searches of a `FrozenSet`, giving the keys found.
+pub struct FrozenSetIterator<'a, 'b> {
+
+    it: FrozenTstIterator<'a, 'b>
+}
+
+
+impl<'a, 'b> Iterator for FrozenSetIterator<'a, 'b> {
+
+    type Item = String;
+
+    fn next(&mut self) -> Option<String> {
+
+        self.it.next().map(|_| self.it.current_key())
+    }
+}
+
+
+impl<'a, 'b> DoubleEndedIterator for FrozenSetIterator<'a, 'b> {
+
+    fn next_back(&mut self) -> Option<String> {
+
+        self.it.next_back().map(|_| self.it.current_key_back())
+    }
+}
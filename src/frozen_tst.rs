@@ -0,0 +1,501 @@
+use std::io::{self, Write, ErrorKind};
+use std::cmp::Ordering::Less;
+use std::cmp::Ordering::Equal;
+use std::cmp::Ordering::Greater;
+use std::char;
+use std::option;
+
+use Tst;
+use Node;
+use Link;
+use LabelOrder;
+use Balancing;
+use binary::Codec;
+use CodePointOrder;
+use query::{Query, Cursor};
+use walk::{Walk, WalkNode};
+
+
+const MAGIC: &[u8; 4] = b"TSTF";
+const VERSION: u8 = 1;
+
+const HEADER_LEN: usize = 24;
+const NODE_LEN: usize = 20;
+
+//Index of a missing node, or offset of a missing value
+pub const NIL: u32 = u32::MAX;
+
+
+fn invalid_data(message: &str) -> io::Error {
+
+    io::Error::new(ErrorKind::InvalidData, message.to_string())
+}
+
+
+fn read_u32(bytes: &[u8], offset: usize) -> u32 {
+
+    let mut word = [0u8; 4];
+
+    word.copy_from_slice(&bytes[offset..offset+4]);
+
+    u32::from_le_bytes(word)
+}
+
+
+pub fn to_u32(n: usize) -> io::Result<u32> {
+
+    if n >= NIL as usize {
+
+        return Err(io::Error::new(ErrorKind::InvalidInput, "tree too large to freeze"));
+    }
+
+    Ok(n as u32)
+}
+
+
+/// A read-only map queried straight from its bytes, such as a memory-mapped file, without
+/// loading it first. Bytes are written by `Tst::write_frozen`.
+///
+/// Values are kept as written by their `Codec`, and returned as byte slices which `Codec::decode`
+/// turns back into values. `new` only checks the header, so that opening a large file costs
+/// nothing, and queries never fail nor loop, even on corrupted bytes, which `check` detects.
+/// Bytes where nodes have several parents may still make a query take time exponential in the
+/// count of nodes, so `check` rejects them too.
+///
+/// The layout is a 24 bytes header, then nodes of 20 bytes, then values. All numbers are `u32`
+/// in little endian. The header is the magic "TSTF", the version (1) and three zero bytes, then
+/// the count of nodes, the count of values, the index of the root node and the length of the
+/// values. A node is its label as a code point, the indices of its left, middle and right
+/// children and the offset of its value, with `u32::MAX` for none. Children come after their
+/// parent, and only the nodes of a `FrozenSet` may have several parents. A value is its length
+/// followed by its bytes.
+#[derive(Clone, Copy)]
+pub struct FrozenTst<'a> {
+
+    nodes: &'a [u8],
+    values: &'a [u8],
+    root: u32,
+    count: usize
+}
+
+
+#[derive(Clone, Copy)]
+struct FrozenNode<'a> {
+
+    label: char,
+    left: u32,
+    middle: u32,
+    right: u32,
+    value: Option<&'a [u8]>
+}
+
+
+impl<'a> FrozenTst<'a> {
+
+    /// Checks the header of `bytes` and gives a map over them, in constant time whatever their
+    /// length. Fails with `ErrorKind::InvalidData` on a wrong magic header, an unknown version or
+    /// a length which does not match the header.
+    pub fn new(bytes: &'a [u8]) -> io::Result<Self> {
+
+        if bytes.len() < HEADER_LEN || &bytes[..4] != MAGIC {
+
+            return Err(invalid_data("not a frozen ternary tree"));
+        }
+
+        if bytes[4] != VERSION {
+
+            return Err(invalid_data(&format!("unsupported version {}", bytes[4])));
+        }
+
+        let node_count = read_u32(bytes, 8) as usize;
+        let count = read_u32(bytes, 12) as usize;
+        let root = read_u32(bytes, 16);
+        let values_len = read_u32(bytes, 20) as usize;
+
+        let nodes_len = node_count.checked_mul(NODE_LEN).ok_or_else(|| invalid_data("too many nodes"))?;
+
+        if bytes.len() != HEADER_LEN + nodes_len + values_len {
+
+            return Err(invalid_data("length does not match the header"));
+        }
+
+        let nodes = &bytes[HEADER_LEN..HEADER_LEN+nodes_len];
+        let values = &bytes[HEADER_LEN+nodes_len..];
+
+        if (root == NIL) != (node_count == 0) || (root != NIL && root as usize >= node_count) {
+
+            return Err(invalid_data("invalid root node"));
+        }
+
+        Ok(FrozenTst { nodes, values, root, count })
+    }
+
+
+    /// Checks every node, which `new` leaves to the queries reading them : there, a bad link or
+    /// value reads as missing, and a bad label as U+FFFD. Also checks that each node has a single
+    /// parent. Fails with `ErrorKind::InvalidData`.
+    pub fn check(&self) -> io::Result<()> {
+
+        check_nodes(self, false)
+    }
+
+
+    //Links only go forward, so that no query can loop
+    fn is_link(&self, index: u32, child: u32) -> bool {
+
+        child > index && (child as usize) < self.node_count()
+    }
+
+
+    fn value(&self, offset: u32) -> Option<&'a [u8]> {
+
+        let start = (offset as usize).checked_add(4)?;
+
+        if start > self.values.len() {
+
+            return None;
+        }
+
+        let len = read_u32(self.values, offset as usize) as usize;
+
+        self.values.get(start..start.checked_add(len)?)
+    }
+
+
+    fn node(&self, index: u32) -> FrozenNode<'a> {
+
+        let offset = index as usize * NODE_LEN;
+        let nodes = self.nodes;
+
+        let link = |at: usize| {
+
+            let child = read_u32(nodes, offset + at);
+
+            if self.is_link(index, child) { child } else { NIL }
+        };
+
+        let value = match read_u32(nodes, offset + 16) {
+
+            NIL => None,
+
+            value => self.value(value)
+        };
+
+        FrozenNode {
+
+            label: char::from_u32(read_u32(nodes, offset)).unwrap_or(char::REPLACEMENT_CHARACTER),
+            left: link(4),
+            middle: link(8),
+            right: link(12),
+            value
+        }
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.count
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    pub fn node_count(&self) -> usize {
+
+        self.nodes.len() / NODE_LEN
+    }
+
+
+    /// Returns the bytes of the value of `key`, as written by its `Codec`.
+    pub fn get(&self, key: &str) -> Option<&'a [u8]> {
+
+        let mut key_tail = key.chars();
+
+        let mut label = key_tail.next()?;
+
+        let mut index = self.root;
+
+        while index != NIL {
+
+            let node = self.node(index);
+
+            index = match label.cmp(&node.label) {
+
+                Less => node.left,
+
+                Greater => node.right,
+
+                Equal => match key_tail.next() {
+
+                    None => return node.value,
+
+                    Some(next) => {
+
+                        label = next;
+                        node.middle
+                    }
+                }
+            };
+        }
+
+        None
+    }
+
+
+    fn root_node(&self) -> Option<FrozenRef<'a>> {
+
+        FrozenRef::new(*self, self.root)
+    }
+
+
+    pub fn iter(&self) -> FrozenTstIterator<'a, 'static> {
+
+        Walk::new(self.root_node(), Query::All, Some(Cursor::new("", 0)), &CodePointOrder)
+    }
+
+
+    pub fn iter_complete<'b>(&self, prefix: &'b str) -> FrozenTstIterator<'a, 'b> {
+
+        Walk::new(self.root_node(), Query::Complete, Some(Cursor::new(prefix, 0)), &CodePointOrder)
+    }
+
+
+    pub fn iter_neighbor<'b>(&self, key: &'b str, range: usize) -> FrozenTstIterator<'a, 'b> {
+
+        Walk::new(self.root_node(), Query::Neighbor, Some(Cursor::new(key, range)), &CodePointOrder)
+    }
+
+
+    pub fn iter_crossword<'b>(&self, key: &'b str, joker: char) -> FrozenTstIterator<'a, 'b> {
+
+        Walk::new(self.root_node(), Query::Crossword(joker), Some(Cursor::new(key, 0)), &CodePointOrder)
+    }
+}
+
+
+//Node of a frozen tree, as read by the shared walks. A node shared by several parents (as in a
+//`FrozenSet`) is reached once per path, so its keys are told apart by their labels only
+#[derive(Clone, Copy)]
+pub struct FrozenRef<'a> {
+
+    tst: FrozenTst<'a>,
+    index: u32,
+    node: FrozenNode<'a>
+}
+
+
+impl<'a> FrozenRef<'a> {
+
+    fn new(tst: FrozenTst<'a>, index: u32) -> Option<Self> {
+
+        if index == NIL { None } else { Some(FrozenRef { tst, index, node: tst.node(index) }) }
+    }
+}
+
+
+impl<'a> WalkNode<'a> for FrozenRef<'a> {
+
+    type Value = [u8];
+    type Label = option::IntoIter<char>;
+
+    fn label(&self) -> Self::Label {
+
+        Some(self.node.label).into_iter()
+    }
+
+
+    fn value(&self) -> Option<&'a [u8]> {
+
+        self.node.value
+    }
+
+
+    fn left(&self) -> Option<Self> {
+
+        FrozenRef::new(self.tst, self.node.left)
+    }
+
+
+    fn middle(&self) -> Option<Self> {
+
+        FrozenRef::new(self.tst, self.node.middle)
+    }
+
+
+    fn right(&self) -> Option<Self> {
+
+        FrozenRef::new(self.tst, self.node.right)
+    }
+
+
+    fn id(&self) -> usize {
+
+        self.index as usize
+    }
+}
+
+
+/// Iterator of all the searches of a `FrozenTst`, giving the bytes of the values. Keys are
+/// compared by code point.
+pub type FrozenTstIterator<'a, 'b> = Walk<'a, 'b, FrozenRef<'a>, CodePointOrder, Query>;
+
+
+//Checks the nodes of `tst`, which may have several parents if `shared`
+pub fn check_nodes(tst: &FrozenTst, shared: bool) -> io::Result<()> {
+
+    let mut has_parent = vec![false; tst.node_count()];
+
+    if tst.root != NIL {
+
+        has_parent[tst.root as usize] = true;
+    }
+
+    for index in 0..tst.node_count() {
+
+        let offset = index * NODE_LEN;
+
+        if char::from_u32(read_u32(tst.nodes, offset)).is_none() {
+
+            return Err(invalid_data("invalid label"));
+        }
+
+        for link in 1..4 {
+
+            let child = read_u32(tst.nodes, offset + 4*link);
+
+            if child == NIL {
+
+                continue;
+            }
+
+            if !tst.is_link(index as u32, child) {
+
+                return Err(invalid_data("invalid link"));
+            }
+
+            if has_parent[child as usize] && !shared {
+
+                return Err(invalid_data("node with several parents"));
+            }
+
+            has_parent[child as usize] = true;
+        }
+
+        let value = read_u32(tst.nodes, offset + 16);
+
+        if value != NIL && tst.value(value).is_none() {
+
+            return Err(invalid_data("invalid value offset"));
+        }
+    }
+
+    Ok(())
+}
+
+
+//Writes nodes given as label, left, middle, right and value offset, in the layout read by `FrozenTst`
+pub fn write_layout<W: Write>(writer: &mut W, nodes: &[[u32; 5]], count: usize, root: u32, values: &[u8]) -> io::Result<()> {
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION, 0, 0, 0])?;
+
+    for &n in [to_u32(nodes.len())?, to_u32(count)?, root, to_u32(values.len())?].iter() {
+
+        writer.write_all(&n.to_le_bytes())?;
+    }
+
+    for node in nodes.iter() {
+
+        for &n in node.iter() {
+
+            writer.write_all(&n.to_le_bytes())?;
+        }
+    }
+
+    writer.write_all(values)
+}
+
+
+//Nodes of a sibling tree, in the order of the tree
+fn siblings_r<'a, T>(link: &'a Link<T>, siblings: &mut Vec<&'a Node<T>>) {
+
+    if let Some(ref node) = *link {
+
+        siblings_r(&node.left, siblings);
+        siblings.push(node);
+        siblings_r(&node.right, siblings);
+    }
+}
+
+
+//Frozen queries compare labels by code point, so each sibling tree is rebuilt balanced in this
+//order, whatever the order of the tree
+fn freeze_r<T: Codec>(link: &Link<T>, nodes: &mut Vec<[u32; 5]>, values: &mut Vec<u8>) -> io::Result<u32> {
+
+    let mut siblings = Vec::new();
+
+    siblings_r(link, &mut siblings);
+    siblings.sort_by_key(|node| node.label);
+
+    freeze_siblings_r(&siblings, nodes, values)
+}
+
+
+//Numbers the nodes in pre-order, so that children come after their parent, and encodes the values
+fn freeze_siblings_r<T: Codec>(siblings: &[&Node<T>], nodes: &mut Vec<[u32; 5]>, values: &mut Vec<u8>) -> io::Result<u32> {
+
+    if siblings.is_empty() {
+
+        return Ok(NIL);
+    }
+
+    let median = siblings.len() / 2;
+    let node = siblings[median];
+
+    let index = to_u32(nodes.len())?;
+
+    nodes.push([node.label as u32, NIL, NIL, NIL, NIL]);
+
+    if let Some(ref value) = node.value {
+
+        let offset = values.len();
+
+        values.extend_from_slice(&[0; 4]);
+        value.encode(values)?;
+
+        let len = to_u32(values.len() - offset - 4)?;
+
+        values[offset..offset+4].copy_from_slice(&len.to_le_bytes());
+        nodes[index as usize][4] = to_u32(offset)?;
+    }
+
+    let left = freeze_siblings_r(&siblings[..median], nodes, values)?;
+    let middle = freeze_r(&node.middle, nodes, values)?;
+    let right = freeze_siblings_r(&siblings[median+1..], nodes, values)?;
+
+    nodes[index as usize][1] = left;
+    nodes[index as usize][2] = middle;
+    nodes[index as usize][3] = right;
+
+    Ok(index)
+}
+
+
+impl<T: Codec, O: LabelOrder, B: Balancing> Tst<T, O, B> {
+
+    /// Writes the tree in the layout read by `FrozenTst`, where keys are ordered by code point
+    /// whatever the order of the tree, and sibling trees are balanced.
+    pub fn write_frozen<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+
+        let mut nodes = Vec::new();
+        let mut values = Vec::new();
+
+        let root = freeze_r(&self.root, &mut nodes, &mut values)?;
+
+        write_layout(writer, &nodes, self.count, root, &values)
+    }
+}
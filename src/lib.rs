@@ -17,17 +17,55 @@ The following tree is the TST we get after inserting the following keys in order
 
 A checked box "☑" denotes a node  which stores a value (it corresponds to the last character of a key). An empty box "☐" means that the node has no value.
 
-A TST can be used as a map, but it allows more flexible ways to retrieve values associated with keys. This crate provides four ways to iterate over the values of a TST :
+A TST can be used as a map, but it allows more flexible ways to retrieve values associated with keys. This crate provides eight ways to iterate over the values of a TST :
 
 * get all values (same as a regular map), with `visit_values` or `iter`
 * get all values whose keys begin with some prefix (i.e. _complete_ some prefix), with `visit_complete_values` or `iter_complete`
 * get all values whose keys are _close_ to some string ([Hamming distance](https://en.wikipedia.org/wiki/Hamming_distance)), with `visit_neighbor_values` or `iter_neighbor`
+* get all values whose keys begin with something _close_ to some prefix ([edit distance](https://en.wikipedia.org/wiki/Damerau%E2%80%93Levenshtein_distance), for typo-tolerant completion), with `iter_fuzzy_complete`
 * get all values whose keys match a string with some joker (e.g. "a?c"), with `visit_crossword_values` or `iter_crossword`
+* get all values whose keys match a string with some joker, except at a few positions, with `visit_crossword_neighbor_values` or `iter_crossword_neighbor`
+* get all values whose keys are anagrams of some letters (e.g. "tca" for "act" and "cat"), with `visit_anagram_values` or `iter_anagrams`
+* get all values whose keys can be built from a Scrabble rack, with some blank tiles and an optional crossword pattern, with `visit_rack_values` or `iter_rack` (and their `_crossword` versions)
 
 Visit methods are recursive and apply a closure to found values. They exist in immutable and mutable version (i.e. `visit_neighbor_values_mut`). But once a value is found (based on its key), they offer no way to know what the actual key is.
 
 Iterators, on the other hand, save their context in a `Vec` and only work on immutable trees. However they are double ended, and support `next` and `next_back` methods to walk the tree from both ends. Moreover, once a value is found, they offer the `current_key` and `current_key_back` methods to retrieve the key associated with the last value.
 
+Node labels are compared by Unicode code point, so values come out in code point order. A TST created with `Tst::with_order` uses another `LabelOrder` instead (any `Fn(char, char) -> Ordering` closure will do), and every insertion, lookup, visit and iteration then follows this collation.
+
+To find keys containing some substring (not only starting with it), a `SubstringIndex` stores every suffix of its keys in a TST, and turns a substring query into a prefix completion. Likewise, a `SuffixTst` keeps its keys reversed in a second TST, so that `iter_suffix` finds the keys ending with some suffix.
+
+To search names by their sound, a `PhoneticTst` stores its keys under phonetic codes (`Soundex`, `FrenchPhonetic` or any `PhoneticEncoder`), so that `find_sound_alike` finds "Smyth" for "Smith", and `complete_sound_alike` completes a prefix by its sound.
+
+When values have a weight, a `ScoredTst` caches the best weight of each subtree, and `top_k_complete` returns the best scored completions of a prefix without walking all of them.
+
+The shape of a TST depends on the order of insertions, and inserting sorted keys one by one gives long chains of left or right siblings. `Tst::from_sorted_iter` and `Tst::build_balanced` build the whole tree at once instead, with the median label at the root of each sibling tree. A tree which went lopsided over time can be rebalanced in place with `optimize`, and `balance_report` tells when it is worth it. A tree created with `Tst::new_self_balancing` (or turned into one with `into_self_balancing`) keeps its sibling trees balanced on every insert and remove instead, so that even keys inserted in sorted order stay quick to find.
+
+A tree whose values implement `Codec` can be saved with `write_to` and loaded back with `read_from`, in a versioned binary format which keeps the node structure, which is much quicker than inserting the keys again.
+
+For large dictionaries shared between processes, `write_frozen` writes a flat layout which a `FrozenTst` queries straight from a byte slice (e.g. a memory-mapped file), with `get`, completion, neighbor and crossword searches, without loading anything.
+
+For word lists, `minimize` shares identical subtrees (such as common suffixes) between keys, and a `FrozenSet` answers the same searches from the resulting bytes in a fraction of the memory.
+
+Plain text dictionaries, with one `key<TAB>value` or one word per line, are read with `load_lines` and written with `dump_lines`, which take a parser and a formatter for the values.
+
+A `PersistentTst` shares its nodes between versions : `snapshot` is O(1), and a snapshot keeps answering every search of `Tst` (with the same label orders) as it was taken while the map is updated.
+
+When reads are much more frequent than writes, a `SnapshotPublisher` applies updates (one by one, or in batches with `batch`) to a `PersistentTst` and publishes each result as a new version, which `SnapshotReader` handles on other threads pick up with a single atomic load, without waiting for the writer.
+
+A `ConcurrentTst` is shared between threads behind an `Arc`: its keys are split into shards by their first char, each with its own lock, so that `insert`, `get` and `remove` take `&self` and completion searches only lock the shard of their prefix.
+
+With the `serde` feature, a `Tst` implements `Serialize` and `Deserialize` as a map of keys to values, and the `RawNodes` wrapper serializes its node structure instead, for faster loading.
+
+Long and sparse keys (such as URLs) take one node per char in a TST. A `CompressedTst` stores chains of single middle nodes as one node with a multi-char label, splitting and merging labels as keys come and go, and offers every search of `Tst` (with the same label orders).
+
+For very large dictionaries, an `ArenaTst` keeps its nodes in a single `Vec` linked by `u32` indices (with a free list for removed nodes) instead of boxing each of them, offers every search of `Tst` (with the same label orders), and its `stat` reports how the arena is used.
+
+Neighbor searches charge 1 for any differing char. Their weighted versions (`visit_weighted_neighbor_values`, `iter_weighted_neighbor` and `iter_weighted_fuzzy_complete`) take a `SubstitutionCost` instead, such as a closure, a `CostMatrix` or a `KeyboardLayout` (where typing a key next to the right one costs less), and a fractional budget.
+
+For command palettes, `find_subsequence` and `top_k_subsequence` find the keys in which the chars of some query appear in order (as fzf does), and score them. Finally, `find_in_grid` finds the keys hidden in a Boggle grid, walking the grid and the tree middle links together.
+
 The following lines may give you a foretaste of this crate and TSTs
 
 ```
@@ -89,28 +127,156 @@ assert_eq!(map.get("cca"), Some(&"xxx"));
 
 #![forbid(unsafe_code)]
 
+extern crate arc_swap;
+
+#[cfg(feature = "serde")]
+extern crate serde;
+
 use std::str::Chars;
 use std::mem::replace;
+use std::cmp::Ordering;
 use std::cmp::Ordering::Less;
 use std::cmp::Ordering::Equal;
 use std::cmp::Ordering::Greater;
 use std::io::Write;
-use std::ptr;
+use std::marker::PhantomData;
 use std::fmt;
 use std::mem;
+use std::option;
+
+#[macro_use]
+mod walk;
+mod substring_index;
+mod suffix_tst;
+mod scored_tst;
+mod subsequence;
+mod substitution_cost;
+mod phonetic_tst;
+mod compressed_tst;
+mod query;
+mod arena_tst;
+mod balance;
+mod binary;
+mod frozen_tst;
+mod frozen_set;
+mod lines;
+mod persistent_tst;
+mod concurrent_tst;
+mod snapshot_publisher;
+#[cfg(feature = "serde")]
+mod serde_support;
+
+pub use substring_index::SubstringIndex;
+pub use suffix_tst::{SuffixTst, TstSuffixIterator};
+pub use scored_tst::ScoredTst;
+pub use substitution_cost::{SubstitutionCost, UnitCost, CostMatrix, KeyboardLayout};
+pub use phonetic_tst::{PhoneticTst, PhoneticEncoder, Soundex, FrenchPhonetic};
+pub use compressed_tst::{CompressedTst, CompressedTstIterator};
+pub use arena_tst::{ArenaTst, ArenaTstIterator};
+pub use balance::{NotSortedError, BalanceReport};
+pub use binary::Codec;
+pub use frozen_tst::{FrozenTst, FrozenTstIterator};
+pub use frozen_set::{FrozenSet, FrozenSetIterator};
+pub use lines::{Duplicates, LoadLinesError};
+pub use persistent_tst::{PersistentTst, PersistentTstIterator};
+pub use concurrent_tst::ConcurrentTst;
+pub use snapshot_publisher::{SnapshotPublisher, SnapshotReader};
+#[cfg(feature = "serde")]
+pub use serde_support::RawNodes;
+
+use balance::{lift_child, unlink_r};
+use query::{Search, Query};
+use walk::{Walk, WalkNode};
+
+
+pub struct Tst<T, O = CodePointOrder, B = ManualBalancing> {
+
+    root: Link<T>,
+    count: usize,
+    order: O,
+    balancing: PhantomData<B>
+}
 
 
-pub struct Tst<T> {
+/// Order used to place node labels in the left/right sibling trees.
+///
+/// It must be a total order where only identical chars compare `Equal`, otherwise
+/// distinct keys would end up sharing the same nodes.
+pub trait LabelOrder {
 
-    root: Link<T>,
-    count: usize
+    fn compare(&self, a: char, b: char) -> Ordering;
+}
+
+
+/// Default order of a `Tst`, which compares labels by Unicode code point.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct CodePointOrder;
+
+
+impl LabelOrder for CodePointOrder {
+
+    fn compare(&self, a: char, b: char) -> Ordering {
+
+        a.cmp(&b)
+    }
+}
+
+
+impl<F> LabelOrder for F where F: Fn(char, char) -> Ordering {
+
+    fn compare(&self, a: char, b: char) -> Ordering {
+
+        self(a, b)
+    }
+}
+
+
+/// How the left/right sibling trees of a `Tst` are shaped as keys are inserted and removed,
+/// whatever the `LabelOrder` of their labels.
+pub trait Balancing {
+
+    fn is_self_balancing() -> bool;
+}
+
+
+/// Default balancing of a `Tst`, whose shape follows the order of insertions until it is
+/// rebuilt with `optimize`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct ManualBalancing;
+
+
+impl Balancing for ManualBalancing {
+
+    fn is_self_balancing() -> bool {
+
+        false
+    }
+}
+
+
+/// Keeps every left/right sibling tree balanced on each `insert` and `remove`, whatever the
+/// order of the keys, see `Tst::into_self_balancing`.
+///
+/// Sibling trees are treaps whose priorities are hashes of the labels, so their shape only
+/// depends on the labels they hold, and their height stays logarithmic in the alphabet size.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct SelfBalancing;
+
+
+impl Balancing for SelfBalancing {
+
+    fn is_self_balancing() -> bool {
+
+        true
+    }
 }
 
 
 type Link<T> = Option<Box<Node<T>>>;
 
 
-struct Node<T> {
+/// A node of a `Tst`, as walked by its iterators.
+pub struct Node<T> {
 
     label: char,
     value: Option<T>,
@@ -150,13 +316,55 @@ impl<T> fmt::Debug for Node<T> {
 }
 
 
-fn insert_r<T>(link: &mut Link<T>, label: char, mut key_tail: Chars, value: T) -> Option<T> {
+impl<'a, T> WalkNode<'a> for &'a Node<T> {
+
+    type Value = T;
+    type Label = option::IntoIter<char>;
+
+    fn label(&self) -> Self::Label {
+
+        Some(self.label).into_iter()
+    }
+
+
+    fn value(&self) -> Option<&'a T> {
+
+        self.value.as_ref()
+    }
+
+
+    fn left(&self) -> Option<Self> {
+
+        self.left.as_deref()
+    }
+
+
+    fn middle(&self) -> Option<Self> {
+
+        self.middle.as_deref()
+    }
+
+
+    fn right(&self) -> Option<Self> {
+
+        self.right.as_deref()
+    }
+
+
+    fn id(&self) -> usize {
+
+        *self as *const Node<T> as usize
+    }
+}
+
+
+fn insert_r<T, O: LabelOrder>(link: &mut Link<T>, label: char, mut key_tail: Chars, value: T, order: &O, balanced: bool) -> Option<T> {
 
-    let choose_branch_and_do_insert = |node: &mut Box<Node<T>>| match label.cmp(&node.label) {
+    let choose_branch_and_do_insert = |node: &mut Box<Node<T>>| match order.compare(label, node.label) {
 
-        Less => insert_r(&mut node.left, label, key_tail, value),
+        Less => insert_r(&mut node.left, label, key_tail, value, order, balanced),
 
-        Greater => insert_r(&mut node.right, label, key_tail, value),
+        Greater => insert_r(&mut node.right, label, key_tail, value, order, balanced),
 
         Equal => {
 
@@ -166,12 +374,12 @@ fn insert_r<T>(link: &mut Link<T>, label: char, mut key_tail: Chars, value: T) -
 
                 None => replace(&mut node.value, Some(value)),
 
-                Some(label) => insert_r(&mut node.middle, label, key_tail, value)
+                Some(label) => insert_r(&mut node.middle, label, key_tail, value, order, balanced)
             }
         }
     };
 
-    match link {
+    let old_value = match link {
 
         None => {
 
@@ -185,19 +393,27 @@ fn insert_r<T>(link: &mut Link<T>, label: char, mut key_tail: Chars, value: T) -
         }
 
         Some(ref mut node) => choose_branch_and_do_insert(node)
+    };
+
+    //A new node goes up its sibling tree as long as its priority is higher than its parent one
+    if balanced {
+
+        lift_child(link);
     }
+
+    old_value
 }
 
 
-fn get_r<'a, T>(link: &'a Link<T>, label: char, key_tail: &mut Chars) -> Option<&'a T> {
+fn get_r<'a, T, O: LabelOrder>(link: &'a Link<T>, label: char, key_tail: &mut Chars, order: &O) -> Option<&'a T> {
 
     match *link {
 
         None => None,
 
-        Some(ref node) => match label.cmp(&node.label) {
+        Some(ref node) => match order.compare(label, node.label) {
 
-            Less => get_r(&node.left, label, key_tail),
+            Less => get_r(&node.left, label, key_tail, order),
 
             Equal => {
 
@@ -212,25 +428,25 @@ fn get_r<'a, T>(link: &'a Link<T>, label: char, key_tail: &mut Chars) -> Option<
                         Some(ref value) => Some(value)
                     }
 
-                    Some(label) => get_r(&node.middle, label, key_tail)
+                    Some(label) => get_r(&node.middle, label, key_tail, order)
                 }
             },
 
-            Greater => get_r(&node.right, label, key_tail),
+            Greater => get_r(&node.right, label, key_tail, order),
         }
     }
 }
 
 
-fn get_r_mut<'a, T>(link: &'a mut Link<T>, label: char, key_tail: &mut Chars) -> Option<&'a mut T> {
+fn get_r_mut<'a, T, O: LabelOrder>(link: &'a mut Link<T>, label: char, key_tail: &mut Chars, order: &O) -> Option<&'a mut T> {
 
     match *link {
 
         None => None,
 
-        Some(ref mut node) => match label.cmp(&node.label) {
+        Some(ref mut node) => match order.compare(label, node.label) {
 
-            Less => get_r_mut(&mut node.left, label, key_tail),
+            Less => get_r_mut(&mut node.left, label, key_tail, order),
 
             Equal => {
 
@@ -245,27 +461,27 @@ fn get_r_mut<'a, T>(link: &'a mut Link<T>, label: char, key_tail: &mut Chars) ->
                         Some(ref mut value) => Some(value)
                     }
 
-                    Some(label) => get_r_mut(&mut node.middle, label, key_tail)
+                    Some(label) => get_r_mut(&mut node.middle, label, key_tail, order)
                 }
             },
 
-            Greater => get_r_mut(&mut node.right, label, key_tail),
+            Greater => get_r_mut(&mut node.right, label, key_tail, order),
         }
     }
 }
 
 
-fn remove_r<T>(link: &mut Link<T>, label: char, key_tail: &mut Chars) -> (bool, Option<T>) {
+fn remove_r<T, O: LabelOrder>(link: &mut Link<T>, label: char, key_tail: &mut Chars, order: &O, balanced: bool) -> (bool, Option<T>) {
 
-    match *link {
+    let (prune, old_value) = match *link {
 
         None => (false, None),
 
-        Some(ref mut node) => match label.cmp(&node.label) {
+        Some(ref mut node) => match order.compare(label, node.label) {
 
             Less => {
 
-                let (prune, old_value) = remove_r(&mut node.left, label, key_tail);
+                let (prune, old_value) = remove_r(&mut node.left, label, key_tail, order, balanced);
 
                 if prune {
 
@@ -292,7 +508,7 @@ fn remove_r<T>(link: &mut Link<T>, label: char, key_tail: &mut Chars) -> (bool,
 
                     Some(label) => {
 
-                        let (prune, old_value) = remove_r(&mut node.middle, label, key_tail);
+                        let (prune, old_value) = remove_r(&mut node.middle, label, key_tail, order, balanced);
 
                         if prune {
 
@@ -307,7 +523,7 @@ fn remove_r<T>(link: &mut Link<T>, label: char, key_tail: &mut Chars) -> (bool,
 
             Greater => {
 
-                let (prune, old_value) = remove_r(&mut node.right, label, key_tail);
+                let (prune, old_value) = remove_r(&mut node.right, label, key_tail, order, balanced);
 
                 if prune {
 
@@ -318,7 +534,25 @@ fn remove_r<T>(link: &mut Link<T>, label: char, key_tail: &mut Chars) -> (bool,
                 (more_pruning, old_value)
             }
         }
+    };
+
+    //A node left with no value and no middle child is taken out of its sibling tree right away
+    if balanced && !prune {
+
+        let useless = match *link {
+
+            None => false,
+
+            Some(ref node) => node.value.is_none() && node.middle.is_none()
+        };
+
+        if useless {
+
+            unlink_r(link);
+        }
     }
+
+    (prune, old_value)
 }
 
 
@@ -334,6 +568,9 @@ pub struct CountStat { pub nodes:usize, pub values: usize }
 #[derive(Default,PartialEq,Debug)]
 pub struct BytesStat { pub node: usize, pub total: usize }
 
+#[derive(Default,PartialEq,Debug)]
+pub struct ArenaStat { pub slots: usize, pub free: usize, pub capacity: usize }
+
 #[derive(Default,PartialEq,Debug)]
 pub struct Stats {
 
@@ -341,1515 +578,300 @@ pub struct Stats {
     pub key_len: KeyLenStat,
     pub count: CountStat,
     pub bytes: BytesStat,
+    pub arena: ArenaStat,
 }
 
 
-fn stat_r<T>(stats: Stats, link: &Link<T>, matches: usize, sides: usize, depth: usize) -> Stats {
-
-    match *link {
-
-        None => stats,
-
-        Some(ref node) => {
-
-            let mut stats = stat_r(stats, &node.left, matches, sides+1, depth+1);
-
-            stats.count.nodes+=1;
-
-            if node.value.is_some() {
+fn visit_r_mut<'b, T, O, S, C>(link: &mut Link<T>, search: &S, state: &S::State, order: &O, callback: &mut C)
+where O: LabelOrder, S: Search<'b>, C: FnMut (&mut T, &S::State) {
 
-                let matches = matches + 1;
-                let depth = depth + 1;
+    if let Some(ref mut node) = *link {
 
-                while stats.dist.len() <= depth {
+        if search.may_go(state, node.label, Less, order) {
 
-                    stats.dist.push(DistStat { matches: 0, sides: 0, depth: 0 });
-                }
-
-                stats.dist[matches].matches+=1;
-                stats.dist[sides].sides+=1;
-                stats.dist[depth].depth+=1;
+            visit_r_mut(&mut node.left, search, state, order, callback);
+        }
 
-                if stats.key_len.min == 0 || matches < stats.key_len.min {
+        if let Some(new_state) = search.step(state.clone(), node.label) {
 
-                    stats.key_len.min = matches;
-                }
+            if let Some(ref mut value) = node.value {
 
-                if matches > stats.key_len.max {
+                if search.accepts(&new_state) {
 
-                    stats.key_len.max = matches;
+                    callback(value, &new_state);
                 }
-
-                stats.count.values+=1;
             }
 
-            let mut stats = stat_r(stats, &node.middle, matches+1, sides, depth+1);
-            let stats = stat_r(stats, &node.right, matches, sides+1, depth+1);
+            visit_r_mut(&mut node.middle, search, &new_state, order, callback);
+        }
+
+        if search.may_go(state, node.label, Greater, order) {
 
-            stats
+            visit_r_mut(&mut node.right, search, state, order, callback);
         }
     }
 }
 
 
-//TODO - Documenter piège : le préfix à compléter est "passé" et l'éventuelle valeur attachée au préfix n'est par conséquent pas remontée
-fn find_complete_root_r<'a, T>(link: &'a Link<T>, label: char, mut key_tail: Chars) -> &'a Link<T> {
+/// Cells which follow each other in a word found by `find_in_grid`.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Adjacency {
 
-    match *link {
+    Orthogonal,
+    OrthogonalAndDiagonal
+}
 
-        None => &link,
 
-        Some(ref node) => match label.cmp(&node.label) {
+impl Adjacency {
 
-            Less => find_complete_root_r(&node.left, label, key_tail),
+    fn neighbors(&self, grid: &[Vec<char>], row: usize, col: usize) -> Vec<(usize, usize)> {
 
-            Greater => find_complete_root_r(&node.right, label, key_tail),
+        let mut cells = Vec::new();
 
-            Equal => {
+        for dr in -1isize..2 {
 
-                let new_label = key_tail.next();
+            for dc in -1isize..2 {
 
-                match new_label {
+                if (dr == 0 && dc == 0) || (*self == Adjacency::Orthogonal && dr != 0 && dc != 0) {
+
+                    continue;
+                }
+
+                let r = row as isize + dr;
+                let c = col as isize + dc;
 
-                    None => &node.middle,
+                if r >= 0 && (r as usize) < grid.len() && c >= 0 && (c as usize) < grid[r as usize].len() {
 
-                    Some(label) => find_complete_root_r(&node.middle, label, key_tail)
+                    cells.push((r as usize, c as usize));
                 }
             }
         }
+
+        cells
     }
 }
 
 
-fn find_complete_root_r_mut<'a, T>(link: &'a mut Link<T>, label: char, mut key_tail: Chars) -> &'a mut Link<T> {
-
-    match *link {
-
-        None => { link }
-
-        Some(ref mut node) => match label.cmp(&node.label) {
-
-            Less => find_complete_root_r_mut(&mut node.left, label, key_tail),
+impl<T> Tst<T> {
 
-            Greater => find_complete_root_r_mut(&mut node.right, label, key_tail),
+    pub fn new() -> Self {
 
-            Equal => {
+        Tst::with_order(CodePointOrder)
+    }
+}
 
-                let new_label = key_tail.next();
 
-                match new_label {
+impl<T> Tst<T, CodePointOrder, SelfBalancing> {
 
-                    None => &mut node.middle,
+    pub fn new_self_balancing() -> Self {
 
-                    Some(label) => find_complete_root_r_mut(&mut node.middle, label, key_tail)
-                }
-            }
-        }
+        Tst::with_order(CodePointOrder).into_self_balancing()
     }
 }
 
 
-fn visit_values_r<'a, T, C>(link: &'a Link<T>, callback: &mut C)
-where C: FnMut (&T) {
+impl<T, O: LabelOrder> Tst<T, O> {
 
-    match *link {
+    pub fn with_order(order: O) -> Self {
 
-        None => return,
+        Tst { root: None, count: 0, order, balancing: PhantomData }
+    }
+}
 
-        Some(ref node) => {
 
-            visit_values_r(&node.left, callback);
+impl<T, O: LabelOrder, B: Balancing> Tst<T, O, B> {
 
-            if let Some(ref value) = node.value {
 
-                callback(value);
-            }
+    pub fn order(&self) -> &O {
 
-            visit_values_r(&node.middle, callback);
-            visit_values_r(&node.right, callback);
-        }
+        &self.order
     }
-}
-
-
-fn visit_values_r_mut<'a, T, C>(link: &'a mut Link<T>, callback: &mut C)
-where C: FnMut (&mut T) {
 
-    match *link {
 
-        None => return,
+    fn root_node(&self) -> Option<&Node<T>> {
 
-        Some(ref mut node) => {
+        self.root.as_deref()
+    }
 
-            visit_values_r_mut(&mut node.left, callback);
 
-            if let Some(ref mut value) = node.value {
+    fn visit_mut<'b, S, C>(&mut self, search: S, state: Option<S::State>, mut callback: C)
+    where S: Search<'b>, C: FnMut (&mut T, &S::State) {
 
-                callback(value);
-            }
+        if let Some(state) = state {
 
-            visit_values_r_mut(&mut node.middle, callback);
-            visit_values_r_mut(&mut node.right, callback);
+            visit_r_mut(&mut self.root, &search, &state, &self.order, &mut callback);
         }
     }
-}
-
 
-fn visit_complete_values_r<'a, T, C>(link: &'a Link<T>, callback: &mut C)
-where C: FnMut (&T) {
 
-    match *link {
+    pub fn is_self_balancing(&self) -> bool {
 
-        None => return,
+        B::is_self_balancing()
+    }
 
-        Some(ref node) => {
 
-            visit_values_r(&node.left, callback);
+    /// Turns this tree into a self-balancing one (see `SelfBalancing`), reshaping its sibling
+    /// trees. Trees built at once (as by `build_balanced`) or read back are never self-balancing
+    /// until turned into one.
+    pub fn into_self_balancing(self) -> Tst<T, O, SelfBalancing> {
 
-            if let Some(ref value) = node.value {
+        let mut tst = Tst { root: self.root, count: self.count, order: self.order, balancing: PhantomData };
 
-                callback(value);
-            }
+        tst.optimize();
 
-            visit_values_r(&node.middle, callback);
-            visit_values_r(&node.right, callback);
-        }
+        tst
     }
-}
 
 
-fn visit_complete_values_r_mut<'a, T, C>(link: &'a mut Link<T>, callback: &mut C)
-where C: FnMut (&mut T) {
+    // La clé n'est pas consommée (contrairement au treemap)
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
 
-    match *link {
+        let mut key_tail = key.chars();
 
-        None => return,
+        match key_tail.next() {
 
-        Some(ref mut node) => {
+            None => Some(value),
 
-            visit_values_r_mut(&mut node.left, callback);
+            Some(label) => {
 
-            if let Some(ref mut value) = node.value {
+                let old_value = insert_r(&mut self.root, label, key_tail, value, &self.order, B::is_self_balancing());
 
-                callback(value);
-            }
+                if old_value.is_none() {
+
+                    self.count += 1;
+                }
 
-            visit_values_r_mut(&mut node.middle, callback);
-            visit_values_r_mut(&mut node.right, callback);
+                old_value
+            }
         }
     }
-}
 
 
-//TODO - revoir syntaxe des mut, avant ou après les ':' ?
-fn visit_neighbor_values_r<'a, T, C>(link: &'a Link<T>, label: Option<char>, key_tail: &mut Chars, tail_len: usize, range: usize, callback: &mut C)
-where C: FnMut (&T) {
+    pub fn get(&self, key: &str) -> Option<&T> {
 
-    if range == 0 {
+        let mut key_tail = key.chars();
 
-        if let Some(label) = label {
+        match key_tail.next() {
 
-            if let Some(value) = get_r(link, label, key_tail) {
+            None => None,
 
-                callback(value);
-            }
+            Some(label) => get_r(&self.root, label, &mut key_tail, &self.order)
         }
+    }
 
-    } else {
 
-        if let Some(ref node) = *link {
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
 
-            visit_neighbor_values_r(&node.left, label, key_tail, tail_len, range, callback);
+        let mut key_tail = key.chars();
 
-            if let Some(ref value) = node.value {
+        match key_tail.next() {
 
-                let new_range = match label {
+            None => None,
 
-                    None => range-1,
+            Some(label) => get_r_mut(&mut self.root, label, &mut key_tail, &self.order)
+        }
+    }
 
-                    Some(label) => if label==node.label { range } else { range-1 }
-                };
 
-                if tail_len <= new_range {
+    pub fn remove(&mut self, key: &str) -> Option<T> {
 
-                    callback(value);
-                }
-            }
+        let mut key_tail = key.chars();
 
-            //TODO - Vérifier la libération des objets. Cela arrive-t-il plus rapidement
-            //       avec une portée réduite ?
-            {
-                let new_range = match label {
+        let (prune, old_value) = match key_tail.next() {
 
-                    None => range-1,
+            None => (false, None),
 
-                    Some(label) => if label==node.label { range } else { range-1 }
-                };
+            Some(label) => remove_r(&mut self.root, label, &mut key_tail, &self.order, B::is_self_balancing())
+        };
 
-                let mut new_tail = key_tail.clone();
-                let new_label = new_tail.next();
+        if prune {
 
-                let new_len = if tail_len > 0 { tail_len-1 } else { tail_len };
+            self.root = None;
+        }
 
-                visit_neighbor_values_r(&node.middle, new_label, &mut new_tail, new_len, new_range, callback);
-            }
+        if old_value.is_some() {
 
-            visit_neighbor_values_r(&node.right, label, key_tail, tail_len, range, callback);
+            self.count -= 1;
         }
-    }
-}
-
 
-fn visit_neighbor_values_r_mut<'a, T, C>(link: &'a mut Link<T>, label: Option<char>, key_tail: &mut Chars, tail_len: usize, range: usize, callback: &mut C)
-where C: FnMut (&mut T) {
+        old_value
+    }
 
-    if range == 0 {
 
-        if let Some(label) = label {
+    pub fn len(&self) -> usize {
 
-            //TODO - Clarifier ça...
-            if let Some(/*ref mut*/ value) = get_r_mut(link, label, key_tail) {
+        self.count
+    }
 
-                callback(value);
-            }
-        }
 
-    } else {
+    pub fn stat(&self) -> Stats {
 
-        if let Some(ref mut node) = *link {
+        let empty_stats: Stats = Default::default();
 
-            let label_tmp = node.label;
+        let mut stats = walk::stat_r(empty_stats, self.root_node(), 0, 0, 0);
 
-            visit_neighbor_values_r_mut(&mut node.left, label, key_tail, tail_len, range, callback);
+        stats.bytes.node = mem::size_of::<Node<T>>();
+        stats.bytes.total = mem::size_of::<Tst<T, O>>()+stats.count.nodes*stats.bytes.node;
 
-            if let Some(ref mut value) = node.value {
+        stats
+    }
 
-                let new_range = match label {
 
-                    None => range-1,
+    pub fn clear(&mut self) {
 
-                    //TODO - Clarifier ça...
-                    Some(label) => if label == /*node.label*/ label_tmp { range } else { range-1 }
-                };
+        self.root = None;
+        self.count = 0;
+    }
 
-                if tail_len <= new_range {
 
-                    callback(value);
-                }
-            }
+    gen_search_api!(TstIterator);
+    gen_search_api_mut!();
+}
 
-            //TODO - Vérifier la libération des objets. Cela arrive-t-il plus rapidement
-            //       avec une portée réduite ?
-            {
-                let new_range = match label {
 
-                    None => range-1,
+#[macro_export]
+macro_rules! tst {
 
-                    Some(label) => if label == node.label { range } else { range-1 }
-                };
+    () => {{
+        $crate::Tst::new()
+    }};
 
-                let mut new_tail = key_tail.clone();
-                let new_label = new_tail.next();
+    ($($key:expr => $value:expr,)+) => (tst!($($key => $value),+));
 
-                let new_len = if tail_len > 0 { tail_len-1 } else { tail_len };
+    ($($key: expr => $val: expr),*) => {{
 
-                visit_neighbor_values_r_mut(&mut node.middle, new_label, &mut new_tail, new_len, new_range, callback);
-            }
+        let mut tst = $crate::Tst::new();
+        $(
+            tst.insert($key, $val);
+        )*
 
-            visit_neighbor_values_r_mut(&mut node.right, label, key_tail, tail_len, range, callback);
-        }
-    }
+        tst
+    }};
 }
 
 
-fn visit_crossword_values_r<'a, T, C>(link: &'a Link<T>, label: char, key_tail: &mut Chars, joker: char, callback: &mut C)
-    where C: FnMut (&T) {
-
-    match *link {
+#[derive(Debug, PartialEq)]
+enum TstIteratorAction {
 
-        None => return,
+    GoLeft,
+    Visit,
+    GoMiddle,
+    GoRight
+}
 
-        Some(ref node) => {
 
-            if label == joker || label < node.label {
+impl<'a, T, O: LabelOrder, B: Balancing> IntoIterator for &'a Tst<T, O, B> {
 
-                visit_crossword_values_r(&node.left, label, key_tail, joker, callback);
-            }
+    type Item = &'a T;
+    type IntoIter = TstIterator<'a, 'static, T, O>;
 
-            if label == joker || label == node.label {
+    fn into_iter(self) -> Self::IntoIter {
 
-                let mut new_tail = key_tail.clone();
-                let new_label = new_tail.next();
+        self.iter()
+    }
+}
 
-                match new_label {
 
-                    None =>  if let Some(ref value) = node.value {
-
-                        callback(value);
-                    },
-
-                    Some(label) => visit_crossword_values_r(&node.middle, label, &mut new_tail, joker, callback)
-                }
-            }
-
-            if label == joker || label > node.label {
-
-                visit_crossword_values_r(&node.right, label, key_tail, joker, callback);
-            }
-        }
-    }
-}
-
-
-fn visit_crossword_values_r_mut<'a, T, C>(link: &'a mut Link<T>, label: char, key_tail: &mut Chars, joker: char, callback: &mut C)
-    where C: FnMut (&mut T) {
-
-    match *link {
-
-        None => return,
-
-        Some(ref mut node) => {
-
-            if label == joker || label < node.label {
-
-                visit_crossword_values_r_mut(&mut node.left, label, key_tail, joker, callback);
-            }
-
-            if label == joker || label == node.label {
-
-                let mut new_tail = key_tail.clone();
-                let new_label = new_tail.next();
-
-                match new_label {
-
-                    None =>  if let Some(ref mut value) = node.value {
-
-                        callback(value);
-                    },
-
-                    Some(label) => visit_crossword_values_r_mut(&mut node.middle, label, &mut new_tail, joker, callback)
-                }
-            }
-
-            if label == joker || label > node.label {
-
-                visit_crossword_values_r_mut(&mut node.right, label, key_tail, joker, callback);
-            }
-        }
-    }
-}
-
-
-fn pretty_print_r<'a, T>(link: &'a Link<T>, writer: &mut Write) {
-
-    match *link {
-
-        None => return,
-
-        Some(ref node) => {
-
-            let value_box = match node.value {
-
-                None => "☐", Some(_) => "☑"
-            };
-
-            let _ = writeln!(writer, r#""{:p}" [label=<<TABLE BORDER="0" CELLBORDER="1" CELLSPACING="0"><TR><TD COLSPAN="3">{} {}</TD></TR><TR><TD PORT="l"></TD><TD PORT="m"></TD><TD PORT="r"></TD></TR></TABLE>>]"#, node, value_box, node.label);
-
-            {
-                let mut print_edge = |link, start, style| if let &Some(ref child) = link {
-
-                    let _ = writeln!(writer, r#""{:p}":{} -> "{:p}" [style={}]"#, node, start, child, style);
-                };
-
-                print_edge(&node.left, "l", "solid");
-                print_edge(&node.middle, "m", "bold");
-                print_edge(&node.right, "r", "solid");
-            }
-
-            pretty_print_r(&node.left, writer);
-            pretty_print_r(&node.middle, writer);
-            pretty_print_r(&node.right, writer);
-        }
-    }
-}
-
-
-impl<T> Tst<T> {
-
-    pub fn new() -> Self {
-
-        Tst { root: None, count: 0 }
-    }
-
-
-    // La clé n'est pas consommée (contrairement au treemap)
-    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => Some(value),
-
-            Some(label) => {
-
-                let old_value = insert_r(&mut self.root, label, key_tail, value);
-
-                if old_value.is_none() {
-
-                    self.count += 1;
-                }
-
-                old_value
-            }
-        }
-    }
-
-
-    pub fn get(&self, key: &str) -> Option<&T> {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => None,
-
-            Some(label) => get_r(&self.root, label, &mut key_tail)
-        }
-    }
-
-
-    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => None,
-
-            Some(label) => get_r_mut(&mut self.root, label, &mut key_tail)
-        }
-    }
-
-
-    pub fn remove(&mut self, key: &str) -> Option<T> {
-
-        let mut key_tail = key.chars();
-
-        let (prune, old_value) = match key_tail.next() {
-
-            None => (false, None),
-
-            Some(label) => remove_r(&mut self.root, label, &mut key_tail)
-        };
-
-        if prune {
-
-            self.root = None;
-        }
-
-        if old_value.is_some() {
-
-            self.count -= 1;
-        }
-
-        old_value
-    }
-
-
-    pub fn len(&self) -> usize {
-
-        self.count
-    }
-
-
-    pub fn stat(&self) -> Stats {
-
-        let empty_stats: Stats = Default::default();
-
-        let mut stats = stat_r(empty_stats, &self.root, 0, 0, 0);
-
-        stats.bytes.node = mem::size_of::<Node<T>>();
-        stats.bytes.total = mem::size_of::<Tst<T>>()+stats.count.nodes*stats.bytes.node;
-
-        stats
-    }
-
-
-    pub fn clear(&mut self) {
-
-        self.root = None;
-        self.count = 0;
-    }
-
-
-    pub fn visit_values<C>(&self, mut callback: C)
-    where C: FnMut (&T) {
-
-        visit_values_r(&self.root, &mut callback);
-    }
-
-
-    pub fn visit_values_mut<C>(&mut self, mut callback: C)
-    where C: FnMut (&mut T) {
-
-        visit_values_r_mut(&mut self.root, &mut callback);
-    }
-
-
-    pub fn visit_complete_values<C>(&self, key: &str, mut callback: C)
-    where C: FnMut (&T) {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => visit_values_r(&self.root, &mut callback),
-
-            Some(label) => {
-
-                let new_root = find_complete_root_r(&self.root, label, key_tail);
-                visit_complete_values_r(new_root, &mut callback)
-            }
-        }
-    }
-
-
-    pub fn visit_complete_values_mut<C>(&mut self, key: &str, mut callback: C)
-    where C: FnMut (&mut T) {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => visit_values_r_mut(&mut self.root, &mut callback),
-
-            Some(label) => {
-
-                let mut new_root = find_complete_root_r_mut(&mut self.root, label, key_tail);
-                visit_complete_values_r_mut(&mut new_root, &mut callback)
-            }
-        }
-    }
-
-
-    pub fn visit_neighbor_values<C>(&self, key: &str, dist: usize, mut callback: C)
-    where C: FnMut (&T) {
-
-        let mut key_tail = key.chars();
-        let label = key_tail.next();
-        let tail_len = if key.len() == 0 { 0 } else { key.len()-1 };
-
-        visit_neighbor_values_r(&self.root, label, &mut key_tail, tail_len, dist, &mut callback);
-    }
-
-
-    pub fn visit_neighbor_values_mut<C>(&mut self, key: &str, dist: usize, mut callback: C)
-    where C: FnMut (&mut T) {
-
-        let mut key_tail = key.chars();
-        let label = key_tail.next();
-        let tail_len = if key.len() == 0 { 0 } else { key.len()-1 };
-
-        visit_neighbor_values_r_mut(&mut self.root, label, &mut key_tail, tail_len, dist, &mut callback);
-    }
-
-
-    pub fn visit_crossword_values<C>(&self, key: &str, joker: char, mut callback: C)
-    where C: FnMut (&T) {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => return,
-
-            Some(label) => visit_crossword_values_r(&self.root, label, &mut key_tail, joker, &mut callback)
-        }
-    }
-
-
-    pub fn visit_crossword_values_mut<C>(&mut self, key: &str, joker: char, mut callback: C)
-    where C: FnMut (&mut T) {
-
-        let mut key_tail = key.chars();
-
-        match key_tail.next() {
-
-            None => return,
-
-            Some(label) => visit_crossword_values_r_mut(&mut self.root, label, &mut key_tail, joker, &mut callback)
-        }
-    }
-
-
-    pub fn pretty_print(&self, writer: &mut Write) {
-
-        let _ = writeln!(writer, "digraph {{");
-        let _ = writeln!(writer, "node [shape=plaintext]");
-
-        pretty_print_r(&self.root, writer);
-
-        let _ = writeln!(writer, "}}");
-
-    }
-
-
-    pub fn iter(&self) -> TstIterator<T> {
-
-        TstIterator::<T>::new(&self)
-    }
-
-
-    pub fn iter_complete(&self, prefix: &str) -> TstCompleteIterator<T> {
-
-        TstCompleteIterator::<T>::new(&self, prefix)
-    }
-
-
-    pub fn iter_neighbor<'a, 'b>(&'a self, key: &'b str, range: usize) -> TstNeighborIterator<'a, 'b, T> {
-
-        TstNeighborIterator::<T>::new(&self, key, range)
-    }
-
-
-    pub fn iter_crossword<'a, 'b>(&'a self, key: &'b str, joker: char) -> TstCrosswordIterator<'a, 'b, T> {
-
-        TstCrosswordIterator::<T>::new(&self, key, joker)
-    }
-}
-
-
-#[macro_export]
-macro_rules! tst {
-
-    () => {{
-        $crate::Tst::new()
-    }};
-
-    ($($key:expr => $value:expr,)+) => (tst!($($key => $value),+));
-
-    ($($key: expr => $val: expr),*) => {{
-
-        let mut tst = $crate::Tst::new();
-        $(
-            tst.insert($key, $val);
-        )*
-
-        tst
-    }};
-}
-
-
-#[derive(Debug, PartialEq)]
-enum TstIteratorAction {
-
-    GoLeft,
-    Visit,
-    GoMiddle,
-    GoRight
-}
-
-use self::TstIteratorAction::*;
-
-
-#[derive(Debug)]
-pub struct TstIterator<'a, T: 'a> {
-
-    todo_i: Vec<(&'a Node<T>, TstIteratorAction)>,
-    last_i: Option<&'a Node<T>>,
-
-    todo_j: Vec<(&'a Node<T>, TstIteratorAction)>,
-    last_j: Option<&'a Node<T>>
-}
-
-
-macro_rules! gen_it_path {
-
-    ($path_of_x:ident, $todo_x:ident, $a1:expr, $a2:expr) => (
-
-        pub fn $path_of_x(&self) -> String {
-
-            let mut path = String::new();
-
-            for todo in self.$todo_x.iter() {
-
-                if todo.1 == $a1 || todo.1 == $a2 {
-
-                    path.push(todo.0.label);
-                }
-            }
-
-            path
-        }
-    );
-}
-
-
-impl<'a, T> TstIterator<'a, T> {
-
-    pub fn new(tst: &'a Tst<T>) -> Self {
-
-        TstIterator::new_from_root(&tst.root)
-    }
-
-
-    fn new_from_root(root: &'a Link<T>) -> Self {
-
-        let mut it = TstIterator {
-
-            todo_i: Vec::new(), last_i: None,
-            todo_j: Vec::new(), last_j: None,
-        };
-
-        if let Some(ref node) = root {
-
-            //TODO - Comprendre exactement comment on se débarasse de la box ici
-            //no method named `paf` found for type `&std::boxed::Box<tst::Node<T>>`
-            //node.paf();
-
-            it.todo_i.push((node, GoLeft));
-            it.todo_j.push((node, GoRight));
-        }
-
-        it
-    }
-
-
-    gen_it_path!(current_key, todo_i, GoMiddle, GoRight);
-    gen_it_path!(current_key_back, todo_j, Visit, GoLeft);
-}
-
-
-impl<'a, T> Iterator for TstIterator<'a, T> {
-
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<&'a T> {
-
-        let mut found = None;
-
-        while let Some((node, action)) = self.todo_i.pop() {
-
-            match action {
-
-                GoLeft => {
-
-                    self.todo_i.push((node, Visit));
-
-                    if let Some(ref child) = node.left {
-
-                        self.todo_i.push((child, GoLeft));
-                    }
-                }
-
-                Visit => {
-
-                    if node.value.is_some() {
-
-                        if let Some(node_j) = self.last_j {
-
-                            if ptr::eq(node, node_j) {
-
-                                self.todo_i.clear();
-                                self.todo_j.clear();
-
-                                found = None;
-                                break;
-                            }
-                        }
-                    }
-
-                    self.todo_i.push((node, GoMiddle));
-
-                    if let Some(ref value) = node.value {
-
-                        self.last_i = Some(node);
-                        found = Some(value);
-
-                        break;
-                    }
-                }
-
-                GoMiddle => {
-
-                    self.todo_i.push((node, GoRight));
-
-                    if let Some(ref child) = node.middle {
-
-                        self.todo_i.push((child, GoLeft));
-                    }
-                }
-
-                GoRight => {
-
-                    if let Some(ref child) = node.right {
-
-                        self.todo_i.push((child, GoLeft));
-                    }
-                }
-            }
-        }
-
-        found
-    }
-}
-
-
-impl<'a, T> IntoIterator for &'a Tst<T> {
-
-    type Item = &'a T;
-    type IntoIter = TstIterator<'a, T>;
-
-    fn into_iter(self) -> Self::IntoIter {
-
-        self.iter()
-    }
-}
-
-
-impl<'a, T> DoubleEndedIterator for TstIterator<'a, T> {
-
-    fn next_back(&mut self) -> Option<&'a T> {
-
-        let mut found = None;
-
-        while let Some((node, action)) = self.todo_j.pop() {
-
-            match action {
-
-                GoRight => {
-
-                    self.todo_j.push((node, GoMiddle));
-
-                    if let Some(ref child) = node.right {
-
-                        self.todo_j.push((child, GoRight));
-                    }
-                }
-
-                Visit => {
-
-                    if node.value.is_some() {
-
-                        if let Some(node_i) = self.last_i {
-
-                            if ptr::eq(node, node_i) {
-
-                                self.todo_i.clear();
-                                self.todo_j.clear();
-
-                                found = None;
-                                break;
-                            }
-                        }
-                    }
-
-                    self.todo_j.push((node, GoLeft));
-
-                    if let Some(ref value) = node.value {
-
-                        self.last_j = Some(node);
-                        found = Some(value);
-
-                        break;
-                    }
-                }
-
-                GoMiddle => {
-
-                    self.todo_j.push((node, Visit));
-
-                    if let Some(ref child) = node.middle {
-
-                        self.todo_j.push((child, GoRight));
-                    }
-                }
-
-                GoLeft => {
-
-                    if let Some(ref child) = node.left {
-
-                        self.todo_j.push((child, GoRight));
-                    }
-                }
-            }
-        }
-
-        found
-    }
-}
-
-
-#[derive(Debug)]
-pub struct TstCompleteIterator<'a, T: 'a> {
-
-    it: TstIterator<'a, T>,
-    prefix: String
-}
-
-
-impl<'a, T> TstCompleteIterator<'a, T> {
-
-    //TODO - On consomme uns String ou on prend une &str qui est copiée (cohérence interface) ?
-    pub fn new(tst: &'a Tst<T>, key_prefix: &str) -> Self {
-
-        let mut key_tail = key_prefix.chars();
-
-        TstCompleteIterator {
-
-            it : match key_tail.next() {
-
-                None => TstIterator::<T>::new(tst),
-
-                Some(label) => {
-
-                    let new_root = find_complete_root_r(&tst.root, label, key_tail);
-                    TstIterator::<T>::new_from_root(new_root)
-                }
-            },
-
-            prefix: key_prefix.to_string()
-        }
-    }
-
-
-    pub fn current_key(&self) -> String {
-
-        self.prefix.clone() + &self.it.current_key()
-    }
-
-
-    pub fn current_key_back(&self) -> String {
-
-        self.prefix.clone() + &self.it.current_key_back()
-    }
-}
-
-
-impl<'a, T> Iterator for TstCompleteIterator<'a, T> {
-
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<&'a T> {
-
-        self.it.next()
-    }
-}
-
-
-impl<'a, T> DoubleEndedIterator for TstCompleteIterator<'a, T> {
-
-    fn next_back(&mut self) -> Option<&'a T> {
-
-       self.it.next_back()
-    }
-}
-
-
-#[derive(Debug)]
-pub struct TstNeighborIterator<'a, 'b, T: 'a> {
-
-    todo_i: Vec<(&'a Node<T>, TstIteratorAction, Option<char>, Chars<'b>, usize, usize)>,
-    last_i: Option<&'a Node<T>>,
-
-    todo_j: Vec<(&'a Node<T>, TstIteratorAction, Option<char>, Chars<'b>, usize, usize)>,
-    last_j: Option<&'a Node<T>>
-}
-
-
-impl<'a, 'b, T> TstNeighborIterator<'a, 'b, T> {
-
-    pub fn new(tst: &'a Tst<T>, key: &'b str, range: usize) -> Self {
-
-        let mut it = TstNeighborIterator {
-
-            todo_i: Vec::new(), last_i: None,
-            todo_j: Vec::new(), last_j: None,
-        };
-
-        if let Some(ref node) = &tst.root {
-
-            let mut key_tail = key.chars();
-            let label = key_tail.next();
-            let tail_len = if key.len() == 0 { 0 } else { key.len()-1 };
-
-            it.todo_i.push((node, GoLeft, label, key_tail.clone(), tail_len, range));
-            it.todo_j.push((node, GoRight, label, key_tail, tail_len, range));
-        }
-
-        it
-    }
-
-
-    gen_it_path!(current_key, todo_i, GoMiddle, GoRight);
-    gen_it_path!(current_key_back, todo_j, Visit, GoLeft);
-}
-
-
-impl<'a, 'b, T> Iterator for TstNeighborIterator<'a, 'b, T> {
-
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<&'a T> {
-
-        let mut found = None;
-
-        while let Some((node, action, label, mut key_tail, tail_len, range)) = self.todo_i.pop() {
-
-            match action {
-
-                GoLeft => {
-
-                    self.todo_i.push((node, Visit, label, key_tail.clone(), tail_len, range));
-
-                    if let Some(label) = label {
-
-                        if range == 0 && label >= node.label {
-
-                            continue;
-                        }
-                    }
-
-                    if let Some(ref child) = node.left {
-
-                        self.todo_i.push((child, GoLeft, label, key_tail, tail_len, range));
-                    }
-                }
-
-                Visit => {
-
-                    if node.value.is_some() {
-
-                        if let Some(node_j) = self.last_j {
-
-                            if ptr::eq(node, node_j) {
-
-                                self.todo_i.clear();
-                                self.todo_j.clear();
-
-                                found = None;
-                                break;
-                            }
-                        }
-                    }
-
-                    self.todo_i.push((node, GoMiddle, label, key_tail, tail_len, range));
-
-                    if let Some(ref value) = node.value {
-
-                        let delta = match label {
-
-                            None => 1,
-
-                            Some(label) => if label==node.label { 0 } else { 1 }
-
-                        };
-
-                        if range >= delta {
-
-                            let new_range = range - delta;
-
-                            if tail_len  <= new_range {
-
-                                self.last_i = Some(node);
-                                found = Some(value);
-
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                GoMiddle => {
-
-                    self.todo_i.push((node, GoRight, label, key_tail.clone(), tail_len, range));
-
-                    let delta = match label {
-
-                        None => 1,
-
-                        Some(label) => if label==node.label { 0 } else { 1 }
-                    };
-
-                    if range >= delta {
-
-                        let new_range = range - delta;
-
-                        let new_label = key_tail.next();
-                        let new_len = if tail_len > 0 { tail_len-1 } else { tail_len };
-
-                        if let Some(ref child) = node.middle {
-
-                            self.todo_i.push((child, GoLeft, new_label, key_tail, new_len, new_range));
-                        }
-                    }
-                }
-
-                GoRight => {
-
-                    if let Some(label) = label {
-
-                        if range == 0 && label <= node.label {
-
-                            continue;
-                        }
-                    }
-
-                    if let Some(ref child) = node.right {
-
-                        self.todo_i.push((child, GoLeft, label, key_tail, tail_len, range));
-                    }
-                }
-            }
-        }
-
-        found
-    }
-}
-
-
-impl<'a, 'b, T> DoubleEndedIterator for TstNeighborIterator<'a, 'b, T> {
-
-    fn next_back(&mut self) -> Option<&'a T> {
-
-        let mut found = None;
-
-        while let Some((node, action, label, mut key_tail, tail_len, range)) = self.todo_j.pop() {
-
-            match action {
-
-                GoRight => {
-
-                    self.todo_j.push((node, GoMiddle, label, key_tail.clone(), tail_len, range));
-
-                    if let Some(label) = label {
-
-                        if range == 0 && label <= node.label {
-
-                            continue;
-                        }
-                    }
-
-                    if let Some(ref child) = node.right {
-
-                        self.todo_j.push((child, GoRight, label, key_tail, tail_len, range));
-                    }
-                }
-
-                Visit => {
-
-                    if node.value.is_some() {
-
-                        if let Some(node_i) = self.last_i {
-
-                            if ptr::eq(node, node_i) {
-
-                                self.todo_i.clear();
-                                self.todo_j.clear();
-
-                                found = None;
-                                break;
-                            }
-                        }
-                    }
-
-                    self.todo_j.push((node, GoLeft, label, key_tail, tail_len, range));
-
-                    if let Some(ref value) = node.value {
-
-                        let delta = match label {
-
-                            None => 1,
-
-                            Some(label) => if label==node.label { 0 } else { 1 }
-
-                        };
-
-                        if range >= delta {
-
-                            let new_range = range - delta;
-
-                            if tail_len  <= new_range {
-
-                                self.last_j = Some(node);
-                                found = Some(value);
-
-                                break;
-                            }
-                        }
-                    }
-                }
-
-                GoMiddle => {
-
-                    self.todo_j.push((node, Visit, label, key_tail.clone(), tail_len, range));
-
-                    let delta = match label {
-
-                        None => 1,
-
-                        Some(label) => if label==node.label { 0 } else { 1 }
-
-                    };
-
-                    if range >= delta {
-
-                        let new_range = range - delta;
-
-                        let new_label = key_tail.next();
-                        let new_len = if tail_len > 0 { tail_len-1 } else { tail_len };
-
-                        if let Some(ref child) = node.middle {
-
-                            self.todo_j.push((child, GoRight, new_label, key_tail, new_len, new_range));
-                        }
-                    }
-                }
-
-                GoLeft => {
-
-                    if let Some(label) = label {
-
-                        if range == 0 && label >= node.label {
-
-                            continue;
-                        }
-                    }
-
-                    if let Some(ref child) = node.left {
-
-                        self.todo_j.push((child, GoRight, label, key_tail, tail_len, range));
-                    }
-                }
-            }
-        }
-
-        found
-    }
-}
-
-
-#[derive(Debug)]
-pub struct TstCrosswordIterator<'a, 'b, T: 'a> {
-
-    todo_i: Vec<(&'a Node<T>, TstIteratorAction, char, Chars<'b>, usize)>,
-    last_i: Option<&'a Node<T>>,
-
-    todo_j: Vec<(&'a Node<T>, TstIteratorAction, char, Chars<'b>, usize)>,
-    last_j: Option<&'a Node<T>>,
-
-    joker: char
-}
-
-
-impl<'a, 'b, T> TstCrosswordIterator<'a, 'b, T> {
-
-    pub fn new(tst: &'a Tst<T>, key: &'b str, joker: char) -> Self {
-
-        let mut it = TstCrosswordIterator {
-
-            todo_i: Vec::new(), last_i: None,
-            todo_j: Vec::new(), last_j: None,
-            joker: joker,
-
-        };
-
-        if let Some(ref node) = &tst.root {
-
-            let mut key_tail = key.chars();
-
-            if let Some(label) = key_tail.next() {
-
-                let tail_len = key.len()-1;
-
-                it.todo_i.push((node, GoLeft, label, key_tail.clone(), tail_len));
-                it.todo_j.push((node, GoRight, label, key_tail, tail_len));
-            }
-        }
-
-        it
-    }
-
-
-    gen_it_path!(current_key, todo_i, GoMiddle, GoRight);
-    gen_it_path!(current_key_back, todo_j, Visit, GoLeft);
-}
-
-
-impl<'a, 'b, T> Iterator for TstCrosswordIterator<'a, 'b, T> {
-
-    type Item = &'a T;
-
-    fn next(&mut self) -> Option<&'a T> {
-
-        let mut found = None;
-
-        while let Some((node, action, label, mut key_tail, tail_len)) = self.todo_i.pop() {
-
-            match action {
-
-                GoLeft => {
-
-                    self.todo_i.push((node, Visit, label, key_tail.clone(), tail_len));
-
-                    if label == self.joker || label < node.label {
-
-                        if let Some(ref child) = node.left {
-
-                            self.todo_i.push((child, GoLeft, label, key_tail, tail_len));
-                        }
-                    }
-                }
-
-                Visit => {
-
-                    if node.value.is_some() {
-
-                        if let Some(node_j) = self.last_j {
-
-                            if ptr::eq(node, node_j) {
-
-                                self.todo_i.clear();
-                                self.todo_j.clear();
-
-                                found = None;
-                                break;
-                            }
-                        }
-                    }
-
-                    self.todo_i.push((node, GoMiddle, label, key_tail, tail_len));
-
-                    if let Some(ref value) = node.value {
-
-                        if tail_len == 0 && (label == self.joker || label == node.label) {
-
-                            self.last_i = Some(node);
-                            found = Some(value);
-
-                            break;
-                        }
-                    }
-                }
-
-                GoMiddle => {
-
-                    self.todo_i.push((node, GoRight, label, key_tail.clone(), tail_len));
-
-                    if label == self.joker || label == node.label {
-
-                        if let Some(ref child) = node.middle {
-
-                            if let Some(new_label) = key_tail.next() {
-
-                                self.todo_i.push((child, GoLeft, new_label, key_tail, tail_len-1));
-                            }
-                        }
-                    }
-                }
-
-                GoRight => {
-
-                    if label == self.joker || label > node.label {
-
-                        if let Some(ref child) = node.right {
-
-                            self.todo_i.push((child, GoLeft, label, key_tail, tail_len));
-                        }
-                    }
-                }
-            }
-        }
-
-        found
-    }
-}
-
-
-impl<'a, 'b, T> DoubleEndedIterator for TstCrosswordIterator<'a, 'b, T> {
-
-    fn next_back(&mut self) -> Option<&'a T> {
-
-        let mut found = None;
-
-        while let Some((node, action, label, mut key_tail, tail_len)) = self.todo_j.pop() {
-
-            match action {
-
-                GoRight => {
-
-                    self.todo_j.push((node, GoMiddle, label, key_tail.clone(), tail_len));
-
-                    if label == self.joker || label > node.label {
-
-                        if let Some(ref child) = node.right {
-
-                            self.todo_j.push((child, GoRight, label, key_tail, tail_len));
-                        }
-                    }
-                }
-
-                Visit => {
-
-                    if node.value.is_some() {
-
-                        if let Some(node_i) = self.last_i {
-
-                            if ptr::eq(node, node_i) {
-
-                                self.todo_i.clear();
-                                self.todo_j.clear();
-
-                                found = None;
-                                break;
-                            }
-                        }
-                    }
-
-                    self.todo_j.push((node, GoLeft, label, key_tail, tail_len));
-
-                    if let Some(ref value) = node.value {
-
-                        if tail_len == 0 && (label == self.joker || label == node.label) {
-
-                            self.last_j = Some(node);
-                            found = Some(value);
-
-                            break;
-                        }
-                    }
-                }
-
-                GoMiddle => {
-
-                    self.todo_j.push((node, Visit, label, key_tail.clone(), tail_len));
-
-                    if label == self.joker || label == node.label {
-
-                        if let Some(ref child) = node.middle {
-
-                            if let Some(new_label) = key_tail.next() {
-
-                                self.todo_j.push((child, GoRight, new_label, key_tail, tail_len-1));
-                            }
-                        }
-                    }
-                }
-
-                GoLeft => {
-
-                    if label == self.joker || label < node.label {
-
-                        if let Some(ref child) = node.left {
-
-                            self.todo_j.push((child, GoRight, label, key_tail, tail_len));
-                        }
-                    }
-                }
-            }
-        }
-
-        found
-    }
-}
+/// Iterator of all the searches of a `Tst`, which walks the tree from both ends, and tells the
+/// key of the last value found with `current_key` and `current_key_back`.
+pub type TstIterator<'a, 'b, T, O = CodePointOrder, S = Query> = Walk<'a, 'b, &'a Node<T>, O, S>;
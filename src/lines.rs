@@ -0,0 +1,211 @@
+use std::io::{self, BufRead, Write};
+use std::error::Error;
+use std::fmt;
+
+use Tst;
+use LabelOrder;
+use Balancing;
+
+
+/// What `Tst::load_lines` does with a key already in the tree, whether it was there before the
+/// load or comes from an earlier line.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Duplicates {
+
+    KeepFirst,
+    KeepLast,
+    Error
+}
+
+
+/// Error of `Tst::load_lines`. Lines are counted from 1.
+#[derive(Debug)]
+pub enum LoadLinesError {
+
+    Io(io::Error),
+
+    /// A bad escape sequence, an empty key, or a value refused by the parser.
+    Parse { line: usize, message: String },
+
+    /// A key already in the tree, with `Duplicates::Error`.
+    Duplicate { line: usize, key: String }
+}
+
+
+impl fmt::Display for LoadLinesError {
+
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        match *self {
+
+            LoadLinesError::Io(ref e) => write!(f, "{}", e),
+
+            LoadLinesError::Parse { line, ref message } => write!(f, "line {}: {}", line, message),
+
+            LoadLinesError::Duplicate { line, ref key } => write!(f, "line {}: duplicate key \"{}\"", line, key)
+        }
+    }
+}
+
+
+impl Error for LoadLinesError {}
+
+
+impl From<io::Error> for LoadLinesError {
+
+    fn from(e: io::Error) -> Self {
+
+        LoadLinesError::Io(e)
+    }
+}
+
+
+fn escape(s: &str, out: &mut String) {
+
+    for c in s.chars() {
+
+        match c {
+
+            '\\' => out.push_str("\\\\"),
+
+            '\t' => out.push_str("\\t"),
+
+            '\n' => out.push_str("\\n"),
+
+            '\r' => out.push_str("\\r"),
+
+            c => out.push(c)
+        }
+    }
+}
+
+
+fn unescape(s: &str) -> Result<String, String> {
+
+    let mut out = String::with_capacity(s.len());
+    let mut chars = s.chars();
+
+    while let Some(c) = chars.next() {
+
+        if c != '\\' {
+
+            out.push(c);
+            continue;
+        }
+
+        match chars.next() {
+
+            Some('\\') => out.push('\\'),
+
+            Some('t') => out.push('\t'),
+
+            Some('n') => out.push('\n'),
+
+            Some('r') => out.push('\r'),
+
+            Some(c) => return Err(format!("invalid escape \"\\{}\"", c)),
+
+            None => return Err("unfinished escape at end of line".to_string())
+        }
+    }
+
+    Ok(out)
+}
+
+
+impl<T, O: LabelOrder, B: Balancing> Tst<T, O, B> {
+
+    /// Inserts the keys of a text with one `key<TAB>value` per line, or just `key` for word lists
+    /// (the value is then parsed from an empty string). Returns the count of lines loaded.
+    ///
+    /// Tabs, newlines, carriage returns and backslashes in keys and values are escaped as `\t`,
+    /// `\n`, `\r` and `\\`, as `dump_lines` writes them. Empty lines are skipped. On error, the
+    /// lines before the failing one stay loaded.
+    pub fn load_lines<R, P, E>(&mut self, reader: R, duplicates: Duplicates, mut parse: P) -> Result<usize, LoadLinesError>
+    where R: BufRead, P: FnMut (&str) -> Result<T, E>, E: fmt::Display {
+
+        let mut loaded = 0;
+
+        for (index, line) in reader.lines().enumerate() {
+
+            let line = line?;
+            let line_number = index + 1;
+
+            let line = line.strip_suffix('\r').unwrap_or(&line);
+
+            if line.is_empty() {
+
+                continue;
+            }
+
+            let (key, value) = match line.find('\t') {
+
+                None => (line, ""),
+
+                Some(tab) => (&line[..tab], &line[tab+1..])
+            };
+
+            let parse_error = |message: String| LoadLinesError::Parse { line: line_number, message };
+
+            let key = unescape(key).map_err(parse_error)?;
+
+            if key.is_empty() {
+
+                return Err(parse_error("empty key".to_string()));
+            }
+
+            //The value of a skipped line is not parsed, so it cannot fail the load
+            let mut parse_value = || unescape(value).map_err(parse_error)
+                .and_then(|value| parse(&value).map_err(|e| parse_error(e.to_string())));
+
+            match self.get_mut(&key) {
+
+                None => { self.insert(&key, parse_value()?); }
+
+                Some(old_value) => match duplicates {
+
+                    Duplicates::KeepFirst => continue,
+
+                    Duplicates::KeepLast => *old_value = parse_value()?,
+
+                    Duplicates::Error => return Err(LoadLinesError::Duplicate { line: line_number, key })
+                }
+            }
+
+            loaded += 1;
+        }
+
+        Ok(loaded)
+    }
+
+
+    /// Writes one `key<TAB>value` line per key, in the order of `iter`, with the value given by
+    /// `format`. When `format` gives an empty string, only the key is written.
+    pub fn dump_lines<W, F>(&self, writer: &mut W, mut format: F) -> io::Result<()>
+    where W: Write, F: FnMut (&T) -> String {
+
+        let mut it = self.iter();
+        let mut line = String::new();
+
+        while let Some(value) = it.next() {
+
+            let value = format(value);
+
+            line.clear();
+
+            escape(&it.current_key(), &mut line);
+
+            if !value.is_empty() {
+
+                line.push('\t');
+                escape(&value, &mut line);
+            }
+
+            line.push('\n');
+
+            writer.write_all(line.as_bytes())?;
+        }
+
+        Ok(())
+    }
+}
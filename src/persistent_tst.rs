@@ -0,0 +1,372 @@
+use std::str::Chars;
+use std::cmp::Ordering::Less;
+use std::cmp::Ordering::Equal;
+use std::cmp::Ordering::Greater;
+use std::sync::Arc;
+use std::io::Write;
+use std::option;
+use std::mem;
+
+use Stats;
+use LabelOrder;
+use CodePointOrder;
+use query::Query;
+use walk::{self, Walk, WalkNode};
+
+
+/// A map with the same searches as `Tst`, whose versions share their nodes.
+///
+/// Nodes are held by `Arc`, and `snapshot` gives in O(1) a copy which keeps seeing the map as it
+/// was. Updates only copy the nodes on the path of their key which are shared with a snapshot,
+/// so values must be `Clone` to insert or remove them (wrap them in an `Arc` when cloning is
+/// costly). Snapshots can be sent to other threads. Labels are compared by code point, unless
+/// the map is created `with_order`.
+pub struct PersistentTst<T, O = CodePointOrder> {
+
+    root: PersistentLink<T>,
+    count: usize,
+    order: O
+}
+
+
+type PersistentLink<T> = Option<Arc<PersistentNode<T>>>;
+
+
+#[derive(Clone)]
+pub struct PersistentNode<T> {
+
+    label: char,
+    value: Option<T>,
+    left: PersistentLink<T>,
+    middle: PersistentLink<T>,
+    right: PersistentLink<T>
+}
+
+
+impl<'a, T> WalkNode<'a> for &'a PersistentNode<T> {
+
+    type Value = T;
+    type Label = option::IntoIter<char>;
+
+    fn label(&self) -> Self::Label {
+
+        Some(self.label).into_iter()
+    }
+
+
+    fn value(&self) -> Option<&'a T> {
+
+        self.value.as_ref()
+    }
+
+
+    fn left(&self) -> Option<Self> {
+
+        self.left.as_deref()
+    }
+
+
+    fn middle(&self) -> Option<Self> {
+
+        self.middle.as_deref()
+    }
+
+
+    fn right(&self) -> Option<Self> {
+
+        self.right.as_deref()
+    }
+
+
+    fn id(&self) -> usize {
+
+        *self as *const PersistentNode<T> as usize
+    }
+}
+
+
+fn insert_r<T: Clone, O: LabelOrder>(link: &mut PersistentLink<T>, label: char, mut key_tail: Chars, value: T, order: &O) -> Option<T> {
+
+    if link.is_none() {
+
+        *link = Some(Arc::new(PersistentNode { label, value: None, left: None, middle: None, right: None }));
+    }
+
+    //Copies the node if a snapshot shares it
+    let node = Arc::make_mut(link.as_mut().unwrap());
+
+    match order.compare(label, node.label) {
+
+        Less => insert_r(&mut node.left, label, key_tail, value, order),
+
+        Greater => insert_r(&mut node.right, label, key_tail, value, order),
+
+        Equal => match key_tail.next() {
+
+            None => node.value.replace(value),
+
+            Some(label) => insert_r(&mut node.middle, label, key_tail, value, order)
+        }
+    }
+}
+
+
+fn get_r<'a, T, O: LabelOrder>(link: &'a PersistentLink<T>, key: &str, order: &O) -> Option<&'a PersistentNode<T>> {
+
+    let mut key_tail = key.chars();
+
+    let mut label = key_tail.next()?;
+
+    let mut link = link;
+
+    while let Some(ref node) = *link {
+
+        link = match order.compare(label, node.label) {
+
+            Less => &node.left,
+
+            Greater => &node.right,
+
+            Equal => match key_tail.next() {
+
+                None => return Some(node),
+
+                Some(next) => {
+
+                    label = next;
+                    &node.middle
+                }
+            }
+        };
+    }
+
+    None
+}
+
+
+fn get_r_mut<'a, T: Clone, O: LabelOrder>(link: &'a mut PersistentLink<T>, label: char, key_tail: &mut Chars, order: &O) -> Option<&'a mut T> {
+
+    let node = Arc::make_mut(link.as_mut()?);
+
+    match order.compare(label, node.label) {
+
+        Less => get_r_mut(&mut node.left, label, key_tail, order),
+
+        Greater => get_r_mut(&mut node.right, label, key_tail, order),
+
+        Equal => match key_tail.next() {
+
+            None => node.value.as_mut(),
+
+            Some(label) => get_r_mut(&mut node.middle, label, key_tail, order)
+        }
+    }
+}
+
+
+//Removes a key known to be in the tree, and prunes the nodes left with nothing below them
+fn remove_r<T: Clone, O: LabelOrder>(link: &mut PersistentLink<T>, label: char, key_tail: &mut Chars, order: &O) -> Option<T> {
+
+    let (old_value, prune) = {
+
+        let node = Arc::make_mut(link.as_mut()?);
+
+        let old_value = match order.compare(label, node.label) {
+
+            Less => remove_r(&mut node.left, label, key_tail, order),
+
+            Greater => remove_r(&mut node.right, label, key_tail, order),
+
+            Equal => match key_tail.next() {
+
+                None => node.value.take(),
+
+                Some(label) => remove_r(&mut node.middle, label, key_tail, order)
+            }
+        };
+
+        let prune = node.value.is_none() && node.left.is_none() && node.middle.is_none() && node.right.is_none();
+
+        (old_value, prune)
+    };
+
+    if prune {
+
+        *link = None;
+    }
+
+    old_value
+}
+
+
+impl<T> PersistentTst<T> {
+
+    pub fn new() -> Self {
+
+        PersistentTst::with_order(CodePointOrder)
+    }
+}
+
+
+impl<T, O: LabelOrder> PersistentTst<T, O> {
+
+    pub fn with_order(order: O) -> Self {
+
+        PersistentTst { root: None, count: 0, order }
+    }
+
+
+    pub fn order(&self) -> &O {
+
+        &self.order
+    }
+
+
+    fn root_node(&self) -> Option<&PersistentNode<T>> {
+
+        self.root.as_deref()
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        get_r(&self.root, key, &self.order).and_then(|node| node.value.as_ref())
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.count
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    /// Same statistics as `Tst::stat`. Nodes shared with snapshots are counted in each of them.
+    pub fn stat(&self) -> Stats {
+
+        let empty_stats: Stats = Default::default();
+
+        let mut stats = walk::stat_r(empty_stats, self.root_node(), 0, 0, 0);
+
+        //Each node also comes with the counters of its Arc
+        stats.bytes.node = mem::size_of::<PersistentNode<T>>() + 2*mem::size_of::<usize>();
+        stats.bytes.total = mem::size_of::<PersistentTst<T, O>>()+stats.count.nodes*stats.bytes.node;
+
+        stats
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.root = None;
+        self.count = 0;
+    }
+
+
+    gen_search_api!(PersistentTstIterator);
+}
+
+
+impl<T, O: Clone> PersistentTst<T, O> {
+
+    /// Returns a copy of the map as it is now, unchanged by later updates of either of them.
+    pub fn snapshot(&self) -> Self {
+
+        PersistentTst { root: self.root.clone(), count: self.count, order: self.order.clone() }
+    }
+}
+
+
+impl<T> Default for PersistentTst<T> {
+
+    fn default() -> Self {
+
+        PersistentTst::new()
+    }
+}
+
+
+impl<T: Clone, O: LabelOrder> PersistentTst<T, O> {
+
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        let mut key_tail = key.chars();
+
+        let label = match key_tail.next() {
+
+            None => return Some(value),
+
+            Some(label) => label
+        };
+
+        let old_value = insert_r(&mut self.root, label, key_tail, value, &self.order);
+
+        if old_value.is_none() {
+
+            self.count += 1;
+        }
+
+        old_value
+    }
+
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+
+        //Look first, so that a missing key copies no node
+        self.get(key)?;
+
+        let mut key_tail = key.chars();
+        let label = key_tail.next()?;
+
+        get_r_mut(&mut self.root, label, &mut key_tail, &self.order)
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        self.get(key)?;
+
+        let mut key_tail = key.chars();
+        let label = key_tail.next()?;
+
+        let old_value = remove_r(&mut self.root, label, &mut key_tail, &self.order);
+
+        if old_value.is_some() {
+
+            self.count -= 1;
+        }
+
+        old_value
+    }
+}
+
+
+impl<T, O: Clone> Clone for PersistentTst<T, O> {
+
+    fn clone(&self) -> Self {
+
+        self.snapshot()
+    }
+}
+
+
+impl<'a, T, O: LabelOrder> IntoIterator for &'a PersistentTst<T, O> {
+
+    type Item = &'a T;
+    type IntoIter = PersistentTstIterator<'a, 'static, T, O>;
+
+    fn into_iter(self) -> Self::IntoIter {
+
+        self.iter()
+    }
+}
+
+
+/// Iterator of all the searches of a `PersistentTst`, with the same methods as the iterators of
+/// `Tst`.
+pub type PersistentTstIterator<'a, 'b, T, O = CodePointOrder, S = Query> = Walk<'a, 'b, &'a PersistentNode<T>, O, S>;
@@ -0,0 +1,531 @@
+use Tst;
+
+
+/// Turns a word into a code shared by the words which sound alike.
+///
+/// Any `Fn(&str) -> String` closure is an encoder. Prefix completion looks for the codes beginning
+/// with one of the codes given by `encode_prefix`.
+pub trait PhoneticEncoder {
+
+    fn encode(&self, word: &str) -> String;
+
+
+    /// Codes which the code of a word beginning with `prefix` may begin with. By default, the code
+    /// of `prefix` itself, which suits codes built from left to right without looking ahead (as
+    /// Soundex) : encoders whose rules depend on the next letters or on the end of the word must
+    /// give one code for each way the prefix can go on.
+    fn encode_prefix(&self, prefix: &str) -> Vec<String> {
+
+        vec![self.encode(prefix)]
+    }
+}
+
+
+impl<F> PhoneticEncoder for F where F: Fn(&str) -> String {
+
+    fn encode(&self, word: &str) -> String {
+
+        self(word)
+    }
+}
+
+
+//Uppercase ASCII letter for a letter, with its accents removed (a cedilla always sounds as S)
+fn fold_letter(c: char) -> Option<char> {
+
+    let folded = match c {
+
+        'à' | 'â' | 'ä' | 'á' | 'À' | 'Â' | 'Ä' | 'Á' => 'A',
+
+        'é' | 'è' | 'ê' | 'ë' | 'É' | 'È' | 'Ê' | 'Ë' | 'æ' | 'Æ' | 'œ' | 'Œ' => 'E',
+
+        'î' | 'ï' | 'í' | 'Î' | 'Ï' | 'Í' => 'I',
+
+        'ô' | 'ö' | 'ó' | 'Ô' | 'Ö' | 'Ó' => 'O',
+
+        'ù' | 'û' | 'ü' | 'ú' | 'Ù' | 'Û' | 'Ü' | 'Ú' => 'U',
+
+        'ç' | 'Ç' => 'S',
+
+        'ñ' | 'Ñ' => 'N',
+
+        'ÿ' => 'Y',
+
+        c => c.to_ascii_uppercase()
+    };
+
+    if folded.is_ascii_uppercase() { Some(folded) } else { None }
+}
+
+
+/// American Soundex : the first letter of the word followed by up to three digits for the next
+/// consonants (e.g. "R163" for both "Robert" and "Rupert").
+///
+/// Codes are not padded with zeros, so that short prefixes get short codes.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Soundex;
+
+
+fn soundex_digit(letter: char) -> Option<char> {
+
+    match letter {
+
+        'B' | 'F' | 'P' | 'V' => Some('1'),
+
+        'C' | 'G' | 'J' | 'K' | 'Q' | 'S' | 'X' | 'Z' => Some('2'),
+
+        'D' | 'T' => Some('3'),
+
+        'L' => Some('4'),
+
+        'M' | 'N' => Some('5'),
+
+        'R' => Some('6'),
+
+        _ => None
+    }
+}
+
+
+impl PhoneticEncoder for Soundex {
+
+    fn encode(&self, word: &str) -> String {
+
+        let mut letters = word.chars().filter_map(fold_letter);
+        let mut code = String::new();
+
+        let first = match letters.next() {
+
+            None => return code,
+
+            Some(first) => first
+        };
+
+        code.push(first);
+
+        let mut last = soundex_digit(first);
+
+        for letter in letters {
+
+            if code.len() == 4 {
+
+                break;
+            }
+
+            //H and W do not separate consonants with the same digit, vowels do
+            if letter == 'H' || letter == 'W' {
+
+                continue;
+            }
+
+            let digit = soundex_digit(letter);
+
+            if let Some(d) = digit {
+
+                if digit != last {
+
+                    code.push(d);
+                }
+            }
+
+            last = digit;
+        }
+
+        code
+    }
+}
+
+
+/// Phonetic code for French words and names, where letters which sound the same (e.g. "eau",
+/// "au" and "o", or "ph" and "f") share a code, and final silent letters are dropped. "Dupont",
+/// "Dupond" and "Dupon" all get the same code.
+///
+/// As the sound of the last letters of a prefix depends on the next ones, `encode_prefix` gives
+/// the codes of each way the prefix can go on : "Bon" completes to "Bonbon" as well as "Bonnet".
+#[derive(Clone, Copy, Default, Debug)]
+pub struct FrenchPhonetic;
+
+
+fn is_vowel(letter: char) -> bool {
+
+    "AEIOUY".contains(letter)
+}
+
+
+//Sounds for the letters at the start of `letters`, with the count of letters they use
+fn french_sound(letters: &[char]) -> (&'static str, usize) {
+
+    let starts_with = |s: &str| s.chars().enumerate().all(|(i, c)| letters.get(i) == Some(&c));
+
+    //Vowel followed by N or M is nasal, unless a vowel or another N or M follows
+    let is_nasal = |len: usize| match letters.get(len) {
+
+        None => true,
+
+        Some(&next) => !is_vowel(next) && next != 'N' && next != 'M'
+    };
+
+    let soft = |i: usize| i < letters.len() && "EIY".contains(letters[i]);
+
+    if starts_with("EAU") { return ("O", 3); }
+
+    for &(nasal, sound) in [("AIN", "3"), ("EIN", "3"), ("AN", "1"), ("AM", "1"), ("EN", "1"), ("EM", "1"), ("ON", "2"),
+                            ("OM", "2"), ("IN", "3"), ("IM", "3"), ("UN", "3"), ("UM", "3"), ("YN", "3"), ("YM", "3")].iter() {
+
+        if starts_with(nasal) && is_nasal(nasal.len()) {
+
+            return (sound, nasal.len());
+        }
+    }
+
+    for &(letters, sound) in [("AU", "O"), ("OU", "U"), ("OI", "WA"), ("AI", "E"), ("EI", "E"), ("PH", "F"), ("QU", "K"),
+                              ("SCH", "X"), ("CH", "X"), ("SH", "X"), ("GN", "N"), ("TH", "T")].iter() {
+
+        if starts_with(letters) {
+
+            return (sound, letters.len());
+        }
+    }
+
+    match letters[0] {
+
+        'G' if starts_with("GU") && soft(2) => ("G", 2),
+
+        'G' if soft(1) => ("J", 1),
+
+        'C' if soft(1) => ("S", 1),
+
+        'C' | 'Q' => ("K", 1),
+
+        'W' => ("V", 1),
+
+        'Y' => ("I", 1),
+
+        'Z' => ("S", 1),
+
+        'X' => ("KS", 1),
+
+        'H' => ("", 1),
+
+        'A' => ("A", 1), 'B' => ("B", 1), 'D' => ("D", 1), 'E' => ("E", 1), 'F' => ("F", 1), 'G' => ("G", 1),
+        'I' => ("I", 1), 'J' => ("J", 1), 'K' => ("K", 1), 'L' => ("L", 1), 'M' => ("M", 1), 'N' => ("N", 1),
+        'O' => ("O", 1), 'P' => ("P", 1), 'R' => ("R", 1), 'S' => ("S", 1), 'T' => ("T", 1), 'U' => ("U", 1),
+        'V' => ("V", 1),
+
+        _ => ("", 1)
+    }
+}
+
+
+//Letters which change the sound of the ones before them, with 'B' for all the others
+const FOLLOWING_LETTERS: [char; 11] = ['A', 'E', 'I', 'O', 'U', 'Y', 'N', 'M', 'H', 'C', 'B'];
+
+
+//Sounds of the letters, each with the index of its first letter
+fn french_sounds(letters: &[char]) -> Vec<(&'static str, usize)> {
+
+    let mut sounds = Vec::new();
+    let mut i = 0;
+
+    while i < letters.len() {
+
+        let (sound, len) = french_sound(&letters[i..]);
+
+        sounds.push((sound, i));
+        i += len;
+    }
+
+    sounds
+}
+
+
+//Code of the sounds beginning before the letter `end`
+fn french_code(sounds: &[(&str, usize)], end: usize) -> String {
+
+    let mut code = String::new();
+
+    for &(sound, _) in sounds.iter().take_while(|&&(_, start)| start < end) {
+
+        for c in sound.chars() {
+
+            //Double letters sound as one
+            if !code.ends_with(c) {
+
+                code.push(c);
+            }
+        }
+    }
+
+    code
+}
+
+
+impl PhoneticEncoder for FrenchPhonetic {
+
+    fn encode(&self, word: &str) -> String {
+
+        let mut letters: Vec<char> = word.chars().filter_map(fold_letter).collect();
+
+        //Final silent letters : "Dupont(s)", "Bernard", "Roger", "Rose", but "Bonnet" ends as "Bonnè"
+        let mut silent_t = false;
+
+        while letters.len() > 1 && "STDX".contains(letters[letters.len()-1]) {
+
+            silent_t |= letters.pop() == Some('T');
+        }
+
+        if letters.ends_with(&['E', 'R']) || letters.ends_with(&['E', 'Z']) {
+
+            letters.pop();
+        }
+
+        if letters.len() > 1 && letters.ends_with(&['E']) && !silent_t {
+
+            letters.pop();
+        }
+
+        french_code(&french_sounds(&letters), letters.len())
+    }
+
+
+    fn encode_prefix(&self, prefix: &str) -> Vec<String> {
+
+        let letters: Vec<char> = prefix.chars().filter_map(fold_letter).collect();
+
+        //The prefix may be a whole word, with its end rules
+        let mut codes = vec![self.encode(prefix)];
+
+        //Else its last sounds depend on up to three more letters (as "ai" in "aint"), so it is
+        //read with every continuation which matters to the rules, keeping the sounds which begin
+        //in the prefix. Sounds beginning more than three letters before its end do not change
+        let sounds = french_sounds(&letters);
+
+        let tail = sounds.iter().map(|&(_, start)| start).find(|&start| start + 3 >= letters.len()).unwrap_or(letters.len());
+        let head: Vec<(&str, usize)> = sounds.into_iter().filter(|&(_, start)| start < tail).collect();
+
+        let mut continuations = vec![letters[tail..].to_vec()];
+
+        while let Some(continuation) = continuations.pop() {
+
+            let mut sounds = head.clone();
+
+            sounds.extend(french_sounds(&continuation).into_iter().map(|(sound, start)| (sound, tail + start)));
+
+            codes.push(french_code(&sounds, letters.len()));
+
+            //A sound is settled when the (up to four) letters its rules look at are known
+            let last = sounds.iter().map(|&(_, start)| start).filter(|&start| start < letters.len()).max();
+
+            if last.filter(|&last| last + 4 > tail + continuation.len()).is_some() {
+
+                for &letter in FOLLOWING_LETTERS.iter() {
+
+                    let mut longer = continuation.clone();
+
+                    longer.push(letter);
+                    continuations.push(longer);
+                }
+            }
+        }
+
+        codes.sort();
+        codes.dedup();
+
+        codes
+    }
+}
+
+
+/// A map which finds its keys by their sound, with keys stored under their phonetic code.
+///
+/// Keys are kept as is in a main `Tst` (available through `keys`), and a second one maps each
+/// phonetic code to the keys which have it. A sound-alike query encodes the query, then looks up
+/// or completes its code.
+///
+/// Keys with an empty code (such as keys without any letter for `Soundex`) only sound like each
+/// other, and are never completed.
+pub struct PhoneticTst<T, E = Soundex> {
+
+    keys: Tst<T>,
+    codes: Tst<Vec<String>>,
+    uncoded: Vec<String>,
+    encoder: E
+}
+
+
+impl<T> PhoneticTst<T> {
+
+    pub fn new() -> Self {
+
+        PhoneticTst::with_encoder(Soundex)
+    }
+}
+
+
+impl<T, E: PhoneticEncoder + Default> Default for PhoneticTst<T, E> {
+
+    fn default() -> Self {
+
+        PhoneticTst::with_encoder(E::default())
+    }
+}
+
+
+impl<T, E: PhoneticEncoder> PhoneticTst<T, E> {
+
+    pub fn with_encoder(encoder: E) -> Self {
+
+        PhoneticTst { keys: Tst::new(), codes: Tst::new(), uncoded: Vec::new(), encoder }
+    }
+
+
+    pub fn encoder(&self) -> &E {
+
+        &self.encoder
+    }
+
+
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        let old_value = self.keys.insert(key, value);
+
+        if old_value.is_none() {
+
+            let code = self.encoder.encode(key);
+
+            //A `Tst` holds no empty key, so keys with an empty code have their own list
+            if code.is_empty() {
+
+                self.uncoded.push(key.to_string());
+
+            } else {
+
+                match self.codes.get_mut(&code) {
+
+                    Some(origins) => origins.push(key.to_string()),
+
+                    None => { self.codes.insert(&code, vec![key.to_string()]); }
+                }
+            }
+        }
+
+        old_value
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        self.keys.get(key)
+    }
+
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+
+        self.keys.get_mut(key)
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        let old_value = self.keys.remove(key);
+
+        if old_value.is_some() {
+
+            let code = self.encoder.encode(key);
+
+            if code.is_empty() {
+
+                self.uncoded.retain(|origin| origin != key);
+            }
+
+            let now_empty = match self.codes.get_mut(&code) {
+
+                None => false,
+
+                Some(origins) => {
+
+                    origins.retain(|origin| origin != key);
+                    origins.is_empty()
+                }
+            };
+
+            if now_empty {
+
+                self.codes.remove(&code);
+            }
+        }
+
+        old_value
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.keys.len()
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.keys.clear();
+        self.codes.clear();
+        self.uncoded.clear();
+    }
+
+
+    pub fn keys(&self) -> &Tst<T> {
+
+        &self.keys
+    }
+
+
+    /// Returns the keys which sound like `word` (i.e. with the same code), with their values,
+    /// sorted by key.
+    pub fn find_sound_alike(&self, word: &str) -> Vec<(String, &T)> {
+
+        let code = self.encoder.encode(word);
+
+        if code.is_empty() {
+
+            return self.collect(Some(&self.uncoded).into_iter());
+        }
+
+        self.collect(self.codes.get(&code).into_iter())
+    }
+
+
+    /// Returns the keys which begin with something sounding like `prefix` (i.e. whose code begins
+    /// with one of the codes given by `encode_prefix`), with their values, sorted by key.
+    pub fn complete_sound_alike(&self, prefix: &str) -> Vec<(String, &T)> {
+
+        let mut origins = Vec::new();
+
+        for code in self.encoder.encode_prefix(prefix).iter().filter(|code| !code.is_empty()) {
+
+            //Completion skips the keys stored on the code itself, so fetch them first
+            origins.extend(self.codes.get(code));
+            origins.extend(self.codes.iter_complete(code));
+        }
+
+        self.collect(origins.into_iter())
+    }
+
+
+    fn collect<'a, I>(&'a self, origins: I) -> Vec<(String, &'a T)>
+    where I: Iterator<Item = &'a Vec<String>> {
+
+        let mut found: Vec<&str> = origins.flat_map(|keys| keys.iter().map(|s| s.as_str())).collect();
+
+        //A key may come from several codes
+        found.sort();
+        found.dedup();
+
+        found.into_iter().filter_map(|key| self.keys.get(key).map(|value| (key.to_string(), value))).collect()
+    }
+}
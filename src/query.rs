@@ -0,0 +1,658 @@
+use std::str::Chars;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use LabelOrder;
+use SubstitutionCost;
+
+
+//A search shared by the `Tst` variants, which reads the keys char by char. The state tells where
+//the search is in its key (or prefix, or pattern) after the chars of a path
+pub trait Search<'b> {
+
+    type State: Clone;
+
+    //State after one more char, or None if no key going through it can match
+    fn step(&self, state: Self::State, label: char) -> Option<Self::State>;
+
+    //Whether a value found with this state is part of the results
+    fn accepts(&self, state: &Self::State) -> bool;
+
+    //Whether the siblings on the `side` of a node may match, when the node label begins with `first`
+    fn may_go<O: LabelOrder>(&self, state: &Self::State, first: char, side: Ordering, order: &O) -> bool;
+
+
+    //State after the chars of a node label
+    fn step_label<L>(&self, state: &Self::State, label: L) -> Option<Self::State>
+    where L: Iterator<Item = char> {
+
+        let mut state = state.clone();
+
+        for c in label {
+
+            state = self.step(state, c)?;
+        }
+
+        Some(state)
+    }
+}
+
+
+#[derive(Clone, Copy, Debug)]
+pub enum Query {
+
+    All,
+    Complete,
+    Neighbor,
+    Crossword(char)
+}
+
+
+//Position of a search in its key, with the distance left for neighbor searches
+#[derive(Clone, Debug)]
+pub struct Cursor<'b> {
+
+    key_tail: Chars<'b>,
+    tail_len: usize,
+    range: usize,
+    past_end: bool
+}
+
+
+impl<'b> Cursor<'b> {
+
+    pub fn new(key: &'b str, range: usize) -> Self {
+
+        Cursor { key_tail: key.chars(), tail_len: key.chars().count(), range, past_end: false }
+    }
+}
+
+
+impl<'b> Search<'b> for Query {
+
+    type State = Cursor<'b>;
+
+    fn step(&self, mut cursor: Cursor<'b>, label: char) -> Option<Cursor<'b>> {
+
+        if let Query::All = *self {
+
+            return Some(cursor);
+        }
+
+        let expected = cursor.key_tail.next();
+
+        if expected.is_some() {
+
+            cursor.tail_len -= 1;
+        }
+
+        match *self {
+
+            Query::All => (),
+
+            Query::Complete => match expected {
+
+                None => cursor.past_end = true,
+
+                Some(expected) => if expected != label { return None; }
+            },
+
+            Query::Neighbor => if expected != Some(label) {
+
+                if cursor.range == 0 {
+
+                    return None;
+                }
+
+                cursor.range -= 1;
+            },
+
+            Query::Crossword(joker) => match expected {
+
+                None => return None,
+
+                Some(expected) => if expected != joker && expected != label { return None; }
+            }
+        }
+
+        Some(cursor)
+    }
+
+
+    fn accepts(&self, cursor: &Cursor) -> bool {
+
+        match *self {
+
+            Query::All => true,
+
+            Query::Complete => cursor.past_end,
+
+            Query::Neighbor => cursor.tail_len <= cursor.range,
+
+            Query::Crossword(_) => cursor.tail_len == 0
+        }
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, cursor: &Cursor, first: char, side: Ordering, order: &O) -> bool {
+
+        let expected = cursor.key_tail.clone().next();
+
+        match (*self, expected) {
+
+            (Query::All, _) | (Query::Complete, None) => true,
+
+            (Query::Neighbor, _) if cursor.range > 0 => true,
+
+            (Query::Crossword(_), None) | (Query::Neighbor, None) => false,
+
+            (Query::Crossword(joker), Some(expected)) if expected == joker => true,
+
+            (_, Some(expected)) => order.compare(expected, first) == side
+        }
+    }
+}
+
+
+//Cost of a label on a crossword square, where a mismatch is only tolerated if some budget is left
+fn crossword_cost(label: char, square: char, joker: char) -> usize {
+
+    if square == joker || square == label { 0 } else { 1 }
+}
+
+
+//Crossword search tolerating `range` mismatches on non-joker squares
+#[derive(Clone, Copy, Debug)]
+pub struct CrosswordNeighborQuery {
+
+    pub joker: char,
+    pub range: usize
+}
+
+
+//Position of a crossword search in its pattern, with the positions of the mismatches so far
+#[derive(Clone, Debug)]
+pub struct MismatchCursor<'b> {
+
+    key_tail: Chars<'b>,
+    position: usize,
+    pub mismatches: Vec<usize>
+}
+
+
+impl<'b> MismatchCursor<'b> {
+
+    pub fn new(key: &'b str) -> Self {
+
+        MismatchCursor { key_tail: key.chars(), position: 0, mismatches: Vec::new() }
+    }
+}
+
+
+impl<'b> Search<'b> for CrosswordNeighborQuery {
+
+    type State = MismatchCursor<'b>;
+
+    fn step(&self, mut cursor: MismatchCursor<'b>, label: char) -> Option<MismatchCursor<'b>> {
+
+        let square = cursor.key_tail.next()?;
+
+        if crossword_cost(label, square, self.joker) > 0 {
+
+            if cursor.mismatches.len() == self.range {
+
+                return None;
+            }
+
+            cursor.mismatches.push(cursor.position);
+        }
+
+        cursor.position += 1;
+
+        Some(cursor)
+    }
+
+
+    fn accepts(&self, cursor: &MismatchCursor) -> bool {
+
+        cursor.key_tail.as_str().is_empty()
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, cursor: &MismatchCursor, first: char, side: Ordering, order: &O) -> bool {
+
+        match cursor.key_tail.clone().next() {
+
+            None => false,
+
+            Some(square) => square == self.joker || cursor.mismatches.len() < self.range || order.compare(square, first) == side
+        }
+    }
+}
+
+
+//Position of a weighted neighbor search in its key, with the cost of the substitutions so far
+#[derive(Clone, Debug)]
+pub struct CostCursor<'b> {
+
+    key_tail: Chars<'b>,
+    tail_len: usize,
+    cost: f64
+}
+
+
+impl<'b> CostCursor<'b> {
+
+    pub fn new(key: &'b str) -> Self {
+
+        CostCursor { key_tail: key.chars(), tail_len: key.chars().count(), cost: 0.0 }
+    }
+
+
+    //Typed chars left past the end of the key are missing
+    pub fn total(&self) -> f64 {
+
+        self.cost + self.tail_len as f64
+    }
+}
+
+
+//Neighbor search where substitutions have their own cost
+#[derive(Debug)]
+pub struct WeightedNeighborQuery<'w, W: 'w> {
+
+    budget: f64,
+    costs: &'w W
+}
+
+
+impl<'w, W: SubstitutionCost> WeightedNeighborQuery<'w, W> {
+
+    pub fn new(budget: f64, costs: &'w W) -> Self {
+
+        WeightedNeighborQuery { budget, costs }
+    }
+
+
+    //Cost of a node label standing for the typed label, which is missing past the end of the key
+    fn step_cost(&self, label: Option<char>, node_label: char) -> f64 {
+
+        match label {
+
+            None => 1.0,
+
+            Some(label) => if label == node_label { 0.0 } else { self.costs.substitution_cost(label, node_label) }
+        }
+    }
+}
+
+
+impl<'b, 'w, W: SubstitutionCost> Search<'b> for WeightedNeighborQuery<'w, W> {
+
+    type State = CostCursor<'b>;
+
+    fn step(&self, mut cursor: CostCursor<'b>, label: char) -> Option<CostCursor<'b>> {
+
+        let typed = cursor.key_tail.next();
+
+        if typed.is_some() {
+
+            cursor.tail_len -= 1;
+        }
+
+        //Costs only grow along a key, so the whole middle subtree can be skipped
+        cursor.cost += self.step_cost(typed, label);
+
+        if cursor.cost <= self.budget { Some(cursor) } else { None }
+    }
+
+
+    fn accepts(&self, cursor: &CostCursor) -> bool {
+
+        cursor.total() <= self.budget
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, _cursor: &CostCursor, _first: char, _side: Ordering, _order: &O) -> bool {
+
+        true
+    }
+}
+
+
+//Anagram search, whose state is the multiset of letters left
+#[derive(Clone, Copy, Debug)]
+pub struct AnagramQuery;
+
+
+impl<'b> Search<'b> for AnagramQuery {
+
+    type State = Vec<char>;
+
+    fn step(&self, mut letters: Vec<char>, label: char) -> Option<Vec<char>> {
+
+        let pos = letters.iter().position(|&c| c == label)?;
+
+        letters.swap_remove(pos);
+
+        Some(letters)
+    }
+
+
+    fn accepts(&self, letters: &Vec<char>) -> bool {
+
+        letters.is_empty()
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, letters: &Vec<char>, first: char, side: Ordering, order: &O) -> bool {
+
+        letters.iter().any(|&c| order.compare(c, first) == side)
+    }
+}
+
+
+//Tiles left on a Scrabble rack, and squares left in the crossword pattern (if any) the word must fit in.
+//Jokers in the rack are blank tiles, jokers in the pattern are empty squares to be filled from the rack
+#[derive(Clone, Debug)]
+struct RackState<'b> {
+
+    letters: Vec<char>,
+    blanks: usize,
+    square: Option<char>,
+    pattern: Option<Chars<'b>>,
+    joker: char
+}
+
+
+impl<'b> RackState<'b> {
+
+    fn new(rack: &str, pattern: Option<&'b str>, joker: char) -> Option<Self> {
+
+        let mut state = RackState { letters: Vec::new(), blanks: 0, square: None, pattern: None, joker };
+
+        for c in rack.chars() {
+
+            if c == joker { state.blanks += 1; } else { state.letters.push(c); }
+        }
+
+        if let Some(pattern) = pattern {
+
+            let mut pattern = pattern.chars();
+
+            state.square = Some(pattern.next()?);
+            state.pattern = Some(pattern);
+        }
+
+        if state.letters.is_empty() && state.blanks == 0 && state.square.is_none() {
+
+            None
+
+        } else {
+
+            Some(state)
+        }
+    }
+
+
+    fn board_letter(&self) -> Option<char> {
+
+        match self.square {
+
+            Some(c) if c != self.joker => Some(c),
+
+            _ => None
+        }
+    }
+
+
+    //Whether some word may go through the left or right sibling of a node with this label
+    fn has_side<O: LabelOrder>(&self, label: char, order: &O, side: Ordering) -> bool {
+
+        match self.board_letter() {
+
+            Some(c) => order.compare(c, label) == side,
+
+            None => self.blanks > 0 || self.letters.iter().any(|&c| order.compare(c, label) == side)
+        }
+    }
+
+
+    //Puts the label on the current square, using a rack letter (or else a blank) if the square is empty
+    fn place(&self, label: char) -> Option<(Vec<char>, usize)> {
+
+        match self.board_letter() {
+
+            Some(c) => if c == label { Some((self.letters.clone(), self.blanks)) } else { None },
+
+            None => match self.letters.iter().position(|&c| c == label) {
+
+                Some(pos) => {
+
+                    let mut letters = self.letters.clone();
+                    letters.swap_remove(pos);
+                    Some((letters, self.blanks))
+                }
+
+                None => if self.blanks > 0 { Some((self.letters.clone(), self.blanks-1)) } else { None }
+            }
+        }
+    }
+
+
+    fn is_word_end(&self) -> bool {
+
+        match self.pattern {
+
+            None => true,
+
+            Some(ref pattern) => pattern.as_str().is_empty()
+        }
+    }
+
+
+    //State of the next square, once the current one holds a letter
+    fn next(&self, (letters, blanks): (Vec<char>, usize)) -> Option<Self> {
+
+        let (square, pattern) = match self.pattern {
+
+            None => {
+
+                if letters.is_empty() && blanks == 0 {
+
+                    return None;
+                }
+
+                (None, None)
+            }
+
+            Some(ref pattern) => {
+
+                let mut pattern = pattern.clone();
+                (Some(pattern.next()?), Some(pattern))
+            }
+        };
+
+        Some(RackState { letters, blanks, square, pattern, joker: self.joker })
+    }
+}
+
+
+//Scrabble rack search, with or without a crossword pattern
+#[derive(Clone, Copy, Debug)]
+pub struct RackQuery;
+
+
+//Rack state before the next square (None once the word cannot go further), and whether the path
+//so far is a whole word
+#[derive(Clone, Debug)]
+pub struct RackCursor<'b> {
+
+    rack: Option<RackState<'b>>,
+    word_end: bool
+}
+
+
+impl<'b> RackCursor<'b> {
+
+    pub fn new(rack: &str, pattern: Option<&'b str>, joker: char) -> Option<Self> {
+
+        RackState::new(rack, pattern, joker).map(|rack| RackCursor { rack: Some(rack), word_end: false })
+    }
+}
+
+
+impl<'b> Search<'b> for RackQuery {
+
+    type State = RackCursor<'b>;
+
+    fn step(&self, cursor: RackCursor<'b>, label: char) -> Option<RackCursor<'b>> {
+
+        let rack = cursor.rack?;
+        let tiles = rack.place(label)?;
+
+        Some(RackCursor { word_end: rack.is_word_end(), rack: rack.next(tiles) })
+    }
+
+
+    fn accepts(&self, cursor: &RackCursor) -> bool {
+
+        cursor.word_end
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, cursor: &RackCursor, first: char, side: Ordering, order: &O) -> bool {
+
+        match cursor.rack {
+
+            None => false,
+
+            Some(ref rack) => rack.has_side(first, order, side)
+        }
+    }
+}
+
+
+//Edit distances between the typed prefix and the current tree path, as in the Damerau-Levenshtein
+//algorithm (optimal string alignment version, where swapping two letters costs 1). Substitutions
+//cost what the cost model says, other edits cost 1
+#[derive(Debug)]
+struct FuzzyState {
+
+    row: Vec<f64>,
+    previous_row: Vec<f64>,
+    previous_label: Option<char>,
+    best: f64
+}
+
+
+impl FuzzyState {
+
+    fn new(prefix: &[char]) -> Self {
+
+        FuzzyState {
+
+            row: (0..prefix.len()+1).map(|j| j as f64).collect(),
+            previous_row: Vec::new(),
+            previous_label: None,
+            best: prefix.len() as f64
+        }
+    }
+
+
+    fn next<W: SubstitutionCost>(&self, prefix: &[char], label: char, costs: &W) -> Self {
+
+        let mut row = vec![self.row[0]+1.0; prefix.len()+1];
+
+        for j in 1..prefix.len()+1 {
+
+            let cost = if prefix[j-1] == label { 0.0 } else { costs.substitution_cost(prefix[j-1], label) };
+
+            row[j] = (self.row[j]+1.0).min(row[j-1]+1.0).min(self.row[j-1]+cost);
+
+            if j > 1 && self.previous_label == Some(prefix[j-1]) && label == prefix[j-2] {
+
+                row[j] = row[j].min(self.previous_row[j-2]+1.0);
+            }
+        }
+
+        let best = self.best.min(row[prefix.len()]);
+
+        FuzzyState { previous_row: self.row.clone(), row, previous_label: Some(label), best }
+    }
+
+
+    //Whether some longer path could still be close enough to the prefix
+    fn may_match(&self, budget: f64) -> bool {
+
+        self.best <= budget || self.row.iter().any(|&d| d <= budget)
+    }
+}
+
+
+//Completion of the prefixes within `budget` edits of `prefix`
+#[derive(Debug)]
+pub struct FuzzyCompleteQuery<'w, W: 'w> {
+
+    prefix: Vec<char>,
+    budget: f64,
+    costs: &'w W
+}
+
+
+//Edit distances between the prefix and the current path
+#[derive(Clone, Debug)]
+pub struct FuzzyCursor {
+
+    state: Rc<FuzzyState>
+}
+
+
+impl FuzzyCursor {
+
+    pub fn cost(&self) -> f64 {
+
+        self.state.best
+    }
+}
+
+
+impl<'w, W> FuzzyCompleteQuery<'w, W> {
+
+    pub fn new(prefix: &str, budget: f64, costs: &'w W) -> Self {
+
+        FuzzyCompleteQuery { prefix: prefix.chars().collect(), budget, costs }
+    }
+
+
+    pub fn start(&self) -> FuzzyCursor {
+
+        FuzzyCursor { state: Rc::new(FuzzyState::new(&self.prefix)) }
+    }
+}
+
+
+impl<'b, 'w, W: SubstitutionCost> Search<'b> for FuzzyCompleteQuery<'w, W> {
+
+    type State = FuzzyCursor;
+
+    //Paths too far from the prefix are left as soon as no longer path can get close enough
+    fn step(&self, cursor: FuzzyCursor, label: char) -> Option<FuzzyCursor> {
+
+        let state = cursor.state.next(&self.prefix, label, self.costs);
+
+        if state.may_match(self.budget) { Some(FuzzyCursor { state: Rc::new(state) }) } else { None }
+    }
+
+
+    fn accepts(&self, cursor: &FuzzyCursor) -> bool {
+
+        cursor.cost() <= self.budget
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, _cursor: &FuzzyCursor, _first: char, _side: Ordering, _order: &O) -> bool {
+
+        true
+    }
+}
@@ -0,0 +1,450 @@
+use std::str::Chars;
+use std::cmp::Ordering;
+use std::cmp::Ordering::Less;
+use std::cmp::Ordering::Equal;
+use std::cmp::Ordering::Greater;
+use std::cmp::max;
+use std::collections::BinaryHeap;
+
+use LabelOrder;
+use CodePointOrder;
+use walk;
+
+
+/// A map whose values have a weight, which can complete a prefix with its best scored keys.
+///
+/// Weights are computed from values by a closure given to `new`. Each node caches the greatest
+/// weight found in its subtree, so that `top_k_complete` can walk the tree best-first and stop as
+/// soon as it has found enough keys. Labels are compared by code point, unless the map is created
+/// `with_order`.
+pub struct ScoredTst<T, W, F, O = CodePointOrder> {
+
+    root: ScoredLink<T, W>,
+    count: usize,
+    weight: F,
+    order: O
+}
+
+
+type ScoredLink<T, W> = Option<Box<ScoredNode<T, W>>>;
+
+
+struct ScoredNode<T, W> {
+
+    label: char,
+    value: Option<T>,
+    max_weight: Option<W>,
+    left: ScoredLink<T, W>,
+    middle: ScoredLink<T, W>,
+    right: ScoredLink<T, W>
+}
+
+
+fn max_weight<T, W: Ord + Copy>(link: &ScoredLink<T, W>) -> Option<W> {
+
+    match *link {
+
+        None => None,
+
+        Some(ref node) => node.max_weight
+    }
+}
+
+
+fn update_max_weight<T, W, F>(node: &mut ScoredNode<T, W>, weight: &F)
+where W: Ord + Copy, F: Fn(&T) -> W {
+
+    let own_weight = node.value.as_ref().map(weight);
+
+    node.max_weight = [own_weight, max_weight(&node.left), max_weight(&node.middle), max_weight(&node.right)]
+        .iter().fold(None, |acc, w| max(acc, *w));
+}
+
+
+fn insert_r<T, W, F, O>(link: &mut ScoredLink<T, W>, label: char, mut key_tail: Chars, value: T, weight: &F, order: &O) -> Option<T>
+where W: Ord + Copy, F: Fn(&T) -> W, O: LabelOrder {
+
+    if link.is_none() {
+
+        *link = Some(Box::new(ScoredNode { label, value: None, max_weight: None, left: None, middle: None, right: None }));
+    }
+
+    let node = link.as_mut().unwrap();
+
+    let old_value = match order.compare(label, node.label) {
+
+        Less => insert_r(&mut node.left, label, key_tail, value, weight, order),
+
+        Greater => insert_r(&mut node.right, label, key_tail, value, weight, order),
+
+        Equal => match key_tail.next() {
+
+            None => node.value.replace(value),
+
+            Some(label) => insert_r(&mut node.middle, label, key_tail, value, weight, order)
+        }
+    };
+
+    update_max_weight(node, weight);
+
+    old_value
+}
+
+
+fn get_r<'a, T, W, O: LabelOrder>(link: &'a ScoredLink<T, W>, label: char, key_tail: &mut Chars, order: &O) -> Option<&'a T> {
+
+    match *link {
+
+        None => None,
+
+        Some(ref node) => match order.compare(label, node.label) {
+
+            Less => get_r(&node.left, label, key_tail, order),
+
+            Greater => get_r(&node.right, label, key_tail, order),
+
+            Equal => match key_tail.next() {
+
+                None => node.value.as_ref(),
+
+                Some(label) => get_r(&node.middle, label, key_tail, order)
+            }
+        }
+    }
+}
+
+
+fn remove_r<T, W, F, O>(link: &mut ScoredLink<T, W>, label: char, key_tail: &mut Chars, weight: &F, order: &O) -> (bool, Option<T>)
+where W: Ord + Copy, F: Fn(&T) -> W, O: LabelOrder {
+
+    match *link {
+
+        None => (false, None),
+
+        Some(ref mut node) => {
+
+            let old_value = match order.compare(label, node.label) {
+
+                Less => {
+
+                    let (prune, old_value) = remove_r(&mut node.left, label, key_tail, weight, order);
+
+                    if prune {
+
+                        node.left = None;
+                    }
+
+                    old_value
+                }
+
+                Greater => {
+
+                    let (prune, old_value) = remove_r(&mut node.right, label, key_tail, weight, order);
+
+                    if prune {
+
+                        node.right = None;
+                    }
+
+                    old_value
+                }
+
+                Equal => match key_tail.next() {
+
+                    None => node.value.take(),
+
+                    Some(label) => {
+
+                        let (prune, old_value) = remove_r(&mut node.middle, label, key_tail, weight, order);
+
+                        if prune {
+
+                            node.middle = None;
+                        }
+
+                        old_value
+                    }
+                }
+            };
+
+            update_max_weight(node, weight);
+
+            let prune = node.value.is_none() && node.left.is_none() && node.middle.is_none() && node.right.is_none();
+            (prune, old_value)
+        }
+    }
+}
+
+
+fn find_complete_root_r<'a, T, W, O>(link: &'a ScoredLink<T, W>, label: char, mut key_tail: Chars, order: &O) -> &'a ScoredLink<T, W>
+where O: LabelOrder {
+
+    match *link {
+
+        None => link,
+
+        Some(ref node) => match order.compare(label, node.label) {
+
+            Less => find_complete_root_r(&node.left, label, key_tail, order),
+
+            Greater => find_complete_root_r(&node.right, label, key_tail, order),
+
+            Equal => match key_tail.next() {
+
+                None => &node.middle,
+
+                Some(label) => find_complete_root_r(&node.middle, label, key_tail, order)
+            }
+        }
+    }
+}
+
+
+//A subtree still to explore, or a value found along the way
+enum Candidate<'a, T: 'a, W: 'a> {
+
+    Subtree(&'a ScoredNode<T, W>),
+    Value(&'a T)
+}
+
+
+//A pending step of the best-first search. For a subtree, `weight` is its best weight and all its
+//keys begin with `key` (and are greater). For a value, they are its own weight and key
+struct Step<'a, T: 'a, W: 'a, O: 'a> {
+
+    weight: W,
+    key: String,
+    candidate: Candidate<'a, T, W>,
+    order: &'a O
+}
+
+
+impl<'a, T, W, O> Step<'a, T, W, O> {
+
+    fn is_value(&self) -> bool {
+
+        match self.candidate {
+
+            Candidate::Value(_) => true,
+
+            Candidate::Subtree(_) => false
+        }
+    }
+}
+
+
+//Max-heap order : best weight first, then smallest key, then values before subtrees of the same key
+impl<'a, T, W: Ord, O: LabelOrder> Ord for Step<'a, T, W, O> {
+
+    fn cmp(&self, other: &Self) -> Ordering {
+
+        self.weight.cmp(&other.weight)
+            .then_with(|| walk::compare_keys(self.order, other.key.chars(), self.key.chars()))
+            .then_with(|| self.is_value().cmp(&other.is_value()))
+    }
+}
+
+
+impl<'a, T, W: Ord, O: LabelOrder> PartialOrd for Step<'a, T, W, O> {
+
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+
+        Some(self.cmp(other))
+    }
+}
+
+
+impl<'a, T, W: Ord, O: LabelOrder> PartialEq for Step<'a, T, W, O> {
+
+    fn eq(&self, other: &Self) -> bool {
+
+        self.cmp(other) == Equal
+    }
+}
+
+
+impl<'a, T, W: Ord, O: LabelOrder> Eq for Step<'a, T, W, O> {}
+
+
+fn push_subtree<'a, T, W, O>(todo: &mut BinaryHeap<Step<'a, T, W, O>>, link: &'a ScoredLink<T, W>, key: String, order: &'a O)
+where W: Ord + Copy, O: LabelOrder {
+
+    if let Some(ref node) = *link {
+
+        if let Some(weight) = node.max_weight {
+
+            todo.push(Step { weight, key, candidate: Candidate::Subtree(node), order });
+        }
+    }
+}
+
+
+impl<T, W, F> ScoredTst<T, W, F>
+where W: Ord + Copy, F: Fn(&T) -> W {
+
+    pub fn new(weight: F) -> Self {
+
+        ScoredTst::with_order(weight, CodePointOrder)
+    }
+}
+
+
+impl<T, W, F, O> ScoredTst<T, W, F, O>
+where W: Ord + Copy, F: Fn(&T) -> W, O: LabelOrder {
+
+    pub fn with_order(weight: F, order: O) -> Self {
+
+        ScoredTst { root: None, count: 0, weight, order }
+    }
+
+
+    pub fn order(&self) -> &O {
+
+        &self.order
+    }
+
+
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        let mut key_tail = key.chars();
+
+        match key_tail.next() {
+
+            None => Some(value),
+
+            Some(label) => {
+
+                let old_value = insert_r(&mut self.root, label, key_tail, value, &self.weight, &self.order);
+
+                if old_value.is_none() {
+
+                    self.count += 1;
+                }
+
+                old_value
+            }
+        }
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        let mut key_tail = key.chars();
+
+        match key_tail.next() {
+
+            None => None,
+
+            Some(label) => get_r(&self.root, label, &mut key_tail, &self.order)
+        }
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        let mut key_tail = key.chars();
+
+        let (prune, old_value) = match key_tail.next() {
+
+            None => (false, None),
+
+            Some(label) => remove_r(&mut self.root, label, &mut key_tail, &self.weight, &self.order)
+        };
+
+        if prune {
+
+            self.root = None;
+        }
+
+        if old_value.is_some() {
+
+            self.count -= 1;
+        }
+
+        old_value
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.count
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.root = None;
+        self.count = 0;
+    }
+
+
+    /// Returns the greatest weight among the values whose keys begin with `prefix`.
+    pub fn max_weight(&self, prefix: &str) -> Option<W> {
+
+        max_weight(self.complete_root(prefix))
+    }
+
+
+    /// Returns the `k` best scored keys beginning with `prefix`, with their values.
+    ///
+    /// Results are sorted by decreasing weight, then by key in the order of the map. As with `iter_complete`, a key equal
+    /// to `prefix` is not part of the completions.
+    pub fn top_k_complete(&self, prefix: &str, k: usize) -> Vec<(String, &T)> {
+
+        let mut found = Vec::new();
+        let mut todo = BinaryHeap::new();
+
+        push_subtree(&mut todo, self.complete_root(prefix), prefix.to_string(), &self.order);
+
+        while found.len() < k {
+
+            let step = match todo.pop() {
+
+                None => break,
+
+                Some(step) => step
+            };
+
+            match step.candidate {
+
+                Candidate::Value(value) => found.push((step.key, value)),
+
+                Candidate::Subtree(node) => {
+
+                    let mut key = step.key.clone();
+                    key.push(node.label);
+
+                    if let Some(ref value) = node.value {
+
+                        todo.push(Step { weight: (self.weight)(value), key: key.clone(), candidate: Candidate::Value(value), order: &self.order });
+                    }
+
+                    push_subtree(&mut todo, &node.left, step.key.clone(), &self.order);
+                    push_subtree(&mut todo, &node.middle, key, &self.order);
+                    push_subtree(&mut todo, &node.right, step.key, &self.order);
+                }
+            }
+        }
+
+        found
+    }
+
+
+    fn complete_root(&self, prefix: &str) -> &ScoredLink<T, W> {
+
+        let mut key_tail = prefix.chars();
+
+        match key_tail.next() {
+
+            None => &self.root,
+
+            Some(label) => find_complete_root_r(&self.root, label, key_tail, &self.order)
+        }
+    }
+}
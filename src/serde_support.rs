@@ -0,0 +1,155 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use serde::de::{Deserialize, Deserializer, Error, MapAccess, SeqAccess, Visitor};
+use serde::ser::{Serialize, Serializer, SerializeMap, SerializeSeq};
+
+use Tst;
+use Link;
+use LabelOrder;
+use Balancing;
+use CodePointOrder;
+use binary::{node_flags, NodeStack};
+
+
+/// Serialized as a map of keys to values, so that a `Tst` looks like any other map in JSON or TOML.
+impl<T: Serialize, O: LabelOrder, B: Balancing> Serialize for Tst<T, O, B> {
+
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+
+        let mut map = serializer.serialize_map(Some(self.len()))?;
+        let mut it = self.iter();
+
+        while let Some(value) = it.next() {
+
+            map.serialize_entry(&it.current_key(), value)?;
+        }
+
+        map.end()
+    }
+}
+
+
+struct TstVisitor<T, O> {
+
+    marker: PhantomData<(T, O)>
+}
+
+
+impl<'de, T: Deserialize<'de>, O: LabelOrder + Default> Visitor<'de> for TstVisitor<T, O> {
+
+    type Value = Tst<T, O>;
+
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        f.write_str("a map of string keys to values")
+    }
+
+
+    fn visit_map<A: MapAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+
+        let mut tst = Tst::with_order(O::default());
+
+        while let Some((key, value)) = access.next_entry::<String, T>()? {
+
+            tst.insert(&key, value);
+        }
+
+        Ok(tst)
+    }
+}
+
+
+impl<'de, T: Deserialize<'de>, O: LabelOrder + Default> Deserialize<'de> for Tst<T, O> {
+
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+
+        deserializer.deserialize_map(TstVisitor { marker: PhantomData })
+    }
+}
+
+
+/// Serializes a `Tst` as its raw nodes instead of a map, which loads faster as no key is
+/// inserted again, but only makes sense to this crate.
+///
+/// Nodes come in post-order, each as a `(label, flags, value)` tuple, with the same flags as
+/// `Tst::write_to`. A tree must be read back with the order it was written with, as sibling
+/// labels out of order are an error.
+pub struct RawNodes<T, O = CodePointOrder>(pub Tst<T, O>);
+
+
+fn serialize_r<T: Serialize, S: SerializeSeq>(link: &Link<T>, seq: &mut S) -> Result<(), S::Error> {
+
+    if let Some(ref node) = *link {
+
+        serialize_r(&node.left, seq)?;
+        serialize_r(&node.middle, seq)?;
+        serialize_r(&node.right, seq)?;
+
+        seq.serialize_element(&(node.label, node_flags(node), &node.value))?;
+    }
+
+    Ok(())
+}
+
+
+impl<T: Serialize, O: LabelOrder> Serialize for RawNodes<T, O> {
+
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+
+        let mut seq = serializer.serialize_seq(Some(self.0.stat().count.nodes))?;
+
+        serialize_r(&self.0.root, &mut seq)?;
+
+        seq.end()
+    }
+}
+
+
+struct RawNodesVisitor<T, O> {
+
+    marker: PhantomData<(T, O)>
+}
+
+
+impl<'de, T: Deserialize<'de>, O: LabelOrder + Default> Visitor<'de> for RawNodesVisitor<T, O> {
+
+    type Value = RawNodes<T, O>;
+
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+
+        f.write_str("a sequence of (label, flags, value) nodes")
+    }
+
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut access: A) -> Result<Self::Value, A::Error> {
+
+        let order = O::default();
+        let mut stack = NodeStack::new(&order);
+
+        while let Some((label, flags, value)) = access.next_element::<(char, u8, Option<T>)>()? {
+
+            stack.push(label, flags, value).map_err(A::Error::custom)?;
+        }
+
+        let (root, count) = stack.finish().map_err(A::Error::custom)?;
+
+        let mut tst = Tst::with_order(order);
+
+        tst.root = root;
+        tst.count = count as usize;
+
+        Ok(RawNodes(tst))
+    }
+}
+
+
+impl<'de, T: Deserialize<'de>, O: LabelOrder + Default> Deserialize<'de> for RawNodes<T, O> {
+
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+
+        deserializer.deserialize_seq(RawNodesVisitor { marker: PhantomData })
+    }
+}
@@ -0,0 +1,170 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use arc_swap::ArcSwap;
+
+use PersistentTst;
+
+
+//Last published version, and its generation number, both read and swapped without locking
+struct Published<T> {
+
+    generation: AtomicUsize,
+    snapshot: ArcSwap<PersistentTst<T>>
+}
+
+
+/// The single writer of a map read from many threads, which publishes a new version of it after
+/// each update or batch of updates.
+///
+/// The map is a `PersistentTst`, so that publishing a version is O(1) and readers holding an older
+/// one are never disturbed : an update only copies the nodes on the path of its key, once per
+/// batch. Readers get their versions from `SnapshotReader` handles.
+pub struct SnapshotPublisher<T> {
+
+    working: PersistentTst<T>,
+    published: Arc<Published<T>>
+}
+
+
+impl<T> SnapshotPublisher<T> {
+
+    pub fn new() -> Self {
+
+        SnapshotPublisher::from_map(PersistentTst::new())
+    }
+
+
+    /// Publishes `map` as the first version.
+    pub fn from_map(map: PersistentTst<T>) -> Self {
+
+        let published = Published { generation: AtomicUsize::new(0), snapshot: ArcSwap::from_pointee(map.snapshot()) };
+
+        SnapshotPublisher { working: map, published: Arc::new(published) }
+    }
+
+
+    /// A new handle on the published versions, which can be sent to another thread.
+    pub fn reader(&self) -> SnapshotReader<T> {
+
+        SnapshotReader { published: Arc::clone(&self.published), generation: self.generation(), snapshot: self.published.snapshot.load_full() }
+    }
+
+
+    /// The map as last published.
+    pub fn map(&self) -> &PersistentTst<T> {
+
+        &self.working
+    }
+
+
+    /// Count of versions published since the first one.
+    pub fn generation(&self) -> usize {
+
+        self.published.generation.load(Ordering::Acquire)
+    }
+
+
+    /// Applies all the updates of `update` to the map, then publishes the result as one version,
+    /// readers never seeing the updates in between.
+    pub fn batch<R, F>(&mut self, update: F) -> R
+    where F: FnOnce (&mut PersistentTst<T>) -> R {
+
+        let result = update(&mut self.working);
+
+        self.publish();
+
+        result
+    }
+
+
+    fn publish(&self) {
+
+        //The previous version is freed by the last reader leaving it
+        self.published.snapshot.store(Arc::new(self.working.snapshot()));
+
+        //After the swap, so that a reader seeing the new generation finds the new version
+        self.published.generation.fetch_add(1, Ordering::Release);
+    }
+}
+
+
+impl<T> Default for SnapshotPublisher<T> {
+
+    fn default() -> Self {
+
+        SnapshotPublisher::new()
+    }
+}
+
+
+impl<T: Clone> SnapshotPublisher<T> {
+
+    /// Inserts `key` and publishes the result, as a batch of one update.
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        self.batch(|map| map.insert(key, value))
+    }
+
+
+    /// Removes `key` and publishes the result, as a batch of one update.
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        self.batch(|map| map.remove(key))
+    }
+}
+
+
+/// A reader of the versions of a map published by a `SnapshotPublisher`.
+///
+/// Each reader keeps the version it last saw. Checking for a newer one is a single atomic load,
+/// and taking it never locks, so that readers never wait for the writer nor for each other.
+pub struct SnapshotReader<T> {
+
+    published: Arc<Published<T>>,
+    generation: usize,
+    snapshot: Arc<PersistentTst<T>>
+}
+
+
+impl<T> SnapshotReader<T> {
+
+    /// The last published version of the map, which stays the same until the next call.
+    pub fn load(&mut self) -> &PersistentTst<T> {
+
+        let generation = self.published.generation.load(Ordering::Acquire);
+
+        if generation != self.generation {
+
+            self.snapshot = self.published.snapshot.load_full();
+            self.generation = generation;
+        }
+
+        &self.snapshot
+    }
+
+
+    /// The last published version of the map, which can be kept as long as needed.
+    pub fn snapshot(&mut self) -> Arc<PersistentTst<T>> {
+
+        self.load();
+
+        Arc::clone(&self.snapshot)
+    }
+
+
+    /// Generation of the version given by the last `load` or `snapshot`.
+    pub fn generation(&self) -> usize {
+
+        self.generation
+    }
+}
+
+
+impl<T> Clone for SnapshotReader<T> {
+
+    fn clone(&self) -> Self {
+
+        SnapshotReader { published: Arc::clone(&self.published), generation: self.generation, snapshot: Arc::clone(&self.snapshot) }
+    }
+}
@@ -0,0 +1,202 @@
+use std::cmp::max;
+use std::cmp::Reverse;
+use std::cmp::Ordering;
+use std::rc::Rc;
+
+use LabelOrder;
+use query::Search;
+use walk::{self, Walk, WalkNode};
+
+
+const SCORE_MATCH: i32 = 16;
+const PENALTY_GAP: i32 = 1;
+const BONUS_CONSECUTIVE: i32 = 4;
+const BONUS_BOUNDARY: i32 = 8;
+const BONUS_CAMEL_CASE: i32 = 7;
+const BONUS_PREFIX: i32 = 8;
+
+
+//Bonus for a match at a key position, given the label before it (None at the start of the key)
+fn position_bonus(previous: Option<char>, label: char) -> i32 {
+
+    match previous {
+
+        None => BONUS_PREFIX + BONUS_BOUNDARY,
+
+        Some(previous) => {
+
+            if !previous.is_alphanumeric() && label.is_alphanumeric() {
+
+                BONUS_BOUNDARY
+
+            } else if (previous.is_lowercase() && label.is_uppercase()) || (!previous.is_numeric() && label.is_numeric()) {
+
+                BONUS_CAMEL_CASE
+
+            } else {
+
+                0
+            }
+        }
+    }
+}
+
+
+//Scores of the best alignments of the query on a key prefix. For each count j of matched query
+//chars, `matched[j]` is the best score where the last key char is matched with query[j-1] (with
+//the bonus of the first char of its run of consecutive matches), and `best[j]` is the best score
+//whatever the last key char is
+#[derive(Debug)]
+struct Row {
+
+    matched: Vec<Option<(i32, i32)>>,
+    best: Vec<Option<i32>>
+}
+
+
+struct SubsequenceQuery {
+
+    query: Vec<char>,
+    ignore_case: bool
+}
+
+
+
+impl SubsequenceQuery {
+
+    //Smart case, as in fzf : a query with no uppercase letter ignores case
+    fn new(query: &str) -> Self {
+
+        SubsequenceQuery {
+
+            query: query.chars().collect(),
+            ignore_case: !query.chars().any(|c| c.is_uppercase())
+        }
+    }
+
+
+    fn is_match(&self, q: char, label: char) -> bool {
+
+        q == label || (self.ignore_case && label.to_lowercase().eq(q.to_lowercase()))
+    }
+
+
+    fn first_row(&self) -> Row {
+
+        let mut row = Row { matched: vec![None; self.query.len()+1], best: vec![None; self.query.len()+1] };
+        row.best[0] = Some(0);
+        row
+    }
+
+
+    //Fills `next` (a row of the same length) with the scores after one more key char
+    fn next_row(&self, row: &Row, previous: Option<char>, label: char, next: &mut Row) {
+
+        let m = self.query.len();
+
+        next.best[0] = Some(0);
+
+        for j in 1..m+1 {
+
+            next.matched[j] = None;
+
+            if self.is_match(self.query[j-1], label) {
+
+                let bonus = position_bonus(previous, label);
+
+                let after_gap = row.best[j-1].map(|s| (s + SCORE_MATCH + bonus, bonus));
+
+                //As in fzf, a run of consecutive matches keeps the bonus of its first char, so
+                //that a contiguous match at the start of a word beats a scattered one
+                let consecutive = if j > 1 {
+
+                    row.matched[j-1].map(|(s, run_bonus)| {
+
+                        let run_bonus = max(run_bonus, bonus);
+
+                        (s + SCORE_MATCH + max(run_bonus, BONUS_CONSECUTIVE), run_bonus)
+                    })
+
+                } else { None };
+
+                next.matched[j] = max(after_gap, consecutive);
+            }
+
+            //Skipped chars are only penalized between the first and the last matches
+            let skipped = match row.best[j] {
+
+                None => None,
+
+                Some(s) => if j == m { Some(s) } else { Some(s - PENALTY_GAP) }
+            };
+
+            next.best[j] = max(next.matched[j].map(|(s, _)| s), skipped);
+        }
+    }
+
+
+    fn score(&self, row: &Row) -> Option<i32> {
+
+        row.best[self.query.len()]
+    }
+}
+
+
+//Scores of the key so far, with its last char
+#[derive(Clone, Debug)]
+pub struct SubsequenceCursor {
+
+    row: Rc<Row>,
+    previous: Option<char>
+}
+
+
+impl<'b> Search<'b> for SubsequenceQuery {
+
+    type State = SubsequenceCursor;
+
+    fn step(&self, cursor: SubsequenceCursor, label: char) -> Option<SubsequenceCursor> {
+
+        let mut row = self.first_row();
+
+        self.next_row(&cursor.row, cursor.previous, label, &mut row);
+
+        Some(SubsequenceCursor { row: Rc::new(row), previous: Some(label) })
+    }
+
+
+    fn accepts(&self, cursor: &SubsequenceCursor) -> bool {
+
+        self.score(&cursor.row).is_some()
+    }
+
+
+    fn may_go<O: LabelOrder>(&self, _cursor: &SubsequenceCursor, _first: char, _side: Ordering, _order: &O) -> bool {
+
+        true
+    }
+}
+
+
+//`find_subsequence` of every `Tst` variant
+pub fn find_subsequence<'a, N, O>(root: Option<N>, query: &str, order: &'a O) -> Vec<(String, &'a N::Value, i32)>
+where N: WalkNode<'a>, O: LabelOrder {
+
+    let query = SubsequenceQuery::new(query);
+    let cursor = SubsequenceCursor { row: Rc::new(query.first_row()), previous: None };
+
+    walk::collect(Walk::new(root, query, Some(cursor), order)).into_iter()
+        .filter_map(|(key, value, cursor)| cursor.row.best.last().and_then(|&score| score).map(|score| (key, value, score)))
+        .collect()
+}
+
+
+//Keeps the `k` best scored keys, by decreasing score
+pub fn top_k<V>(mut found: Vec<(String, V, i32)>, k: usize) -> Vec<(String, V, i32)> {
+
+    //Stable sort, so that keys with the same score stay in tree order
+    found.sort_by_key(|&(_, _, score)| Reverse(score));
+    found.truncate(k);
+
+    found
+}
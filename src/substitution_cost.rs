@@ -0,0 +1,154 @@
+use std::collections::HashMap;
+
+
+/// Cost of a typed char standing for a different label, used by the weighted searches.
+///
+/// Any closure taking the typed char and the label of the tree is a cost model. Missing or extra
+/// chars always cost 1, so costs are best kept between 0 and 1.
+pub trait SubstitutionCost {
+
+    fn substitution_cost(&self, typed: char, label: char) -> f64;
+}
+
+
+impl<F> SubstitutionCost for F where F: Fn(char, char) -> f64 {
+
+    fn substitution_cost(&self, typed: char, label: char) -> f64 {
+
+        self(typed, label)
+    }
+}
+
+
+/// Every substitution costs 1, as in `visit_neighbor_values`.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct UnitCost;
+
+
+impl SubstitutionCost for UnitCost {
+
+    fn substitution_cost(&self, _typed: char, _label: char) -> f64 {
+
+        1.0
+    }
+}
+
+
+/// Costs given pair by pair, with a default cost for the pairs not in the matrix.
+#[derive(Clone, Debug)]
+pub struct CostMatrix {
+
+    costs: HashMap<(char, char), f64>,
+    default_cost: f64
+}
+
+
+impl CostMatrix {
+
+    pub fn new(default_cost: f64) -> Self {
+
+        CostMatrix { costs: HashMap::new(), default_cost }
+    }
+
+
+    /// Sets the cost of typing `typed` instead of `label`, and the other way round.
+    pub fn set(&mut self, typed: char, label: char, cost: f64) {
+
+        self.costs.insert((typed, label), cost);
+        self.costs.insert((label, typed), cost);
+    }
+}
+
+
+impl SubstitutionCost for CostMatrix {
+
+    fn substitution_cost(&self, typed: char, label: char) -> f64 {
+
+        match self.costs.get(&(typed, label)) {
+
+            None => self.default_cost,
+
+            Some(&cost) => cost
+        }
+    }
+}
+
+
+/// Typos between neighbor keys of a keyboard (or between cases of a letter) cost
+/// `adjacent_cost`, others cost 1.
+#[derive(Clone, Debug)]
+pub struct KeyboardLayout {
+
+    positions: HashMap<char, (f64, f64)>,
+    adjacent_cost: f64
+}
+
+
+//Horizontal shift of each row of keys, in key widths, from the digits row down
+const ROW_OFFSETS: [f64; 4] = [0.0, 0.5, 0.75, 1.25];
+
+
+fn fold_case(c: char) -> char {
+
+    c.to_lowercase().next().unwrap_or(c)
+}
+
+
+impl KeyboardLayout {
+
+    /// Builds a layout from its rows of lowercase keys, from the digits row down (at most four
+    /// rows, staggered as on a usual keyboard).
+    pub fn new(rows: &[&str], adjacent_cost: f64) -> Self {
+
+        let mut positions = HashMap::new();
+
+        for (y, row) in rows.iter().enumerate().take(ROW_OFFSETS.len()) {
+
+            for (x, key) in row.chars().enumerate() {
+
+                positions.insert(key, (x as f64 + ROW_OFFSETS[y], y as f64));
+            }
+        }
+
+        KeyboardLayout { positions, adjacent_cost }
+    }
+
+
+    pub fn qwerty() -> Self {
+
+        KeyboardLayout::new(&["1234567890", "qwertyuiop", "asdfghjkl", "zxcvbnm"], 0.5)
+    }
+
+
+    pub fn azerty() -> Self {
+
+        KeyboardLayout::new(&["1234567890", "azertyuiop", "qsdfghjklm", "wxcvbn"], 0.5)
+    }
+
+
+    pub fn is_adjacent(&self, a: char, b: char) -> bool {
+
+        match (self.positions.get(&fold_case(a)), self.positions.get(&fold_case(b))) {
+
+            (Some(&(xa, ya)), Some(&(xb, yb))) => (xa-xb).abs() <= 1.0 && (ya-yb).abs() <= 1.0,
+
+            _ => false
+        }
+    }
+}
+
+
+impl SubstitutionCost for KeyboardLayout {
+
+    fn substitution_cost(&self, typed: char, label: char) -> f64 {
+
+        if fold_case(typed) == fold_case(label) || self.is_adjacent(typed, label) {
+
+            self.adjacent_cost
+
+        } else {
+
+            1.0
+        }
+    }
+}
@@ -0,0 +1,145 @@
+use Tst;
+
+
+/// A map which can find every key containing some substring.
+///
+/// Every suffix of every inserted key is stored in a second `Tst`, along with the keys it comes
+/// from. A substring query is then a prefix completion over these suffixes.
+pub struct SubstringIndex<T> {
+
+    keys: Tst<T>,
+    suffixes: Tst<Vec<String>>
+}
+
+
+impl<T> SubstringIndex<T> {
+
+    pub fn new() -> Self {
+
+        SubstringIndex { keys: Tst::new(), suffixes: Tst::new() }
+    }
+
+
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        if key.is_empty() {
+
+            return Some(value);
+        }
+
+        let old_value = self.keys.insert(key, value);
+
+        if old_value.is_none() {
+
+            for (i, _) in key.char_indices() {
+
+                let suffix = &key[i..];
+
+                match self.suffixes.get_mut(suffix) {
+
+                    Some(origins) => origins.push(key.to_string()),
+
+                    None => { self.suffixes.insert(suffix, vec![key.to_string()]); }
+                }
+            }
+        }
+
+        old_value
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        self.keys.get(key)
+    }
+
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+
+        self.keys.get_mut(key)
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        let old_value = self.keys.remove(key);
+
+        if old_value.is_some() {
+
+            for (i, _) in key.char_indices() {
+
+                let suffix = &key[i..];
+
+                let now_empty = match self.suffixes.get_mut(suffix) {
+
+                    None => false,
+
+                    Some(origins) => {
+
+                        origins.retain(|origin| origin != key);
+                        origins.is_empty()
+                    }
+                };
+
+                if now_empty {
+
+                    self.suffixes.remove(suffix);
+                }
+            }
+        }
+
+        old_value
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.keys.len()
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.keys.clear();
+        self.suffixes.clear();
+    }
+
+
+    pub fn keys(&self) -> &Tst<T> {
+
+        &self.keys
+    }
+
+
+    /// Returns the distinct keys containing `pattern`, with their values, sorted by key.
+    pub fn find(&self, pattern: &str) -> Vec<(String, &T)> {
+
+        let mut found: Vec<&str> = Vec::new();
+
+        //Completion skips the value stored on the pattern itself, so fetch it first
+        for origins in self.suffixes.get(pattern).into_iter().chain(self.suffixes.iter_complete(pattern)) {
+
+            found.extend(origins.iter().map(|s| s.as_str()));
+        }
+
+        found.sort();
+        found.dedup();
+
+        found.into_iter().filter_map(|key| self.keys.get(key).map(|value| (key.to_string(), value))).collect()
+    }
+}
+
+
+impl<T> Default for SubstringIndex<T> {
+
+    fn default() -> Self {
+
+        SubstringIndex::new()
+    }
+}
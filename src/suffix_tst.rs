@@ -0,0 +1,210 @@
+use std::vec;
+
+use Tst;
+
+
+/// A map which can also complete keys from their end.
+///
+/// Values are stored once, in slots which both a main `Tst` of the keys and a second one of the
+/// reversed keys point to, so that finding the keys ending with some suffix is a prefix
+/// completion on reversed keys, which gets to the values without looking the keys up again.
+pub struct SuffixTst<T> {
+
+    keys: Tst<usize>,
+    reversed: Tst<usize>,
+    values: Vec<Option<T>>,
+    free: Vec<usize>
+}
+
+
+fn reverse(key: &str) -> String {
+
+    key.chars().rev().collect()
+}
+
+
+impl<T> SuffixTst<T> {
+
+    pub fn new() -> Self {
+
+        SuffixTst { keys: Tst::new(), reversed: Tst::new(), values: Vec::new(), free: Vec::new() }
+    }
+
+
+    pub fn insert(&mut self, key: &str, value: T) -> Option<T> {
+
+        if let Some(&slot) = self.keys.get(key) {
+
+            return self.values[slot].replace(value);
+        }
+
+        if key.is_empty() {
+
+            return Some(value);
+        }
+
+        let slot = match self.free.pop() {
+
+            Some(slot) => {
+
+                self.values[slot] = Some(value);
+                slot
+            }
+
+            None => {
+
+                self.values.push(Some(value));
+                self.values.len() - 1
+            }
+        };
+
+        self.keys.insert(key, slot);
+        self.reversed.insert(&reverse(key), slot);
+
+        None
+    }
+
+
+    pub fn get(&self, key: &str) -> Option<&T> {
+
+        self.keys.get(key).and_then(|&slot| self.values[slot].as_ref())
+    }
+
+
+    pub fn get_mut(&mut self, key: &str) -> Option<&mut T> {
+
+        match self.keys.get(key) {
+
+            None => None,
+
+            Some(&slot) => self.values[slot].as_mut()
+        }
+    }
+
+
+    pub fn remove(&mut self, key: &str) -> Option<T> {
+
+        let slot = self.keys.remove(key)?;
+
+        self.reversed.remove(&reverse(key));
+        self.free.push(slot);
+
+        self.values[slot].take()
+    }
+
+
+    pub fn len(&self) -> usize {
+
+        self.keys.len()
+    }
+
+
+    pub fn is_empty(&self) -> bool {
+
+        self.len() == 0
+    }
+
+
+    pub fn clear(&mut self) {
+
+        self.keys.clear();
+        self.reversed.clear();
+        self.values.clear();
+        self.free.clear();
+    }
+
+
+    /// Iterates over the values whose keys end with `suffix`, including `suffix` itself, in the
+    /// order of their keys.
+    pub fn iter_suffix<'a>(&'a self, suffix: &str) -> TstSuffixIterator<'a, T> {
+
+        TstSuffixIterator::<T>::new(self, suffix)
+    }
+}
+
+
+impl<T> Default for SuffixTst<T> {
+
+    fn default() -> Self {
+
+        SuffixTst::new()
+    }
+}
+
+
+pub struct TstSuffixIterator<'a, T: 'a> {
+
+    it: vec::IntoIter<(String, &'a T)>,
+    key: String,
+    key_back: String
+}
+
+
+impl<'a, T> TstSuffixIterator<'a, T> {
+
+    pub fn new(tst: &'a SuffixTst<T>, suffix: &str) -> Self {
+
+        let mut found = Vec::new();
+
+        if let Some(value) = tst.get(suffix) {
+
+            found.push((suffix.to_string(), value));
+        }
+
+        //Reversed keys come in the order of their last chars, so keys are sorted once all found
+        let reversed_suffix = reverse(suffix);
+        let mut it = tst.reversed.iter_complete(&reversed_suffix);
+
+        while let Some(&slot) = it.next() {
+
+            if let Some(ref value) = tst.values[slot] {
+
+                found.push((reverse(&it.current_key()), value));
+            }
+        }
+
+        found.sort_by(|a, b| a.0.cmp(&b.0));
+
+        TstSuffixIterator { it: found.into_iter(), key: String::new(), key_back: String::new() }
+    }
+
+
+    pub fn current_key(&self) -> String {
+
+        self.key.clone()
+    }
+
+
+    pub fn current_key_back(&self) -> String {
+
+        self.key_back.clone()
+    }
+}
+
+
+impl<'a, T> Iterator for TstSuffixIterator<'a, T> {
+
+    type Item = &'a T;
+
+    fn next(&mut self) -> Option<&'a T> {
+
+        let (key, value) = self.it.next()?;
+
+        self.key = key;
+
+        Some(value)
+    }
+}
+
+
+impl<'a, T> DoubleEndedIterator for TstSuffixIterator<'a, T> {
+
+    fn next_back(&mut self) -> Option<&'a T> {
+
+        let (key, value) = self.it.next_back()?;
+
+        self.key_back = key;
+
+        Some(value)
+    }
+}
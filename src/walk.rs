@@ -0,0 +1,901 @@
+use std::cmp::Ordering;
+use std::cmp::Ordering::Less;
+use std::cmp::Ordering::Equal;
+use std::cmp::Ordering::Greater;
+use std::collections::HashSet;
+use std::io::Write;
+
+use Stats;
+use DistStat;
+use LabelOrder;
+use Adjacency;
+use TstIteratorAction;
+use TstIteratorAction::*;
+use SubstitutionCost;
+use UnitCost;
+use query::{Search, CrosswordNeighborQuery, WeightedNeighborQuery, CostCursor, FuzzyCompleteQuery, FuzzyCursor};
+
+
+//Read-only searches of `Tst` and its variants, which all have the same signatures. The tree gives
+//its root to the walks with `root_node`, has an `order` field, and imports `std::io::Write`
+macro_rules! gen_search_api {
+
+    ($iterator:ident) => (
+
+        fn visit<'b, S, C>(&self, search: S, state: Option<S::State>, mut callback: C)
+        where S: $crate::query::Search<'b>, C: FnMut (&T, &S::State) {
+
+            if let Some(state) = state {
+
+                $crate::walk::visit_r(self.root_node(), &search, &state, &self.order, &mut callback);
+            }
+        }
+
+
+        pub fn visit_values<C>(&self, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::Query::All, Some($crate::query::Cursor::new("", 0)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_complete_values<C>(&self, key: &str, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::Query::Complete, Some($crate::query::Cursor::new(key, 0)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_neighbor_values<C>(&self, key: &str, dist: usize, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::Query::Neighbor, Some($crate::query::Cursor::new(key, dist)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_crossword_values<C>(&self, key: &str, joker: char, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::Query::Crossword(joker), Some($crate::query::Cursor::new(key, 0)), |value, _| callback(value));
+        }
+
+
+        /// Visits the values whose keys match `key` with jokers, as `visit_crossword_values` does,
+        /// except for at most `dist` non-joker positions. The callback also gets these mismatch positions.
+        pub fn visit_crossword_neighbor_values<C>(&self, key: &str, joker: char, dist: usize, mut callback: C)
+        where C: FnMut (&T, &[usize]) {
+
+            let search = $crate::query::CrosswordNeighborQuery { joker, range: dist };
+
+            self.visit(search, Some($crate::query::MismatchCursor::new(key)), |value, cursor| callback(value, &cursor.mismatches));
+        }
+
+
+        /// Visits the values whose keys are close to `key`, as `visit_neighbor_values` does, but with
+        /// substitution costs given by `costs` instead of 1 (missing or extra chars still cost 1).
+        ///
+        /// The callback gets the total cost of each key, which is at most `budget`.
+        pub fn visit_weighted_neighbor_values<W, C>(&self, key: &str, budget: f64, costs: &W, mut callback: C)
+        where W: $crate::SubstitutionCost, C: FnMut (&T, f64) {
+
+            let search = $crate::query::WeightedNeighborQuery::new(budget, costs);
+
+            self.visit(search, Some($crate::query::CostCursor::new(key)), |value, cursor| callback(value, cursor.total()));
+        }
+
+
+        pub fn visit_anagram_values<C>(&self, letters: &str, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::AnagramQuery, Some(letters.chars().collect()), |value, _| callback(value));
+        }
+
+
+        pub fn visit_rack_values<C>(&self, rack: &str, joker: char, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::RackQuery, $crate::query::RackCursor::new(rack, None, joker), |value, _| callback(value));
+        }
+
+
+        pub fn visit_rack_crossword_values<C>(&self, rack: &str, pattern: &str, joker: char, mut callback: C)
+        where C: FnMut (&T) {
+
+            self.visit($crate::query::RackQuery, $crate::query::RackCursor::new(rack, Some(pattern), joker), |value, _| callback(value));
+        }
+
+
+        /// Finds the keys which can be read in a Boggle grid, following adjacent cells without
+        /// using a cell twice. Each key is returned once, with its value, in the order of the tree.
+        pub fn find_in_grid(&self, grid: &[Vec<char>], adjacency: $crate::Adjacency) -> Vec<(String, &T)> {
+
+            $crate::walk::find_in_grid(self.root_node(), grid, adjacency, &self.order)
+        }
+
+
+        /// Finds the keys in which the chars of `query` appear in order, not necessarily next to each
+        /// other (e.g. "fb" matches "FooBar" and "file_browser"). Case is ignored unless the query has
+        /// some uppercase letter.
+        ///
+        /// Each key comes with its value and a score, which is better with fewer gaps between
+        /// matched chars, and with matches at the start of the key or of its words. Results are in
+        /// the order of the tree.
+        pub fn find_subsequence(&self, query: &str) -> Vec<(String, &T, i32)> {
+
+            $crate::subsequence::find_subsequence(self.root_node(), query, &self.order)
+        }
+
+
+        /// Returns the `k` best scored results of `find_subsequence`, by decreasing score.
+        pub fn top_k_subsequence(&self, query: &str, k: usize) -> Vec<(String, &T, i32)> {
+
+            $crate::subsequence::top_k(self.find_subsequence(query), k)
+        }
+
+
+        pub fn pretty_print(&self, writer: &mut dyn Write) {
+
+            $crate::walk::pretty_print(self.root_node(), writer);
+        }
+
+
+        pub fn iter<'a>(&'a self) -> $iterator<'a, 'static, T, O> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::Query::All, Some($crate::query::Cursor::new("", 0)), &self.order)
+        }
+
+
+        pub fn iter_complete<'a, 'b>(&'a self, prefix: &'b str) -> $iterator<'a, 'b, T, O> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::Query::Complete, Some($crate::query::Cursor::new(prefix, 0)), &self.order)
+        }
+
+
+        pub fn iter_neighbor<'a, 'b>(&'a self, key: &'b str, range: usize) -> $iterator<'a, 'b, T, O> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::Query::Neighbor, Some($crate::query::Cursor::new(key, range)), &self.order)
+        }
+
+
+        pub fn iter_crossword<'a, 'b>(&'a self, key: &'b str, joker: char) -> $iterator<'a, 'b, T, O> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::Query::Crossword(joker), Some($crate::query::Cursor::new(key, 0)), &self.order)
+        }
+
+
+        pub fn iter_crossword_neighbor<'a, 'b>(&'a self, key: &'b str, joker: char, range: usize)
+                                               -> $iterator<'a, 'b, T, O, $crate::query::CrosswordNeighborQuery> {
+
+            let search = $crate::query::CrosswordNeighborQuery { joker, range };
+
+            $crate::walk::Walk::new(self.root_node(), search, Some($crate::query::MismatchCursor::new(key)), &self.order)
+        }
+
+
+        pub fn iter_weighted_neighbor<'a, 'b, W>(&'a self, key: &'b str, budget: f64, costs: &'a W)
+                                                 -> $iterator<'a, 'b, T, O, $crate::query::WeightedNeighborQuery<'a, W>>
+        where W: $crate::SubstitutionCost {
+
+            let search = $crate::query::WeightedNeighborQuery::new(budget, costs);
+
+            $crate::walk::Walk::new(self.root_node(), search, Some($crate::query::CostCursor::new(key)), &self.order)
+        }
+
+
+        pub fn iter_anagrams<'a>(&'a self, letters: &str) -> $iterator<'a, 'static, T, O, $crate::query::AnagramQuery> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::AnagramQuery, Some(letters.chars().collect()), &self.order)
+        }
+
+
+        pub fn iter_fuzzy_complete<'a>(&'a self, prefix: &str, max_dist: usize)
+                                       -> $iterator<'a, 'static, T, O, $crate::query::FuzzyCompleteQuery<'a, $crate::UnitCost>> {
+
+            self.iter_weighted_fuzzy_complete(prefix, max_dist as f64, &$crate::UnitCost)
+        }
+
+
+        /// Same as `iter_fuzzy_complete`, with substitution costs given by `costs` instead of 1.
+        pub fn iter_weighted_fuzzy_complete<'a, W>(&'a self, prefix: &str, budget: f64, costs: &'a W)
+                                                   -> $iterator<'a, 'static, T, O, $crate::query::FuzzyCompleteQuery<'a, W>>
+        where W: $crate::SubstitutionCost {
+
+            let search = $crate::query::FuzzyCompleteQuery::new(prefix, budget, costs);
+            let state = search.start();
+
+            $crate::walk::Walk::new(self.root_node(), search, Some(state), &self.order)
+        }
+
+
+        pub fn iter_rack<'a>(&'a self, rack: &str, joker: char) -> $iterator<'a, 'static, T, O, $crate::query::RackQuery> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::RackQuery, $crate::query::RackCursor::new(rack, None, joker), &self.order)
+        }
+
+
+        pub fn iter_rack_crossword<'a, 'b>(&'a self, rack: &str, pattern: &'b str, joker: char) -> $iterator<'a, 'b, T, O, $crate::query::RackQuery> {
+
+            $crate::walk::Walk::new(self.root_node(), $crate::query::RackQuery, $crate::query::RackCursor::new(rack, Some(pattern), joker), &self.order)
+        }
+    );
+}
+
+
+//Mutable versions of the searches of `gen_search_api`, for a variant which visits its values
+//mutably with `visit_mut`
+macro_rules! gen_search_api_mut {
+
+    () => (
+
+        pub fn visit_values_mut<C>(&mut self, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::Query::All, Some($crate::query::Cursor::new("", 0)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_complete_values_mut<C>(&mut self, key: &str, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::Query::Complete, Some($crate::query::Cursor::new(key, 0)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_neighbor_values_mut<C>(&mut self, key: &str, dist: usize, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::Query::Neighbor, Some($crate::query::Cursor::new(key, dist)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_crossword_values_mut<C>(&mut self, key: &str, joker: char, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::Query::Crossword(joker), Some($crate::query::Cursor::new(key, 0)), |value, _| callback(value));
+        }
+
+
+        pub fn visit_crossword_neighbor_values_mut<C>(&mut self, key: &str, joker: char, dist: usize, mut callback: C)
+        where C: FnMut (&mut T, &[usize]) {
+
+            let search = $crate::query::CrosswordNeighborQuery { joker, range: dist };
+
+            self.visit_mut(search, Some($crate::query::MismatchCursor::new(key)), |value, cursor| callback(value, &cursor.mismatches));
+        }
+
+
+        pub fn visit_weighted_neighbor_values_mut<W, C>(&mut self, key: &str, budget: f64, costs: &W, mut callback: C)
+        where W: $crate::SubstitutionCost, C: FnMut (&mut T, f64) {
+
+            let search = $crate::query::WeightedNeighborQuery::new(budget, costs);
+
+            self.visit_mut(search, Some($crate::query::CostCursor::new(key)), |value, cursor| callback(value, cursor.total()));
+        }
+
+
+        pub fn visit_anagram_values_mut<C>(&mut self, letters: &str, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::AnagramQuery, Some(letters.chars().collect()), |value, _| callback(value));
+        }
+
+
+        pub fn visit_rack_values_mut<C>(&mut self, rack: &str, joker: char, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::RackQuery, $crate::query::RackCursor::new(rack, None, joker), |value, _| callback(value));
+        }
+
+
+        pub fn visit_rack_crossword_values_mut<C>(&mut self, rack: &str, pattern: &str, joker: char, mut callback: C)
+        where C: FnMut (&mut T) {
+
+            self.visit_mut($crate::query::RackQuery, $crate::query::RackCursor::new(rack, Some(pattern), joker), |value, _| callback(value));
+        }
+    );
+}
+
+
+//A node of one of the `Tst` variants, as read by the walks they share
+pub trait WalkNode<'a>: Copy {
+
+    type Value: ?Sized + 'a;
+    type Label: Iterator<Item = char>;
+
+    fn label(&self) -> Self::Label;
+
+    fn value(&self) -> Option<&'a Self::Value>;
+
+    fn left(&self) -> Option<Self>;
+
+    fn middle(&self) -> Option<Self>;
+
+    fn right(&self) -> Option<Self>;
+
+    //Tells nodes apart, in the graph of `pretty_print` and among the words of `find_in_grid`
+    fn id(&self) -> usize;
+
+
+    //Siblings are ordered by the first char of their label
+    fn first_label(&self) -> char {
+
+        self.label().next().unwrap()
+    }
+
+
+    //Bytes held by the label outside of the node
+    fn label_capacity(&self) -> usize {
+
+        0
+    }
+}
+
+
+//Compares two keys in the order of a tree, where a key comes before the keys it is a prefix of
+pub fn compare_keys<O, A, B>(order: &O, mut a: A, mut b: B) -> Ordering
+where O: LabelOrder, A: Iterator<Item = char>, B: Iterator<Item = char> {
+
+    loop {
+
+        match (a.next(), b.next()) {
+
+            (None, None) => return Equal,
+            (None, Some(_)) => return Less,
+            (Some(_), None) => return Greater,
+
+            (Some(a_label), Some(b_label)) => match order.compare(a_label, b_label) {
+
+                Equal => continue,
+                ordering => return ordering
+            }
+        }
+    }
+}
+
+
+pub fn stat_r<'a, N: WalkNode<'a>>(stats: Stats, node: Option<N>, matches: usize, sides: usize, depth: usize) -> Stats {
+
+    let node = match node {
+
+        None => return stats,
+
+        Some(node) => node
+    };
+
+    let mut stats = stat_r(stats, node.left(), matches, sides+1, depth+1);
+
+    let key_len = matches + node.label().count();
+
+    stats.count.nodes+=1;
+    stats.bytes.total+=node.label_capacity();
+
+    if node.value().is_some() {
+
+        let depth = depth + 1;
+
+        while stats.dist.len() <= key_len.max(sides).max(depth) {
+
+            stats.dist.push(DistStat { matches: 0, sides: 0, depth: 0 });
+        }
+
+        stats.dist[key_len].matches+=1;
+        stats.dist[sides].sides+=1;
+        stats.dist[depth].depth+=1;
+
+        if stats.key_len.min == 0 || key_len < stats.key_len.min {
+
+            stats.key_len.min = key_len;
+        }
+
+        if key_len > stats.key_len.max {
+
+            stats.key_len.max = key_len;
+        }
+
+        stats.count.values+=1;
+    }
+
+    let stats = stat_r(stats, node.middle(), key_len, sides, depth+1);
+    stat_r(stats, node.right(), matches, sides+1, depth+1)
+}
+
+
+pub fn visit_r<'a, 'b, N, S, O, C>(node: Option<N>, search: &S, state: &S::State, order: &O, callback: &mut C)
+where N: WalkNode<'a>, S: Search<'b>, O: LabelOrder, C: FnMut (&'a N::Value, &S::State) {
+
+    if let Some(node) = node {
+
+        let first = node.first_label();
+
+        if search.may_go(state, first, Less, order) {
+
+            visit_r(node.left(), search, state, order, callback);
+        }
+
+        if let Some(new_state) = search.step_label(state, node.label()) {
+
+            if let Some(value) = node.value() {
+
+                if search.accepts(&new_state) {
+
+                    callback(value, &new_state);
+                }
+            }
+
+            visit_r(node.middle(), search, &new_state, order, callback);
+        }
+
+        if search.may_go(state, first, Greater, order) {
+
+            visit_r(node.right(), search, state, order, callback);
+        }
+    }
+}
+
+
+//Node of a sibling tree whose label begins with `label`
+fn find_label<'a, N, O>(mut node: Option<N>, label: char, order: &O) -> Option<N>
+where N: WalkNode<'a>, O: LabelOrder {
+
+    while let Some(n) = node {
+
+        node = match order.compare(label, n.first_label()) {
+
+            Less => n.left(),
+
+            Equal => return Some(n),
+
+            Greater => n.right()
+        };
+    }
+
+    None
+}
+
+
+struct GridWalk<'a, 'g, N: WalkNode<'a>, O: 'a> {
+
+    grid: &'g [Vec<char>],
+    adjacency: Adjacency,
+    order: &'a O,
+    visited: Vec<Vec<bool>>,
+    key: String,
+    found: Vec<(String, &'a N::Value)>,
+    seen: HashSet<usize>
+}
+
+
+impl<'a, 'g, N: WalkNode<'a>, O: LabelOrder> GridWalk<'a, 'g, N, O> {
+
+    //Walks the grid from a cell whose letter matches the char at `at` in the node label
+    fn walk(&mut self, node: N, label: &[char], at: usize, (row, col): (usize, usize)) {
+
+        self.visited[row][col] = true;
+        self.key.push(label[at]);
+
+        if at + 1 < label.len() {
+
+            for (r, c) in self.adjacency.neighbors(self.grid, row, col) {
+
+                if !self.visited[r][c] && self.grid[r][c] == label[at+1] {
+
+                    self.walk(node, label, at+1, (r, c));
+                }
+            }
+
+        } else {
+
+            if let Some(value) = node.value() {
+
+                if self.seen.insert(node.id()) {
+
+                    self.found.push((self.key.clone(), value));
+                }
+            }
+
+            for (r, c) in self.adjacency.neighbors(self.grid, row, col) {
+
+                if !self.visited[r][c] {
+
+                    if let Some(child) = find_label(node.middle(), self.grid[r][c], self.order) {
+
+                        let child_label: Vec<char> = child.label().collect();
+
+                        self.walk(child, &child_label, 0, (r, c));
+                    }
+                }
+            }
+        }
+
+        self.key.pop();
+        self.visited[row][col] = false;
+    }
+}
+
+
+pub fn find_in_grid<'a, N, O>(root: Option<N>, grid: &[Vec<char>], adjacency: Adjacency, order: &'a O) -> Vec<(String, &'a N::Value)>
+where N: WalkNode<'a>, O: LabelOrder {
+
+    let mut search = GridWalk {
+
+        grid, adjacency, order,
+        visited: grid.iter().map(|row| vec![false; row.len()]).collect(),
+        key: String::new(), found: Vec::new(), seen: HashSet::new()
+    };
+
+    for (row, cells) in grid.iter().enumerate() {
+
+        for (col, &label) in cells.iter().enumerate() {
+
+            if let Some(node) = find_label(root, label, order) {
+
+                let node_label: Vec<char> = node.label().collect();
+
+                search.walk(node, &node_label, 0, (row, col));
+            }
+        }
+    }
+
+    let mut found = search.found;
+
+    found.sort_by(|a, b| compare_keys(order, a.0.chars(), b.0.chars()));
+
+    found
+}
+
+
+fn pretty_print_r<'a, N: WalkNode<'a>>(node: Option<N>, writer: &mut dyn Write) {
+
+    if let Some(node) = node {
+
+        let value_box = match node.value() {
+
+            None => "☐", Some(_) => "☑"
+        };
+
+        let label: String = node.label().collect();
+
+        let _ = writeln!(writer, r#""{:#x}" [label=<<TABLE BORDER="0" CELLBORDER="1" CELLSPACING="0"><TR><TD COLSPAN="3">{} {}</TD></TR><TR><TD PORT="l"></TD><TD PORT="m"></TD><TD PORT="r"></TD></TR></TABLE>>]"#, node.id(), value_box, label);
+
+        for &(child, start, style) in [(node.left(), "l", "solid"), (node.middle(), "m", "bold"), (node.right(), "r", "solid")].iter() {
+
+            if let Some(child) = child {
+
+                let _ = writeln!(writer, r#""{:#x}":{} -> "{:#x}" [style={}]"#, node.id(), start, child.id(), style);
+            }
+        }
+
+        pretty_print_r(node.left(), writer);
+        pretty_print_r(node.middle(), writer);
+        pretty_print_r(node.right(), writer);
+    }
+}
+
+
+pub fn pretty_print<'a, N: WalkNode<'a>>(root: Option<N>, writer: &mut dyn Write) {
+
+    let _ = writeln!(writer, "digraph {{");
+    let _ = writeln!(writer, "node [shape=plaintext]");
+
+    pretty_print_r(root, writer);
+
+    let _ = writeln!(writer, "}}");
+}
+
+
+//Each frame holds the search state before its node, and after its label once computed
+type Todo<N, St> = Vec<(N, TstIteratorAction, St, Option<St>)>;
+
+
+/// Double ended iterator of a search in one of the `Tst` variants.
+pub struct Walk<'a, 'b, N, O: 'a, S: Search<'b>> {
+
+    todo_i: Todo<N, S::State>,
+    last_i: Option<S::State>,
+
+    todo_j: Todo<N, S::State>,
+    last_j: Option<S::State>,
+
+    search: S,
+    order: &'a O
+}
+
+
+impl<'a, 'b, N: WalkNode<'a>, O: LabelOrder, S: Search<'b>> Walk<'a, 'b, N, O, S> {
+
+    pub fn new(root: Option<N>, search: S, state: Option<S::State>, order: &'a O) -> Self {
+
+        let mut walk = Walk {
+
+            todo_i: Vec::new(), last_i: None,
+            todo_j: Vec::new(), last_j: None,
+            search, order
+        };
+
+        if let (Some(root), Some(state)) = (root, state) {
+
+            walk.todo_i.push((root, GoLeft, state.clone(), None));
+            walk.todo_j.push((root, GoRight, state, None));
+        }
+
+        walk
+    }
+
+
+    pub fn current_key(&self) -> String {
+
+        self.todo_i.iter().filter(|todo| todo.1 == GoMiddle || todo.1 == GoRight).flat_map(|todo| todo.0.label()).collect()
+    }
+
+
+    pub fn current_key_back(&self) -> String {
+
+        self.todo_j.iter().filter(|todo| todo.1 == Visit || todo.1 == GoLeft).flat_map(|todo| todo.0.label()).collect()
+    }
+
+
+    //Whether the front end has caught up with the last key found by the back end. Keys are
+    //compared rather than nodes, as the same node may be reached by several paths in a DAG
+    fn ends_met(&self) -> bool {
+
+        if self.todo_i.is_empty() {
+
+            return true;
+        }
+
+        let key_i = self.todo_i.iter().filter(|todo| todo.1 == GoMiddle || todo.1 == GoRight).flat_map(|todo| todo.0.label());
+        let key_j = self.todo_j.iter().filter(|todo| todo.1 == Visit || todo.1 == GoLeft).flat_map(|todo| todo.0.label());
+
+        compare_keys(self.order, key_i, key_j) != Less
+    }
+
+
+    //Value of a node, if the search accepts it with the state after its label
+    fn accepted(&self, node: N, after: &Option<S::State>) -> Option<(&'a N::Value, S::State)> {
+
+        match (node.value(), after.as_ref()) {
+
+            (Some(value), Some(after)) if self.search.accepts(after) => Some((value, after.clone())),
+
+            _ => None
+        }
+    }
+}
+
+
+impl<'a, 'b, N: WalkNode<'a>, O: LabelOrder, S: Search<'b>> Iterator for Walk<'a, 'b, N, O, S> {
+
+    type Item = &'a N::Value;
+
+    fn next(&mut self) -> Option<&'a N::Value> {
+
+        let mut found = None;
+
+        while let Some((node, action, state, after)) = self.todo_i.pop() {
+
+            match action {
+
+                GoLeft => {
+
+                    let child = node.left().filter(|_| self.search.may_go(&state, node.first_label(), Less, self.order));
+
+                    self.todo_i.push((node, Visit, state.clone(), None));
+
+                    if let Some(child) = child {
+
+                        self.todo_i.push((child, GoLeft, state, None));
+                    }
+                }
+
+                Visit => {
+
+                    let after = self.search.step_label(&state, node.label());
+                    let accepted = self.accepted(node, &after);
+
+                    self.todo_i.push((node, GoMiddle, state, after));
+
+                    if let Some((value, after)) = accepted {
+
+                        if self.last_j.is_some() && self.ends_met() {
+
+                            self.todo_i.clear();
+                            self.todo_j.clear();
+
+                            found = None;
+                            break;
+                        }
+
+                        self.last_i = Some(after);
+                        found = Some(value);
+
+                        break;
+                    }
+                }
+
+                GoMiddle => {
+
+                    self.todo_i.push((node, GoRight, state, None));
+
+                    if let (Some(child), Some(after)) = (node.middle(), after) {
+
+                        self.todo_i.push((child, GoLeft, after, None));
+                    }
+                }
+
+                GoRight => {
+
+                    if let Some(child) = node.right() {
+
+                        if self.search.may_go(&state, node.first_label(), Greater, self.order) {
+
+                            self.todo_i.push((child, GoLeft, state, None));
+                        }
+                    }
+                }
+            }
+        }
+
+        found
+    }
+}
+
+
+impl<'a, 'b, N: WalkNode<'a>, O: LabelOrder, S: Search<'b>> DoubleEndedIterator for Walk<'a, 'b, N, O, S> {
+
+    fn next_back(&mut self) -> Option<&'a N::Value> {
+
+        let mut found = None;
+
+        while let Some((node, action, state, after)) = self.todo_j.pop() {
+
+            match action {
+
+                GoRight => {
+
+                    let child = node.right().filter(|_| self.search.may_go(&state, node.first_label(), Greater, self.order));
+
+                    self.todo_j.push((node, GoMiddle, state.clone(), None));
+
+                    if let Some(child) = child {
+
+                        self.todo_j.push((child, GoRight, state, None));
+                    }
+                }
+
+                Visit => {
+
+                    let accepted = self.accepted(node, &after);
+
+                    self.todo_j.push((node, GoLeft, state, None));
+
+                    if let Some((value, after)) = accepted {
+
+                        if self.last_i.is_some() && self.ends_met() {
+
+                            self.todo_i.clear();
+                            self.todo_j.clear();
+
+                            found = None;
+                            break;
+                        }
+
+                        self.last_j = Some(after);
+                        found = Some(value);
+
+                        break;
+                    }
+                }
+
+                GoMiddle => {
+
+                    let after = self.search.step_label(&state, node.label());
+
+                    self.todo_j.push((node, Visit, state, after.clone()));
+
+                    if let (Some(child), Some(after)) = (node.middle(), after) {
+
+                        self.todo_j.push((child, GoRight, after, None));
+                    }
+                }
+
+                GoLeft => {
+
+                    if let Some(child) = node.left() {
+
+                        if self.search.may_go(&state, node.first_label(), Less, self.order) {
+
+                            self.todo_j.push((child, GoRight, state, None));
+                        }
+                    }
+                }
+            }
+        }
+
+        found
+    }
+}
+
+
+impl<'a, 'b, N: WalkNode<'a>, O: LabelOrder> Walk<'a, 'b, N, O, CrosswordNeighborQuery> {
+
+    /// Positions where the last key found by `next` does not match the pattern.
+    pub fn current_mismatches(&self) -> Vec<usize> {
+
+        self.last_i.as_ref().map(|cursor| cursor.mismatches.clone()).unwrap_or_default()
+    }
+
+
+    /// Positions where the last key found by `next_back` does not match the pattern.
+    pub fn current_mismatches_back(&self) -> Vec<usize> {
+
+        self.last_j.as_ref().map(|cursor| cursor.mismatches.clone()).unwrap_or_default()
+    }
+}
+
+
+impl<'a, 'b, 'w, N: WalkNode<'a>, O: LabelOrder, W: SubstitutionCost> Walk<'a, 'b, N, O, WeightedNeighborQuery<'w, W>> {
+
+    /// Total cost of the last key found by `next`.
+    pub fn current_cost(&self) -> Option<f64> {
+
+        self.last_i.as_ref().map(CostCursor::total)
+    }
+
+
+    /// Total cost of the last key found by `next_back`.
+    pub fn current_cost_back(&self) -> Option<f64> {
+
+        self.last_j.as_ref().map(CostCursor::total)
+    }
+}
+
+
+impl<'a, 'b, 'w, N: WalkNode<'a>, O: LabelOrder, W: SubstitutionCost> Walk<'a, 'b, N, O, FuzzyCompleteQuery<'w, W>> {
+
+    /// Edit cost between the prefix and the closest prefix of the last key found by `next`.
+    pub fn current_cost(&self) -> Option<f64> {
+
+        self.last_i.as_ref().map(FuzzyCursor::cost)
+    }
+
+
+    /// Edit cost between the prefix and the closest prefix of the last key found by `next_back`.
+    pub fn current_cost_back(&self) -> Option<f64> {
+
+        self.last_j.as_ref().map(FuzzyCursor::cost)
+    }
+}
+
+
+impl<'a, 'b, 'w, N: WalkNode<'a>, O: LabelOrder> Walk<'a, 'b, N, O, FuzzyCompleteQuery<'w, UnitCost>> {
+
+    /// Edit distance between the prefix and the closest prefix of the last key found by `next`.
+    pub fn current_distance(&self) -> Option<usize> {
+
+        self.current_cost().map(|cost| cost as usize)
+    }
+
+
+    /// Edit distance between the prefix and the closest prefix of the last key found by `next_back`.
+    pub fn current_distance_back(&self) -> Option<usize> {
+
+        self.current_cost_back().map(|cost| cost as usize)
+    }
+}
+
+
+//Runs a walk to its end, with the key and the final search state of each value
+pub fn collect<'a, 'b, N, O, S>(mut walk: Walk<'a, 'b, N, O, S>) -> Vec<(String, &'a N::Value, S::State)>
+where N: WalkNode<'a>, O: LabelOrder, S: Search<'b> {
+
+    let mut found = Vec::new();
+
+    while let Some(value) = walk.next() {
+
+        let state = walk.last_i.clone().unwrap();
+
+        found.push((walk.current_key(), value, state));
+    }
+
+    found
+}
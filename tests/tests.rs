@@ -1,4 +1,6 @@
 extern crate ternary_tree;
+#[cfg(feature = "serde")]
+extern crate serde_json;
 use ternary_tree::Tst;
 
 
@@ -1668,3 +1670,3054 @@ fn tst_create_with_macro() {
 
     assert_eq!(v, SORTED_VEC_123);
 }
+
+
+fn french_order(a: char, b: char) -> std::cmp::Ordering {
+
+    let base = |c| match c {
+
+        'à' | 'â' => 'a',
+        'é' | 'è' | 'ê' | 'ë' => 'e',
+        'î' | 'ï' => 'i',
+        'ô' => 'o',
+        'ù' | 'û' => 'u',
+        'ç' => 'c',
+        c => c
+    };
+
+    base(a).cmp(&base(b)).then(a.cmp(&b))
+}
+
+
+const FRENCH_VEC : [&str; 6] = ["côté", "cz", "cote", "cotte", "côte", "coté"];
+
+
+#[test]
+fn tst_iterate_with_default_order() {
+
+    let mut map = Tst::new();
+
+    for k in FRENCH_VEC.iter() {
+
+        map.insert(k, *k);
+    }
+
+    let v: Vec<&str> = map.iter().cloned().collect();
+    assert_eq!(v, ["cote", "cotte", "coté", "cz", "côte", "côté"]);
+}
+
+
+#[test]
+fn tst_iterate_with_custom_order() {
+
+    let mut map = Tst::with_order(french_order);
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.insert(k, *k), None);
+    }
+
+    assert_eq!(map.len(), 6);
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.get(k), Some(k));
+    }
+
+    let v: Vec<&str> = map.iter().cloned().collect();
+    assert_eq!(v, ["cote", "coté", "cotte", "côte", "côté", "cz"]);
+
+    let mut v: Vec<&str> = map.iter().rev().cloned().collect();
+    v.reverse();
+    assert_eq!(v, ["cote", "coté", "cotte", "côte", "côté", "cz"]);
+
+    let mut it = map.iter();
+    let mut w = Vec::new();
+
+    while it.next().is_some() {
+
+        w.push(it.current_key());
+    }
+
+    assert_eq!(w, ["cote", "coté", "cotte", "côte", "côté", "cz"]);
+
+    ////////////////////////////////////////////////////
+
+    let v: Vec<&str> = map.iter_complete("cô").cloned().collect();
+    assert_eq!(v, ["côte", "côté"]);
+
+    let mut v = Vec::new();
+    map.visit_complete_values("co", |s| v.push(*s));
+    assert_eq!(v, ["cote", "coté", "cotte"]);
+
+    ////////////////////////////////////////////////////
+
+    let v: Vec<&str> = map.iter_neighbor("cote", 1).cloned().collect();
+    assert_eq!(v, ["cote", "coté", "côte"]);
+
+    let mut v: Vec<&str> = map.iter_neighbor("cote", 1).rev().cloned().collect();
+    v.reverse();
+    assert_eq!(v, ["cote", "coté", "côte"]);
+
+    let mut v = Vec::new();
+    map.visit_neighbor_values("cote", 1, |s| v.push(*s));
+    assert_eq!(v, ["cote", "coté", "côte"]);
+
+    let v: Vec<&str> = map.iter_neighbor("côté", 0).cloned().collect();
+    assert_eq!(v, ["côté"]);
+
+    ////////////////////////////////////////////////////
+
+    let v: Vec<&str> = map.iter_crossword("c?t?", '?').cloned().collect();
+    assert_eq!(v, ["cote", "coté", "côte", "côté"]);
+
+    let mut v: Vec<&str> = map.iter_crossword("cot?", '?').rev().cloned().collect();
+    v.reverse();
+    assert_eq!(v, ["cote", "coté"]);
+
+    let mut v = Vec::new();
+    map.visit_crossword_values("?ôt?", '?', |s| v.push(*s));
+    assert_eq!(v, ["côte", "côté"]);
+
+    ////////////////////////////////////////////////////
+
+    assert_eq!(map.remove("côte"), Some("côte"));
+    assert_eq!(map.remove("côte"), None);
+    assert_eq!(map.len(), 5);
+
+    let v: Vec<&str> = map.iter().cloned().collect();
+    assert_eq!(v, ["cote", "coté", "cotte", "côté", "cz"]);
+}
+
+
+#[test]
+fn tst_find_substring() {
+
+    use ternary_tree::SubstringIndex;
+
+    assert!(SubstringIndex::<usize>::default().is_empty());
+
+    let mut index = SubstringIndex::new();
+
+    for (i, k) in ["banana", "ananas", "bandana", "cabana", "bob"].iter().enumerate() {
+
+        assert_eq!(index.insert(k, i), None);
+    }
+
+    assert_eq!(index.len(), 5);
+    assert!(!index.is_empty());
+    assert_eq!(index.insert("bob", 10), Some(4));
+    assert_eq!(index.insert("", 10), Some(10));
+    assert_eq!(index.len(), 5);
+
+    assert_eq!(index.get("bob"), Some(&10));
+    assert_eq!(index.get("ana"), None);
+
+    let found = index.find("ana");
+    assert_eq!(found, [("ananas".to_string(), &1), ("banana".to_string(), &0),
+                       ("bandana".to_string(), &2), ("cabana".to_string(), &3)]);
+
+    let keys: Vec<String> = index.find("nan").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["ananas", "banana"]);
+
+    let keys: Vec<String> = index.find("b").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["banana", "bandana", "bob", "cabana"]);
+
+    let keys: Vec<String> = index.find("bob").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["bob"]);
+
+    assert!(index.find("xyz").is_empty());
+    assert_eq!(index.find("").len(), 5);
+
+    ////////////////////////////////////////////////////
+
+    assert_eq!(index.remove("banana"), Some(0));
+    assert_eq!(index.remove("banana"), None);
+    assert_eq!(index.len(), 4);
+
+    let keys: Vec<String> = index.find("nan").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["ananas"]);
+
+    let keys: Vec<String> = index.find("ana").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["ananas", "bandana", "cabana"]);
+
+    for k in ["ananas", "bandana", "cabana", "bob"].iter() {
+
+        index.remove(k);
+    }
+
+    assert_eq!(index.len(), 0);
+    assert!(index.find("").is_empty());
+    assert_eq!(index.keys().stat().count.nodes, 0);
+}
+
+
+#[test]
+fn tst_iterate_with_suffix() {
+
+    use ternary_tree::SuffixTst;
+
+    let mut map = SuffixTst::new();
+
+    for k in ["nation", "station", "notion", "nations", "tion", "motion", "ration", "rations", "stone"].iter() {
+
+        assert_eq!(map.insert(k, k.len()), None);
+    }
+
+    assert_eq!(map.len(), 9);
+    assert!(!map.is_empty());
+    assert_eq!(map.insert("tion", 0), Some(4));
+    assert_eq!(map.get("station"), Some(&7));
+    assert_eq!(map.get("sta"), None);
+
+    let mut it = map.iter_suffix("tion");
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        w.push(it.current_key());
+    }
+
+    assert_eq!(w, ["motion", "nation", "notion", "ration", "station", "tion"]);
+    assert_eq!(v, [6, 6, 6, 6, 7, 0]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_suffix("tion");
+    w.clear();
+
+    while it.next_back().is_some() {
+
+        w.push(it.current_key_back());
+    }
+
+    w.reverse();
+
+    assert_eq!(w, ["motion", "nation", "notion", "ration", "station", "tion"]);
+
+    let mut it = map.iter_suffix("tion");
+
+    assert_eq!(it.next_back(), Some(&0));
+    assert_eq!(it.next(), Some(&6));
+    assert_eq!(it.current_key(), "motion");
+    assert_eq!(it.current_key_back(), "tion");
+
+    let mut it = map.iter_suffix("stone");
+
+    assert_eq!(it.next_back(), Some(&5));
+    assert_eq!(it.current_key_back(), "stone");
+    assert_eq!(it.next(), None);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_suffix("ns");
+    w.clear();
+
+    while it.next().is_some() {
+
+        w.push(it.current_key());
+    }
+
+    assert_eq!(w, ["nations", "rations"]);
+
+    assert_eq!(map.iter_suffix("").count(), 9);
+    assert_eq!(map.iter_suffix("xyz").count(), 0);
+
+    ////////////////////////////////////////////////////
+
+    assert_eq!(map.remove("nation"), Some(6));
+    assert_eq!(map.remove("nation"), None);
+    assert_eq!(map.len(), 8);
+
+    let mut it = map.iter_suffix("ation");
+    w.clear();
+
+    while it.next().is_some() {
+
+        w.push(it.current_key());
+    }
+
+    assert_eq!(w, ["ration", "station"]);
+
+    assert_eq!(map.insert("nation", 60), None);
+    assert_eq!(map.iter_suffix("ation").collect::<Vec<_>>(), [&60, &6, &7]);
+
+    map.clear();
+
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+    assert_eq!(map.iter_suffix("").count(), 0);
+}
+
+
+#[test]
+fn tst_top_k_complete() {
+
+    use ternary_tree::ScoredTst;
+
+    let mut map = ScoredTst::new(|freq: &u32| *freq);
+
+    for &(k, freq) in [("help", 50), ("hello", 100), ("helm", 10), ("helium", 70), ("held", 70),
+                       ("hero", 90), ("her", 5), ("apple", 1000)].iter() {
+
+        assert_eq!(map.insert(k, freq), None);
+    }
+
+    assert_eq!(map.len(), 8);
+    assert!(!map.is_empty());
+    assert_eq!(map.get("helium"), Some(&70));
+
+    let keys = |found: Vec<(String, &u32)>| found.into_iter().map(|(k, _)| k).collect::<Vec<String>>();
+
+    assert_eq!(map.top_k_complete("he", 3), [("hello".to_string(), &100), ("hero".to_string(), &90), ("held".to_string(), &70)]);
+    assert_eq!(keys(map.top_k_complete("hel", 10)), ["hello", "held", "helium", "help", "helm"]);
+    assert_eq!(keys(map.top_k_complete("", 2)), ["apple", "hello"]);
+    assert_eq!(keys(map.top_k_complete("her", 2)), ["hero"]);
+    assert!(map.top_k_complete("he", 0).is_empty());
+    assert!(map.top_k_complete("x", 3).is_empty());
+
+    assert_eq!(map.max_weight(""), Some(1000));
+    assert_eq!(map.max_weight("hel"), Some(100));
+    assert_eq!(map.max_weight("helm"), None);
+
+    ////////////////////////////////////////////////////
+
+    assert_eq!(map.remove("hello"), Some(100));
+    assert_eq!(map.remove("hello"), None);
+    assert_eq!(map.len(), 7);
+
+    assert_eq!(map.max_weight("hel"), Some(70));
+    assert_eq!(keys(map.top_k_complete("he", 2)), ["hero", "held"]);
+
+    assert_eq!(map.insert("helm", 200), Some(10));
+    assert_eq!(map.max_weight("he"), Some(200));
+    assert_eq!(keys(map.top_k_complete("he", 2)), ["helm", "hero"]);
+
+    for k in ["help", "helm", "helium", "held", "hero", "her", "apple"].iter() {
+
+        map.remove(k);
+    }
+
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+    assert_eq!(map.max_weight(""), None);
+
+    map.insert("a", 1);
+    map.clear();
+
+    assert_eq!(map.len(), 0);
+    assert!(map.top_k_complete("", 1).is_empty());
+
+    ////////////////////////////////////////////////////
+
+    //Keys of the same weight come in the order of the map
+    let mut map = ScoredTst::with_order(|freq: &u32| *freq, |a: char, b: char| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()));
+
+    for &(k, freq) in [("held", 70), ("helium", 70), ("hero", 90), ("Help", 50)].iter() {
+
+        assert_eq!(map.insert(k, freq), None);
+    }
+
+    assert_eq!(map.insert("HELD", 60), Some(70));
+    assert_eq!(map.get("hElP"), Some(&50));
+    assert_eq!(keys(map.top_k_complete("he", 10)), ["hero", "helium", "held", "help"]);
+    assert_eq!(map.remove("HERO"), Some(90));
+    assert_eq!(map.max_weight("hE"), Some(70));
+
+    let mut map = ScoredTst::with_order(|freq: &u32| *freq, |a: char, b: char| b.cmp(&a));
+
+    for &(k, freq) in [("held", 70), ("helium", 70), ("help", 70), ("hero", 90)].iter() {
+
+        map.insert(k, freq);
+    }
+
+    assert_eq!(keys(map.top_k_complete("he", 4)), ["hero", "help", "helium", "held"]);
+}
+
+
+#[test]
+fn tst_visit_anagram_values() {
+
+    let map = get_sample_map_abc_abc();
+    let mut v = Vec::new();
+
+    map.visit_anagram_values("cab", |s| v.push(*s));
+    assert_eq!(v, ["abc", "bac"]);
+
+    v.clear();
+    map.visit_anagram_values("aab", |s| v.push(*s));
+    assert_eq!(v, ["aab", "aba"]);
+
+    v.clear();
+    map.visit_anagram_values("caa", |s| v.push(*s));
+    assert_eq!(v, ["aca", "caa"]);
+
+    v.clear();
+    map.visit_anagram_values("a", |s| v.push(*s));
+    assert_eq!(v, ["a"]);
+
+    v.clear();
+    map.visit_anagram_values("ba", |s| v.push(*s));
+    assert_eq!(v, ["ab"]);
+
+    v.clear();
+    map.visit_anagram_values("bbx", |s| v.push(*s));
+    map.visit_anagram_values("", |s| v.push(*s));
+    assert!(v.is_empty());
+
+    ////////////////////////////////////////////////////
+
+    let mut map = get_sample_map_abc_count();
+
+    map.visit_anagram_values_mut("bca", |c| *c = 0);
+
+    assert_eq!(map.get("abc"), Some(&0));
+    assert_eq!(map.get("bac"), Some(&0));
+    assert_eq!(map.get("bc"), Some(&3));
+}
+
+
+#[test]
+fn tst_iterate_with_anagrams() {
+
+    let map = get_sample_map_abc_abc();
+
+    let mut it = map.iter_anagrams("aca");
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        w.push(it.current_key());
+    }
+
+    assert_eq!(v, ["aca", "caa"]);
+    assert_eq!(w, ["aca", "caa"]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_anagrams("bca");
+    v.clear();
+    w.clear();
+
+    while let Some(value) = it.next_back() {
+
+        v.push(*value);
+        w.push(it.current_key_back());
+    }
+
+    assert_eq!(v, ["bac", "abc"]);
+    assert_eq!(w, ["bac", "abc"]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_anagrams("aab");
+
+    assert_eq!(it.next(), Some(&"aab"));
+    assert_eq!(it.next_back(), Some(&"aba"));
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next_back(), None);
+
+    let mut it = map.iter_anagrams("b");
+
+    assert_eq!(it.next_back(), Some(&"b"));
+    assert_eq!(it.next(), None);
+
+    assert_eq!(map.iter_anagrams("").next(), None);
+    assert_eq!(map.iter_anagrams("abcd").next(), None);
+    assert_eq!(map.iter_anagrams("cc").next(), None);
+}
+
+
+#[test]
+fn tst_visit_rack_values() {
+
+    let map = get_sample_map_abc_abc();
+    let mut v = Vec::new();
+
+    map.visit_rack_values("ab", '?', |s| v.push(*s));
+    assert_eq!(v, ["a", "ab", "b"]);
+
+    v.clear();
+    map.visit_rack_values("abc", '?', |s| v.push(*s));
+    assert_eq!(v, ["a", "ab", "abc", "ac", "b", "bac", "bc", "c"]);
+
+    v.clear();
+    map.visit_rack_values("a?", '?', |s| v.push(*s));
+    assert_eq!(v, ["a", "aa", "ab", "ac", "b", "c"]);
+
+    v.clear();
+    map.visit_rack_values("??", '?', |s| v.push(*s));
+    assert_eq!(v, ["a", "aa", "ab", "ac", "b", "bc", "c"]);
+
+    v.clear();
+    map.visit_rack_values("", '?', |s| v.push(*s));
+    map.visit_rack_values("xyz", '?', |s| v.push(*s));
+    assert!(v.is_empty());
+
+    ////////////////////////////////////////////////////
+
+    v.clear();
+    map.visit_rack_crossword_values("c?", "a??", '?', |s| v.push(*s));
+    assert_eq!(v, ["abc", "aca"]);
+
+    v.clear();
+    map.visit_rack_crossword_values("ac", "?b?", '?', |s| v.push(*s));
+    assert_eq!(v, ["abc"]);
+
+    v.clear();
+    map.visit_rack_crossword_values("b", "?b?", '?', |s| v.push(*s));
+    map.visit_rack_crossword_values("abc", "", '?', |s| v.push(*s));
+    assert!(v.is_empty());
+
+    ////////////////////////////////////////////////////
+
+    let mut map = get_sample_map_abc_count();
+
+    map.visit_rack_values_mut("b?", '?', |c| *c = 0);
+    map.visit_rack_crossword_values_mut("a", "c?a", '?', |c| *c = 100);
+
+    let mut v = Vec::new();
+    map.visit_values(|c| v.push(*c));
+
+    assert_eq!(v, [0, 16, 14, 0, 1, 15, 5, 4, 8, 0, 11, 0, 0, 100, 10, 13]);
+}
+
+
+#[test]
+fn tst_iterate_with_rack() {
+
+    let map = get_sample_map_abc_abc();
+
+    let mut it = map.iter_rack("abc", '?');
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        w.push(it.current_key());
+    }
+
+    assert_eq!(v, ["a", "ab", "abc", "ac", "b", "bac", "bc", "c"]);
+    assert_eq!(w, v);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_rack("a?", '?');
+    v.clear();
+    w.clear();
+
+    while let Some(value) = it.next_back() {
+
+        v.push(*value);
+        w.push(it.current_key_back());
+    }
+
+    assert_eq!(v, ["c", "b", "ac", "ab", "aa", "a"]);
+    assert_eq!(w, v);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_rack_crossword("c?", "a??", '?');
+
+    assert_eq!(it.next(), Some(&"abc"));
+    assert_eq!(it.current_key(), "abc");
+    assert_eq!(it.next_back(), Some(&"aca"));
+    assert_eq!(it.current_key_back(), "aca");
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next_back(), None);
+
+    let v: Vec<&str> = map.iter_rack_crossword("ac", "?b?", '?').cloned().collect();
+    assert_eq!(v, ["abc"]);
+
+    assert_eq!(map.iter_rack("", '?').next(), None);
+    assert_eq!(map.iter_rack_crossword("b", "?b?", '?').next(), None);
+}
+
+
+#[test]
+fn tst_find_in_grid() {
+
+    use ternary_tree::Adjacency;
+
+    let mut map = Tst::new();
+
+    for (i, k) in ["cat", "car", "cart", "tar", "rat", "star", "ten", "net", "eat", "arts", "cats", "cast", "tat", "dog"].iter().enumerate() {
+
+        map.insert(k, i);
+    }
+
+    let grid = vec![vec!['c', 'a', 't'],
+                    vec!['a', 'r', 's'],
+                    vec!['t', 'e', 'n']];
+
+    let found = map.find_in_grid(&grid, Adjacency::OrthogonalAndDiagonal);
+    let keys: Vec<&str> = found.iter().map(|(k, _)| k.as_str()).collect();
+
+    assert_eq!(keys, ["arts", "car", "cart", "cast", "cat", "cats", "eat", "net", "rat", "star", "tar", "ten"]);
+    assert_eq!(found[4], ("cat".to_string(), &0));
+
+    let found = map.find_in_grid(&grid, Adjacency::Orthogonal);
+    let keys: Vec<&str> = found.iter().map(|(k, _)| k.as_str()).collect();
+
+    assert_eq!(keys, ["car", "cat", "cats", "net", "rat", "star", "tar", "ten"]);
+
+    ////////////////////////////////////////////////////
+
+    let ragged = vec![vec!['d', 'o'], vec!['x', 'g', 'z'], vec![]];
+
+    let found = map.find_in_grid(&ragged, Adjacency::Orthogonal);
+    assert_eq!(found, [("dog".to_string(), &13)]);
+
+    assert!(map.find_in_grid(&[], Adjacency::Orthogonal).is_empty());
+
+    let empty_map: Tst<usize> = Tst::new();
+    assert!(empty_map.find_in_grid(&grid, Adjacency::OrthogonalAndDiagonal).is_empty());
+}
+
+
+#[test]
+fn tst_find_subsequence() {
+
+    let mut map = Tst::new();
+
+    for k in ["FooBar", "file_browser", "fb", "fabric", "forbid", "abc", "feedback"].iter() {
+
+        map.insert(k, k.len());
+    }
+
+    let found = map.find_subsequence("fb");
+    let keys: Vec<&str> = found.iter().map(|(k, _, _)| k.as_str()).collect();
+    let scores: Vec<i32> = found.iter().map(|(_, _, s)| *s).collect();
+
+    assert_eq!(keys, ["FooBar", "fabric", "fb", "feedback", "file_browser", "forbid"]);
+    assert_eq!(scores, [53, 47, 64, 45, 52, 46]);
+    assert_eq!(found[0].1, &6);
+
+    //A contiguous match at the start beats matches at word boundaries
+    let found = map.top_k_subsequence("fb", 3);
+    let keys: Vec<&str> = found.iter().map(|(k, _, _)| k.as_str()).collect();
+
+    assert_eq!(keys, ["fb", "FooBar", "file_browser"]);
+
+    let mut words = Tst::new();
+
+    for k in ["FooBar", "foo_bar", "fb", "fbx", "xfb"].iter() {
+
+        words.insert(k, ());
+    }
+
+    let found = words.top_k_subsequence("fb", 5);
+    let keys: Vec<&str> = found.iter().map(|(k, _, _)| k.as_str()).collect();
+
+    assert_eq!(keys, ["fb", "fbx", "FooBar", "foo_bar", "xfb"]);
+
+    ////////////////////////////////////////////////////
+
+    let keys: Vec<String> = map.find_subsequence("FB").into_iter().map(|(k, _, _)| k).collect();
+    assert_eq!(keys, ["FooBar"]);
+
+    let keys: Vec<String> = map.find_subsequence("bid").into_iter().map(|(k, _, _)| k).collect();
+    assert_eq!(keys, ["forbid"]);
+
+    let keys: Vec<String> = map.top_k_subsequence("fbr", 10).into_iter().map(|(k, _, _)| k).collect();
+    assert_eq!(keys, ["file_browser", "FooBar", "fabric"]);
+
+    assert!(map.find_subsequence("fB").is_empty());
+    assert!(map.find_subsequence("xyz").is_empty());
+    assert_eq!(map.find_subsequence("").len(), 7);
+    assert!(map.top_k_subsequence("fb", 0).is_empty());
+}
+
+
+#[test]
+fn tst_iterate_with_fuzzy_complete() {
+
+    let mut map = Tst::new();
+
+    for k in ["help", "hello", "helper", "helpful", "hero", "heap", "hemp", "world", "he"].iter() {
+
+        map.insert(k, *k);
+    }
+
+    let mut it = map.iter_fuzzy_complete("hepl", 1);
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+    let mut d = Vec::new();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        w.push(it.current_key());
+        d.push(it.current_distance().unwrap());
+    }
+
+    assert_eq!(v, ["hello", "help", "helper", "helpful"]);
+    assert_eq!(w, v);
+    assert_eq!(d, [1, 1, 1, 1]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_fuzzy_complete("hepl", 2);
+    v.clear();
+    w.clear();
+    d.clear();
+
+    while let Some(value) = it.next_back() {
+
+        v.push(*value);
+        w.push(it.current_key_back());
+        d.push(it.current_distance_back().unwrap());
+    }
+
+    assert_eq!(v, ["hero", "hemp", "helpful", "helper", "help", "hello", "heap", "he"]);
+    assert_eq!(w, v);
+    assert_eq!(d, [2, 2, 1, 1, 1, 1, 2, 2]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_fuzzy_complete("help", 0);
+
+    assert_eq!(it.current_distance(), None);
+    assert_eq!(it.next(), Some(&"help"));
+    assert_eq!(it.current_distance(), Some(0));
+    assert_eq!(it.next_back(), Some(&"helpful"));
+    assert_eq!(it.next(), Some(&"helper"));
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next_back(), None);
+
+    assert_eq!(map.iter_fuzzy_complete("wrold", 1).collect::<Vec<_>>(), [&"world"]);
+    assert_eq!(map.iter_fuzzy_complete("xyz", 2).next(), None);
+    assert_eq!(map.iter_fuzzy_complete("", 0).count(), 9);
+}
+
+
+#[test]
+fn tst_visit_crossword_neighbor_values() {
+
+    let map = get_sample_map_abc_abc();
+    let mut v = Vec::new();
+    let mut m = Vec::new();
+
+    map.visit_crossword_neighbor_values("a?c", '?', 0, |s, mismatches| { v.push(*s); m.push(mismatches.to_vec()); });
+    assert_eq!(v, ["abc"]);
+    assert_eq!(m, [Vec::<usize>::new()]);
+
+    v.clear();
+    m.clear();
+
+    map.visit_crossword_neighbor_values("a?c", '?', 1, |s, mismatches| { v.push(*s); m.push(mismatches.to_vec()); });
+    assert_eq!(v, ["aab", "aba", "abb", "abc", "aca", "bac", "cbc"]);
+    assert_eq!(m, [vec![2], vec![2], vec![2], vec![], vec![2], vec![0], vec![0]]);
+
+    v.clear();
+    m.clear();
+
+    map.visit_crossword_neighbor_values("a?c", '?', 2, |s, mismatches| { v.push(*s); m.push(mismatches.to_vec()); });
+    assert_eq!(v, ["aab", "aba", "abb", "abc", "aca", "bac", "caa", "cbc", "cca"]);
+    assert_eq!(m[6], [0, 2]);
+
+    v.clear();
+    map.visit_crossword_neighbor_values("??", '?', 1, |s, _| v.push(*s));
+    assert_eq!(v, ["aa", "ab", "ac", "bc"]);
+
+    v.clear();
+    map.visit_crossword_neighbor_values("", '?', 1, |s, _| v.push(*s));
+    assert!(v.is_empty());
+
+    ////////////////////////////////////////////////////
+
+    let mut map = get_sample_map_abc_count();
+
+    map.visit_crossword_neighbor_values_mut("c?c", '?', 1, |c, mismatches| *c = mismatches.len() * 100);
+
+    assert_eq!(map.get("cbc"), Some(&0));
+    assert_eq!(map.get("abc"), Some(&100));
+    assert_eq!(map.get("caa"), Some(&100));
+    assert_eq!(map.get("aca"), Some(&8));
+}
+
+
+#[test]
+fn tst_iterate_with_crossword_neighbor() {
+
+    let map = get_sample_map_abc_abc();
+
+    let mut it = map.iter_crossword_neighbor("a?c", '?', 1);
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+    let mut m = Vec::new();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        w.push(it.current_key());
+        m.push(it.current_mismatches());
+    }
+
+    assert_eq!(v, ["aab", "aba", "abb", "abc", "aca", "bac", "cbc"]);
+    assert_eq!(w, v);
+    assert_eq!(m, [vec![2], vec![2], vec![2], vec![], vec![2], vec![0], vec![0]]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_crossword_neighbor("a?c", '?', 1);
+    v.clear();
+    w.clear();
+    m.clear();
+
+    while let Some(value) = it.next_back() {
+
+        v.push(*value);
+        w.push(it.current_key_back());
+        m.push(it.current_mismatches_back());
+    }
+
+    assert_eq!(v, ["cbc", "bac", "aca", "abc", "abb", "aba", "aab"]);
+    assert_eq!(w, v);
+    assert_eq!(m, [vec![0], vec![0], vec![2], vec![], vec![2], vec![2], vec![2]]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_crossword_neighbor("c?a", '?', 0);
+
+    assert_eq!(it.next(), Some(&"caa"));
+    assert_eq!(it.next_back(), Some(&"cca"));
+    assert_eq!(it.next(), None);
+    assert_eq!(it.next_back(), None);
+
+    assert_eq!(map.iter_crossword_neighbor("a?c", '?', 2).count(), 9);
+    assert_eq!(map.iter_crossword_neighbor("", '?', 2).next(), None);
+}
+
+
+#[test]
+fn tst_visit_weighted_neighbor_values() {
+
+    use ternary_tree::{CostMatrix, KeyboardLayout, SubstitutionCost};
+
+    let mut map = Tst::new();
+
+    for k in ["cat", "vat", "mat", "car", "cut", "cart", "ca"].iter() {
+
+        map.insert(k, *k);
+    }
+
+    let qwerty = KeyboardLayout::qwerty();
+    let azerty = KeyboardLayout::azerty();
+
+    assert_eq!(qwerty.substitution_cost('q', 'a'), 0.5);
+    assert_eq!(qwerty.substitution_cost('A', 'a'), 0.5);
+    assert_eq!(qwerty.substitution_cost('m', 'l'), 1.0);
+    assert_eq!(azerty.substitution_cost('m', 'l'), 0.5);
+    assert_eq!(azerty.substitution_cost('a', 'p'), 1.0);
+
+    let mut v = Vec::new();
+    map.visit_weighted_neighbor_values("xat", 1.0, &qwerty, |s, cost| v.push((*s, cost)));
+    assert_eq!(v, [("car", 1.0), ("cat", 0.5), ("mat", 1.0), ("vat", 1.0)]);
+
+    v.clear();
+    map.visit_weighted_neighbor_values("xat", 0.5, &qwerty, |s, cost| v.push((*s, cost)));
+    assert_eq!(v, [("cat", 0.5)]);
+
+    ////////////////////////////////////////////////////
+
+    let mut matrix = CostMatrix::new(1.0);
+    matrix.set('i', 'a', 0.25);
+    matrix.set('i', 'u', 0.25);
+
+    v.clear();
+    map.visit_weighted_neighbor_values("cit", 0.5, &matrix, |s, cost| v.push((*s, cost)));
+    assert_eq!(v, [("cat", 0.25), ("cut", 0.25)]);
+
+    v.clear();
+    map.visit_weighted_neighbor_values("ca", 1.0, &|_, _| 0.5, |s, cost| v.push((*s, cost)));
+    assert_eq!(v, [("ca", 0.0), ("car", 1.0), ("cat", 1.0)]);
+
+    ////////////////////////////////////////////////////
+
+    let mut costs = Tst::new();
+
+    for k in ["cat", "vat", "mat", "car"].iter() {
+
+        costs.insert(k, 0.0);
+    }
+
+    costs.visit_weighted_neighbor_values_mut("xat", 1.0, &qwerty, |value, cost| *value = cost);
+
+    assert_eq!(costs.get("cat"), Some(&0.5));
+    assert_eq!(costs.get("car"), Some(&1.0));
+}
+
+
+#[test]
+fn tst_iterate_with_weighted_neighbor() {
+
+    use ternary_tree::KeyboardLayout;
+
+    let mut map = Tst::new();
+
+    for k in ["cat", "vat", "mat", "car", "cut", "cart", "ca"].iter() {
+
+        map.insert(k, *k);
+    }
+
+    let qwerty = KeyboardLayout::qwerty();
+
+    let mut it = map.iter_weighted_neighbor("xat", 1.0, &qwerty);
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+    let mut c = Vec::new();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        w.push(it.current_key());
+        c.push(it.current_cost().unwrap());
+    }
+
+    assert_eq!(v, ["car", "cat", "mat", "vat"]);
+    assert_eq!(w, v);
+    assert_eq!(c, [1.0, 0.5, 1.0, 1.0]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_weighted_neighbor("xat", 1.0, &qwerty);
+    v.clear();
+    w.clear();
+    c.clear();
+
+    while let Some(value) = it.next_back() {
+
+        v.push(*value);
+        w.push(it.current_key_back());
+        c.push(it.current_cost_back().unwrap());
+    }
+
+    assert_eq!(v, ["vat", "mat", "cat", "car"]);
+    assert_eq!(w, v);
+    assert_eq!(c, [1.0, 1.0, 0.5, 1.0]);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_weighted_neighbor("xat", 0.5, &qwerty);
+
+    assert_eq!(it.current_cost(), None);
+    assert_eq!(it.next(), Some(&"cat"));
+    assert_eq!(it.next_back(), None);
+    assert_eq!(it.next(), None);
+
+    ////////////////////////////////////////////////////
+
+    let mut it = map.iter_weighted_fuzzy_complete("xa", 0.5, &qwerty);
+    v.clear();
+    c.clear();
+
+    while let Some(value) = it.next() {
+
+        v.push(*value);
+        c.push(it.current_cost().unwrap());
+    }
+
+    assert_eq!(v, ["ca", "car", "cart", "cat"]);
+    assert_eq!(c, [0.5, 0.5, 0.5, 0.5]);
+
+    let v: Vec<_> = map.iter_fuzzy_complete("xa", 1).cloned().collect();
+    assert_eq!(v, ["ca", "car", "cart", "cat", "mat", "vat"]);
+}
+
+
+#[test]
+fn tst_find_sound_alike() {
+
+    use ternary_tree::{PhoneticTst, PhoneticEncoder, Soundex, FrenchPhonetic};
+
+    assert_eq!(Soundex.encode("Robert"), "R163");
+    assert_eq!(Soundex.encode("Rupert"), "R163");
+    assert_eq!(Soundex.encode("Tymczak"), "T522");
+    assert_eq!(Soundex.encode("Pfister"), "P236");
+    assert_eq!(Soundex.encode("Ashcraft"), "A261");
+    assert_eq!(Soundex.encode("Honeyman"), "H555");
+    assert_eq!(Soundex.encode("Rob"), "R1");
+    assert_eq!(Soundex.encode("42"), "");
+
+    let mut map = PhoneticTst::new();
+
+    for k in ["Smith", "Smyth", "Smart", "Robert", "Rupert", "Rubin", "Ashcraft", "42"].iter() {
+
+        map.insert(k, k.len());
+    }
+
+    assert_eq!(map.len(), 8);
+    assert_eq!(map.get("42"), Some(&2));
+    assert_eq!(map.find_sound_alike("smith"), [("Smith".to_string(), &5), ("Smyth".to_string(), &5)]);
+    assert_eq!(map.find_sound_alike("Schmitt").len(), 2);
+    assert_eq!(map.find_sound_alike("Smarty"), [("Smart".to_string(), &5)]);
+    assert_eq!(map.find_sound_alike("Smit").len(), 2);
+    assert!(map.find_sound_alike("Zorglub").is_empty());
+
+    //Keys without any letter have an empty code, and only sound like each other
+    assert_eq!(map.find_sound_alike("123"), [("42".to_string(), &2)]);
+
+    let v: Vec<_> = map.complete_sound_alike("Rob").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Robert", "Rubin", "Rupert"]);
+
+    let v: Vec<_> = map.complete_sound_alike("Sm").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Smart", "Smith", "Smyth"]);
+
+    assert!(map.complete_sound_alike("").is_empty());
+
+    ////////////////////////////////////////////////////
+
+    assert_eq!(map.remove("Smyth"), Some(5));
+    assert_eq!(map.remove("Smyth"), None);
+    assert_eq!(map.find_sound_alike("Smith"), [("Smith".to_string(), &5)]);
+
+    if let Some(value) = map.get_mut("Smith") {
+
+        *value = 0;
+    }
+
+    assert_eq!(map.find_sound_alike("Smyth"), [("Smith".to_string(), &0)]);
+    assert_eq!(map.remove("Smith"), Some(0));
+    assert!(map.find_sound_alike("Smyth").is_empty());
+    assert_eq!(map.remove("42"), Some(2));
+    assert!(map.find_sound_alike("123").is_empty());
+
+    map.clear();
+    assert_eq!(map.len(), 0);
+    assert!(map.is_empty());
+    assert!(map.complete_sound_alike("R").is_empty());
+
+    ////////////////////////////////////////////////////
+
+    let french = FrenchPhonetic;
+
+    assert_eq!(french.encode("Dupont"), french.encode("Dupond"));
+    assert_eq!(french.encode("Dupont"), french.encode("dupon"));
+    assert_eq!(french.encode("Bonnet"), french.encode("Bonet"));
+    assert_eq!(french.encode("Martin"), french.encode("Martain"));
+    assert_eq!(french.encode("Dufaut"), french.encode("Dufeau"));
+    assert_eq!(french.encode("Philippe"), french.encode("Filipe"));
+    assert_eq!(french.encode("Françoise"), french.encode("Fransoise"));
+    assert_ne!(french.encode("Dupont"), french.encode("Dupuis"));
+
+    let mut map: PhoneticTst<(), FrenchPhonetic> = PhoneticTst::default();
+
+    assert!(map.is_empty());
+
+    for k in ["Dupont", "Dupond", "Dupuis", "Dufeau", "Bonnet"].iter() {
+
+        map.insert(k, ());
+    }
+
+    let v: Vec<_> = map.find_sound_alike("Dupon").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Dupond", "Dupont"]);
+
+    //"Dup" may go on as "Duph", sounding as "Duf"
+    let v: Vec<_> = map.complete_sound_alike("Dup").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Dufeau", "Dupond", "Dupont", "Dupuis"]);
+
+    let v: Vec<_> = map.complete_sound_alike("Dupon").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Dupond", "Dupont"]);
+
+    let v: Vec<_> = map.find_sound_alike("Dufaut").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Dufeau"]);
+
+    //The last letters of a prefix may sound otherwise in a longer word
+    let mut map = PhoneticTst::with_encoder(FrenchPhonetic);
+
+    let words = ["Bon", "Bonnet", "Bonbon", "Bonne", "Bonnes", "Jean", "Jeanne", "Jeannot", "Bernard", "Paul", "Pascal",
+                 "Roger", "Rogers", "Grand", "Grandes", "Cécile", "Cassis", "Beau", "Beauté", "Gaston", "Guillaume"];
+
+    for k in words.iter() {
+
+        map.insert(k, ());
+    }
+
+    let v: Vec<_> = map.complete_sound_alike("Bon").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Bon", "Bonbon", "Bonne", "Bonnes", "Bonnet"]);
+
+    let v: Vec<_> = map.complete_sound_alike("Jean").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Jean", "Jeanne", "Jeannot"]);
+
+    let v: Vec<_> = map.complete_sound_alike("Pa").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(v, ["Pascal", "Paul"]);
+
+    //Every key is found from each of its prefixes
+    for k in words.iter() {
+
+        for (end, _) in k.char_indices().skip(1) {
+
+            assert!(map.complete_sound_alike(&k[..end]).iter().any(|(key, _)| key == k), "{} from {}", k, &k[..end]);
+        }
+    }
+
+    ////////////////////////////////////////////////////
+
+    let mut map = PhoneticTst::with_encoder(|word: &str| word.to_lowercase());
+
+    map.insert("Rust", 1);
+    assert_eq!(map.find_sound_alike("RUST"), [("Rust".to_string(), &1)]);
+}
+
+
+const SEARCH_VEC : [&str; 18] = ["cat", "car", "cart", "tar", "rat", "star", "ten", "net", "eat", "arts", "cats", "cast",
+                                 "help", "hello", "helper", "hemp", "heap", "he"];
+
+
+//Checks that the searches of a `Tst` variant holding SEARCH_VEC, each key with itself as value,
+//find the same values as a `Tst`, from both ends
+macro_rules! assert_same_searches {
+
+    ($map:expr) => {{
+
+        use ternary_tree::{Adjacency, KeyboardLayout};
+
+        let map = &$map;
+        let mut tst = Tst::new();
+
+        for k in SEARCH_VEC.iter() {
+
+            tst.insert(k, *k);
+        }
+
+        let mut v = Vec::new();
+
+        for value in map {
+
+            v.push(*value);
+        }
+
+        assert_eq!(v, tst.iter().cloned().collect::<Vec<_>>());
+        assert_eq!(map.iter().rev().cloned().collect::<Vec<_>>(), tst.iter().rev().cloned().collect::<Vec<_>>());
+
+        for &(key, range) in [("cat", 1), ("c?t", 1), ("??r?", 1), ("hemp", 2), ("he??", 0), ("", 1)].iter() {
+
+            let mut v = Vec::new();
+            let mut w = Vec::new();
+            map.visit_crossword_neighbor_values(key, '?', range, |s, m| v.push((*s, m.to_vec())));
+            tst.visit_crossword_neighbor_values(key, '?', range, |s, m| w.push((*s, m.to_vec())));
+            assert_eq!(v, w);
+
+            let mut it = map.iter_crossword_neighbor(key, '?', range);
+            v.clear();
+
+            while let Some(value) = it.next_back() {
+
+                v.push((*value, it.current_mismatches_back()));
+            }
+
+            w.reverse();
+            assert_eq!(v, w);
+        }
+
+        let qwerty = KeyboardLayout::qwerty();
+
+        for &(key, budget) in [("cat", 1.0), ("vat", 0.5), ("hemo", 1.5), ("ca", 1.0)].iter() {
+
+            let mut v = Vec::new();
+            let mut w = Vec::new();
+            map.visit_weighted_neighbor_values(key, budget, &qwerty, |s, cost| v.push((*s, cost)));
+            tst.visit_weighted_neighbor_values(key, budget, &qwerty, |s, cost| w.push((*s, cost)));
+            assert_eq!(v, w);
+
+            let mut it = map.iter_weighted_neighbor(key, budget, &qwerty);
+            v.clear();
+
+            while let Some(value) = it.next() {
+
+                v.push((*value, it.current_cost().unwrap()));
+            }
+
+            assert_eq!(v, w);
+        }
+
+        for letters in ["tac", "tars", "ehlp", "", "xyz"].iter() {
+
+            let mut v = Vec::new();
+            let mut w = Vec::new();
+            map.visit_anagram_values(letters, |s| v.push(*s));
+            tst.visit_anagram_values(letters, |s| w.push(*s));
+            assert_eq!(v, w);
+            assert_eq!(map.iter_anagrams(letters).rev().cloned().collect::<Vec<_>>(), tst.iter_anagrams(letters).rev().cloned().collect::<Vec<_>>());
+        }
+
+        for rack in ["tca?", "arst", "??", "hlpe"].iter() {
+
+            let mut v = Vec::new();
+            let mut w = Vec::new();
+            map.visit_rack_values(rack, '?', |s| v.push(*s));
+            tst.visit_rack_values(rack, '?', |s| w.push(*s));
+            assert_eq!(v, w);
+            assert_eq!(map.iter_rack(rack, '?').cloned().collect::<Vec<_>>(), w);
+
+            for pattern in ["?a??", "c??", "??t", "h?l?"].iter() {
+
+                let mut v = Vec::new();
+                let mut w = Vec::new();
+                map.visit_rack_crossword_values(rack, pattern, '?', |s| v.push(*s));
+                tst.visit_rack_crossword_values(rack, pattern, '?', |s| w.push(*s));
+                assert_eq!(v, w);
+                assert_eq!(map.iter_rack_crossword(rack, pattern, '?').rev().cloned().collect::<Vec<_>>(),
+                           tst.iter_rack_crossword(rack, pattern, '?').rev().cloned().collect::<Vec<_>>());
+            }
+        }
+
+        for &(prefix, dist) in [("hepl", 1), ("ct", 1), ("", 0), ("xx", 2)].iter() {
+
+            let mut v = Vec::new();
+            let mut w = Vec::new();
+            let mut it = map.iter_fuzzy_complete(prefix, dist);
+
+            while let Some(value) = it.next() {
+
+                v.push((*value, it.current_key(), it.current_distance()));
+            }
+
+            let mut it = tst.iter_fuzzy_complete(prefix, dist);
+
+            while let Some(value) = it.next() {
+
+                w.push((*value, it.current_key(), it.current_distance()));
+            }
+
+            assert_eq!(v, w);
+
+            let mut v = Vec::new();
+            let mut w = Vec::new();
+            let mut it = map.iter_weighted_fuzzy_complete(prefix, dist as f64, &qwerty);
+
+            while let Some(value) = it.next_back() {
+
+                v.push((*value, it.current_cost_back()));
+            }
+
+            let mut it = tst.iter_weighted_fuzzy_complete(prefix, dist as f64, &qwerty);
+
+            while let Some(value) = it.next_back() {
+
+                w.push((*value, it.current_cost_back()));
+            }
+
+            assert_eq!(v, w);
+        }
+
+        let grid = vec![vec!['c', 'a', 't'],
+                        vec!['a', 'r', 's'],
+                        vec!['t', 'e', 'n']];
+
+        for &adjacency in [Adjacency::Orthogonal, Adjacency::OrthogonalAndDiagonal].iter() {
+
+            assert_eq!(map.find_in_grid(&grid, adjacency), tst.find_in_grid(&grid, adjacency));
+        }
+
+        for query in ["ct", "hl", "ats", "", "zz"].iter() {
+
+            assert_eq!(map.find_subsequence(query), tst.find_subsequence(query));
+            assert_eq!(map.top_k_subsequence(query, 3), tst.top_k_subsequence(query, 3));
+        }
+
+        //A tree has one edge less than nodes
+        let mut w = Vec::new();
+        map.pretty_print(&mut w);
+
+        let dot = String::from_utf8(w).unwrap();
+        let nodes = dot.lines().filter(|line| line.contains(" [label=")).count();
+        let edges = dot.lines().filter(|line| line.contains(" -> ")).count();
+
+        assert!(dot.starts_with("digraph {\n"));
+        assert!(dot.ends_with("}\n"));
+        assert_eq!(edges + 1, nodes);
+    }};
+}
+
+
+#[test]
+fn tst_compressed_labels() {
+
+    use ternary_tree::CompressedTst;
+
+    let mut map: CompressedTst<usize> = CompressedTst::default();
+
+    assert!(map.is_empty());
+
+    for k in ["https://example.com/index.html", "https://example.com/about.html", "https://example.org/"].iter() {
+
+        assert_eq!(map.insert(k, k.len()), None);
+    }
+
+    assert_eq!(map.len(), 3);
+    assert!(!map.is_empty());
+    assert_eq!(map.stat().count.nodes, 5);
+    assert_eq!(map.stat().key_len.max, 30);
+    assert_eq!(map.get("https://example.com/index.html"), Some(&30));
+    assert_eq!(map.get("https://example.com/"), None);
+    assert_eq!(map.get("https://example.org"), None);
+    assert_eq!(map.get(""), None);
+
+    //Inserting a key ending inside a label splits it
+    assert_eq!(map.insert("https://example.com/", 0), None);
+    assert_eq!(map.stat().count.nodes, 5);
+    assert_eq!(map.insert("https://example.com/ind", 23), None);
+    assert_eq!(map.stat().count.nodes, 6);
+    assert_eq!(map.get("https://example.com/"), Some(&0));
+    assert_eq!(map.get("https://example.com/ind"), Some(&23));
+    assert_eq!(map.remove("https://example.com/ind"), Some(23));
+    assert_eq!(map.stat().count.nodes, 5);
+
+    if let Some(value) = map.get_mut("https://example.com/") {
+
+        *value = 20;
+    }
+
+    assert_eq!(map.insert("https://example.com/", 19), Some(20));
+
+    //Removing keys merges labels back
+    assert_eq!(map.remove("https://example.com/"), Some(19));
+    assert_eq!(map.stat().count.nodes, 5);
+    assert_eq!(map.remove("https://example.com/about.html"), Some(30));
+    assert_eq!(map.stat().count.nodes, 3);
+    assert_eq!(map.remove("https://example.com/about.html"), None);
+    assert_eq!(map.remove("https://example.org/"), Some(20));
+    assert_eq!(map.stat().count.nodes, 1);
+    assert_eq!(map.get("https://example.com/index.html"), Some(&30));
+    assert_eq!(map.remove("https://example.com/index.html"), Some(30));
+    assert_eq!(map.stat().count.nodes, 0);
+    assert_eq!(map.len(), 0);
+
+    ////////////////////////////////////////////////////
+
+    let map = get_sample_map_abc_abc();
+    let mut compressed = CompressedTst::new();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        compressed.insert(k, *k);
+    }
+
+    assert_eq!(compressed.len(), map.len());
+    assert!(compressed.stat().count.nodes < map.stat().count.nodes);
+    assert_eq!(compressed.iter().cloned().collect::<Vec<_>>(), SORTED_VEC_123);
+    assert_eq!(compressed.iter().rev().cloned().collect::<Vec<_>>(), map.iter().rev().cloned().collect::<Vec<_>>());
+
+    let mut v = Vec::new();
+    compressed.visit_values(|s| v.push(*s));
+    assert_eq!(v, SORTED_VEC_123);
+
+    for prefix in ["", "a", "ab", "abc", "c", "x"].iter() {
+
+        let mut v = Vec::new();
+        let mut w = Vec::new();
+        compressed.visit_complete_values(prefix, |s| v.push(*s));
+        map.visit_complete_values(prefix, |s| w.push(*s));
+        assert_eq!(v, w);
+
+        let mut it = compressed.iter_complete(prefix);
+        v.clear();
+
+        while let Some(value) = it.next() {
+
+            assert_eq!(it.current_key(), *value);
+            v.push(*value);
+        }
+
+        assert_eq!(v, w);
+    }
+
+    for &(key, range) in [("abc", 0), ("abc", 1), ("ac", 1), ("bb", 2), ("", 1), ("aaaa", 2)].iter() {
+
+        let mut v = Vec::new();
+        let mut w = Vec::new();
+        compressed.visit_neighbor_values(key, range, |s| v.push(*s));
+        map.visit_neighbor_values(key, range, |s| w.push(*s));
+        assert_eq!(v, w);
+
+        let mut it = compressed.iter_neighbor(key, range);
+        v.clear();
+
+        while let Some(value) = it.next_back() {
+
+            assert_eq!(it.current_key_back(), *value);
+            v.push(*value);
+        }
+
+        w.reverse();
+        assert_eq!(v, w);
+    }
+
+    for key in ["a?a", "?a?", "??", "c??", "?", ""].iter() {
+
+        let mut v = Vec::new();
+        let mut w = Vec::new();
+        compressed.visit_crossword_values(key, '?', |s| v.push(*s));
+        map.visit_crossword_values(key, '?', |s| w.push(*s));
+        assert_eq!(v, w);
+        assert_eq!(compressed.iter_crossword(key, '?').cloned().collect::<Vec<_>>(), w);
+    }
+
+    ////////////////////////////////////////////////////
+
+    let mut it = compressed.iter_complete("a");
+
+    assert_eq!(it.next(), Some(&"aa"));
+    assert_eq!(it.next_back(), Some(&"aca"));
+    assert_eq!(it.next(), Some(&"aab"));
+    assert_eq!(it.next_back(), Some(&"ac"));
+    assert_eq!(it.by_ref().count(), 4);
+    assert_eq!(it.next_back(), None);
+
+    let mut counts = CompressedTst::new();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        counts.insert(k, 0);
+    }
+
+    counts.visit_values_mut(|value| *value += 1);
+    counts.visit_complete_values_mut("a", |value| *value += 1);
+    counts.visit_neighbor_values_mut("bac", 1, |value| *value += 10);
+    counts.visit_crossword_values_mut("?a?", '?', |value| *value += 100);
+
+    assert_eq!(counts.get("aab"), Some(&102));
+    assert_eq!(counts.get("bac"), Some(&111));
+    assert_eq!(counts.get("bc"), Some(&1));
+    assert_eq!(counts.get("abc"), Some(&2));
+
+    counts.clear();
+    assert_eq!(counts.len(), 0);
+    assert_eq!(counts.iter().next(), None);
+}
+
+
+#[test]
+fn tst_compressed_searches() {
+
+    use ternary_tree::{CompressedTst, KeyboardLayout};
+
+    let mut map = CompressedTst::new();
+
+    for k in SEARCH_VEC.iter() {
+
+        map.insert(k, *k);
+    }
+
+    assert_same_searches!(map);
+
+    ////////////////////////////////////////////////////
+
+    let mut map = CompressedTst::with_order(french_order);
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.insert(k, *k), None);
+    }
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.get(k), Some(k));
+    }
+
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), ["cote", "coté", "cotte", "côte", "côté", "cz"]);
+    assert_eq!(map.iter_complete("cô").rev().cloned().collect::<Vec<_>>(), ["côté", "côte"]);
+    assert_eq!(map.remove("coté"), Some("coté"));
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), ["cote", "cotte", "côte", "côté", "cz"]);
+
+    //Different chars may compare equal, then keys differing by them are the same key
+    let mut map = CompressedTst::with_order(|a: char, b: char| a.to_ascii_lowercase().cmp(&b.to_ascii_lowercase()));
+
+    assert_eq!(map.insert("abc", 1), None);
+    assert_eq!(map.insert("Abd", 2), None);
+    assert_eq!(map.insert("ABC", 3), Some(1));
+    assert_eq!(map.insert("aB", 4), None);
+    assert_eq!(map.len(), 3);
+
+    assert_eq!(map.get("abD"), Some(&2));
+    assert_eq!(map.get("AB"), Some(&4));
+    assert_eq!(map.get("a"), None);
+    assert_eq!(map.get("abcd"), None);
+
+    if let Some(value) = map.get_mut("ABD") {
+
+        *value = 20;
+    }
+
+    assert_eq!(map.remove("aBc"), Some(3));
+    assert_eq!(map.remove("abc"), None);
+
+    let mut it = map.iter();
+
+    assert_eq!(it.next(), Some(&4));
+    assert_eq!(it.current_key(), "ab");
+    assert_eq!(it.next(), Some(&20));
+    assert_eq!(it.current_key(), "abd");
+    assert_eq!(it.next(), None);
+
+    ////////////////////////////////////////////////////
+
+    let qwerty = KeyboardLayout::qwerty();
+    let mut counts = CompressedTst::new();
+    let mut tst_counts = Tst::new();
+
+    for k in SEARCH_VEC.iter() {
+
+        counts.insert(k, 0);
+        tst_counts.insert(k, 0);
+    }
+
+    counts.visit_crossword_neighbor_values_mut("c?t", '?', 1, |value, mismatches| *value += 1 + mismatches.len());
+    counts.visit_weighted_neighbor_values_mut("vat", 1.0, &qwerty, |value, _| *value += 10);
+    counts.visit_anagram_values_mut("tars", |value| *value += 100);
+    counts.visit_rack_values_mut("tca?", '?', |value| *value += 1000);
+    counts.visit_rack_crossword_values_mut("hlpe", "h?l?", '?', |value| *value += 10000);
+
+    tst_counts.visit_crossword_neighbor_values_mut("c?t", '?', 1, |value, mismatches| *value += 1 + mismatches.len());
+    tst_counts.visit_weighted_neighbor_values_mut("vat", 1.0, &qwerty, |value, _| *value += 10);
+    tst_counts.visit_anagram_values_mut("tars", |value| *value += 100);
+    tst_counts.visit_rack_values_mut("tca?", '?', |value| *value += 1000);
+    tst_counts.visit_rack_crossword_values_mut("hlpe", "h?l?", '?', |value| *value += 10000);
+
+    assert_eq!(counts.iter().cloned().collect::<Vec<_>>(), tst_counts.iter().cloned().collect::<Vec<_>>());
+    assert_eq!(counts.get("cat"), Some(&1011));
+    assert_eq!(counts.get("help"), Some(&10000));
+}
+
+
+#[test]
+fn tst_arena_nodes() {
+
+    use ternary_tree::ArenaTst;
+
+    let mut map = ArenaTst::with_capacity(32);
+
+    let s = map.stat();
+    assert_eq!(s.count.nodes, 0);
+    assert_eq!(s.arena.slots, 0);
+    assert!(s.arena.capacity >= 32);
+
+    for k in RANDOM_VEC_123.iter() {
+
+        assert_eq!(map.insert(k, *k), None);
+    }
+
+    assert_eq!(map.insert("", ""), Some(""));
+    assert_eq!(map.insert("aa", "AA"), Some("aa"));
+    assert_eq!(map.insert("aa", "aa"), Some("AA"));
+    assert_eq!(map.len(), 16);
+
+    //Same shape as a boxed tree
+    let s = map.stat();
+    let t = get_sample_map_abc_abc().stat();
+
+    assert_eq!(s.count, t.count);
+    assert_eq!(s.dist, t.dist);
+    assert_eq!(s.key_len, t.key_len);
+    assert_eq!(s.arena.slots, 20);
+    assert_eq!(s.arena.free, 0);
+    assert!(s.bytes.node < t.bytes.node);
+
+    for k in RANDOM_VEC_123.iter() {
+
+        assert_eq!(map.get(k), Some(k));
+    }
+
+    assert_eq!(map.get("abcd"), None);
+    assert_eq!(map.get(""), None);
+
+    if let Some(value) = map.get_mut("cbc") {
+
+        *value = "CBC";
+    }
+
+    assert_eq!(map.get("cbc"), Some(&"CBC"));
+
+    ////////////////////////////////////////////////////
+
+    //Removed nodes are reused before the arena grows
+    assert_eq!(map.remove("cca"), Some("cca"));
+    assert_eq!(map.remove("cca"), None);
+    assert_eq!(map.remove(""), None);
+
+    let s = map.stat();
+    assert_eq!(s.count.nodes, 18);
+    assert_eq!(s.arena.slots, 20);
+    assert_eq!(s.arena.free, 2);
+
+    assert_eq!(map.insert("ccb", "ccb"), None);
+
+    let s = map.stat();
+    assert_eq!(s.count.nodes, 20);
+    assert_eq!(s.arena.slots, 20);
+    assert_eq!(s.arena.free, 0);
+
+    for k in ["ccb", "caa", "cbc", "bac", "bc"].iter() {
+
+        map.remove(k);
+    }
+
+    let s = map.stat();
+    assert_eq!(s.arena.slots, 20);
+    assert_eq!(s.arena.free, 20 - s.count.nodes);
+
+    let nodes = s.count.nodes;
+    map.shrink_to_fit();
+
+    let s = map.stat();
+    assert_eq!(s.count.nodes, nodes);
+    assert_eq!(s.arena.slots, nodes);
+    assert_eq!(s.arena.free, 0);
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), ["a", "aa", "aab", "ab", "aba", "abb", "abc", "ac", "aca", "b", "c"]);
+
+    for k in SORTED_VEC_123.iter() {
+
+        map.remove(k);
+    }
+
+    assert!(map.is_empty());
+    assert_eq!(map.stat().count.nodes, 0);
+    assert_eq!(map.stat().arena.free, nodes);
+    assert_eq!(map.iter().next(), None);
+
+    map.clear();
+    assert_eq!(map.stat().arena.slots, 0);
+
+    ////////////////////////////////////////////////////
+
+    let map = get_sample_map_abc_abc();
+    let mut arena: ArenaTst<&str> = ArenaTst::default();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        arena.insert(k, *k);
+    }
+
+    let mut v = Vec::new();
+    arena.visit_values(|s| v.push(*s));
+    assert_eq!(v, SORTED_VEC_123);
+    assert_eq!(arena.iter().rev().cloned().collect::<Vec<_>>(), map.iter().rev().cloned().collect::<Vec<_>>());
+
+    for prefix in ["", "a", "ab", "abc", "c", "x"].iter() {
+
+        let mut v = Vec::new();
+        let mut w = Vec::new();
+        arena.visit_complete_values(prefix, |s| v.push(*s));
+        map.visit_complete_values(prefix, |s| w.push(*s));
+        assert_eq!(v, w);
+
+        let mut it = arena.iter_complete(prefix);
+        v.clear();
+
+        while let Some(value) = it.next() {
+
+            assert_eq!(it.current_key(), *value);
+            v.push(*value);
+        }
+
+        assert_eq!(v, w);
+    }
+
+    for &(key, range) in [("abc", 0), ("abc", 1), ("ac", 1), ("bb", 2), ("", 1), ("aaaa", 2)].iter() {
+
+        let mut v = Vec::new();
+        let mut w = Vec::new();
+        arena.visit_neighbor_values(key, range, |s| v.push(*s));
+        map.visit_neighbor_values(key, range, |s| w.push(*s));
+        assert_eq!(v, w);
+
+        let mut it = arena.iter_neighbor(key, range);
+        v.clear();
+
+        while let Some(value) = it.next_back() {
+
+            assert_eq!(it.current_key_back(), *value);
+            v.push(*value);
+        }
+
+        w.reverse();
+        assert_eq!(v, w);
+    }
+
+    for key in ["a?a", "?a?", "??", "c??", "?", ""].iter() {
+
+        let mut v = Vec::new();
+        let mut w = Vec::new();
+        arena.visit_crossword_values(key, '?', |s| v.push(*s));
+        map.visit_crossword_values(key, '?', |s| w.push(*s));
+        assert_eq!(v, w);
+        assert_eq!(arena.iter_crossword(key, '?').cloned().collect::<Vec<_>>(), w);
+    }
+
+    let mut it = arena.iter_complete("a");
+
+    assert_eq!(it.next(), Some(&"aa"));
+    assert_eq!(it.next_back(), Some(&"aca"));
+    assert_eq!(it.by_ref().count(), 6);
+    assert_eq!(it.next_back(), None);
+
+    let mut counts = ArenaTst::new();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        counts.insert(k, 0);
+    }
+
+    counts.visit_values_mut(|value| *value += 1);
+    counts.visit_complete_values_mut("a", |value| *value += 1);
+    counts.visit_neighbor_values_mut("bac", 1, |value| *value += 10);
+    counts.visit_crossword_values_mut("?a?", '?', |value| *value += 100);
+
+    assert_eq!(counts.get("aab"), Some(&102));
+    assert_eq!(counts.get("bac"), Some(&111));
+    assert_eq!(counts.get("bc"), Some(&1));
+    assert_eq!(counts.get("abc"), Some(&2));
+}
+
+
+#[test]
+fn tst_arena_searches() {
+
+    use ternary_tree::{ArenaTst, KeyboardLayout};
+
+    let mut map = ArenaTst::new();
+
+    for k in SEARCH_VEC.iter() {
+
+        map.insert(k, *k);
+    }
+
+    assert_same_searches!(map);
+
+    ////////////////////////////////////////////////////
+
+    let mut map = ArenaTst::with_order(french_order);
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.insert(k, *k), None);
+    }
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.get(k), Some(k));
+    }
+
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), ["cote", "coté", "cotte", "côte", "côté", "cz"]);
+    assert_eq!(map.iter_complete("cô").rev().cloned().collect::<Vec<_>>(), ["côté", "côte"]);
+    assert_eq!(map.remove("coté"), Some("coté"));
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), ["cote", "cotte", "côte", "côté", "cz"]);
+
+    ////////////////////////////////////////////////////
+
+    let qwerty = KeyboardLayout::qwerty();
+    let mut counts = ArenaTst::new();
+    let mut tst_counts = Tst::new();
+
+    for k in SEARCH_VEC.iter() {
+
+        counts.insert(k, 0);
+        tst_counts.insert(k, 0);
+    }
+
+    counts.visit_crossword_neighbor_values_mut("c?t", '?', 1, |value, mismatches| *value += 1 + mismatches.len());
+    counts.visit_weighted_neighbor_values_mut("vat", 1.0, &qwerty, |value, _| *value += 10);
+    counts.visit_anagram_values_mut("tars", |value| *value += 100);
+    counts.visit_rack_values_mut("tca?", '?', |value| *value += 1000);
+    counts.visit_rack_crossword_values_mut("hlpe", "h?l?", '?', |value| *value += 10000);
+
+    tst_counts.visit_crossword_neighbor_values_mut("c?t", '?', 1, |value, mismatches| *value += 1 + mismatches.len());
+    tst_counts.visit_weighted_neighbor_values_mut("vat", 1.0, &qwerty, |value, _| *value += 10);
+    tst_counts.visit_anagram_values_mut("tars", |value| *value += 100);
+    tst_counts.visit_rack_values_mut("tca?", '?', |value| *value += 1000);
+    tst_counts.visit_rack_crossword_values_mut("hlpe", "h?l?", '?', |value| *value += 10000);
+
+    assert_eq!(counts.iter().cloned().collect::<Vec<_>>(), tst_counts.iter().cloned().collect::<Vec<_>>());
+    assert_eq!(counts.get("cat"), Some(&1011));
+    assert_eq!(counts.get("help"), Some(&10000));
+}
+
+
+#[test]
+fn tst_build_balanced() {
+
+    use ternary_tree::{NotSortedError, Stats};
+
+    fn max_sides(stats: &Stats) -> usize {
+
+        stats.dist.iter().rposition(|d| d.sides > 0).unwrap_or(0)
+    }
+
+    let map = Tst::from_sorted_iter(SORTED_VEC_123.iter().map(|k| (k, *k))).unwrap();
+
+    assert_eq!(map.len(), 16);
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), SORTED_VEC_123);
+
+    for k in SORTED_VEC_123.iter() {
+
+        assert_eq!(map.get(k), Some(k));
+    }
+
+    let mut v = Vec::new();
+    let mut w = Vec::new();
+    map.visit_neighbor_values("abc", 1, |s| v.push(*s));
+    get_sample_map_abc_abc().visit_neighbor_values("abc", 1, |s| w.push(*s));
+    assert_eq!(v, w);
+
+    ////////////////////////////////////////////////////
+
+    let mut words = Vec::new();
+
+    for a in b'a'..b'z'+1 {
+
+        words.push((a as char).to_string());
+
+        for b in b'a'..b'z'+1 {
+
+            words.push(format!("{}{}", a as char, b as char));
+        }
+    }
+
+    let mut sequential = Tst::new();
+
+    for w in words.iter() {
+
+        sequential.insert(w, w.len());
+    }
+
+    let balanced = Tst::from_sorted_iter(words.iter().map(|w| (w, w.len()))).unwrap();
+    let shuffled = Tst::build_balanced(words.iter().rev().map(|w| (w, w.len())));
+
+    let s1 = sequential.stat();
+    let s2 = balanced.stat();
+
+    assert_eq!(s2.count, s1.count);
+    assert_eq!(max_sides(&s1), 50);
+    assert!(max_sides(&s2) <= 8);
+    assert!(s2.dist.len() < s1.dist.len());
+    assert_eq!(shuffled.stat(), s2);
+    assert_eq!(balanced.iter().collect::<Vec<_>>(), sequential.iter().collect::<Vec<_>>());
+    assert_eq!(balanced.get("qz"), Some(&2));
+
+    ////////////////////////////////////////////////////
+
+    let e = Tst::from_sorted_iter(vec![("a", 1), ("c", 2), ("b", 3)]).err();
+    assert_eq!(e, Some(NotSortedError { index: 2, key: "b".to_string() }));
+    assert_eq!(e.unwrap().to_string(), "key \"b\" at index 2 is not greater than the previous one");
+
+    let e = Tst::from_sorted_iter(vec![("ab", 1), ("ab", 2)]).err();
+    assert_eq!(e, Some(NotSortedError { index: 1, key: "ab".to_string() }));
+
+    let map = Tst::from_sorted_iter(vec![("", 0), ("a", 1), ("ab", 2)]).unwrap();
+    assert_eq!(map.len(), 2);
+
+    let map = Tst::build_balanced(vec![("b", 1), ("a", 2), ("", 0), ("b", 3)]);
+    assert_eq!(map.len(), 2);
+    assert_eq!(map.get("b"), Some(&3));
+
+    let map: Tst<i32> = Tst::build_balanced(Vec::<(&str, i32)>::new());
+    assert_eq!(map.len(), 0);
+    assert_eq!(map.stat().count.nodes, 0);
+
+    ////////////////////////////////////////////////////
+
+    let reverse = |a: char, b: char| b.cmp(&a);
+
+    let mut inserted = Tst::with_order(reverse);
+
+    for k in RANDOM_VEC_123.iter() {
+
+        inserted.insert(k, *k);
+    }
+
+    let built = Tst::build_balanced_with_order(RANDOM_VEC_123.iter().map(|k| (k, *k)), reverse);
+    let keys: Vec<_> = inserted.iter().cloned().collect();
+
+    assert_eq!(built.iter().cloned().collect::<Vec<_>>(), keys);
+    assert!(Tst::from_sorted_iter_with_order(keys.iter().map(|k| (k, ())), reverse).is_ok());
+    assert!(Tst::from_sorted_iter_with_order(SORTED_VEC_123.iter().map(|k| (k, ())), reverse).is_err());
+}
+
+
+#[test]
+fn tst_optimize() {
+
+    use ternary_tree::{BalanceReport, Stats};
+
+    fn max_sides(stats: &Stats) -> usize {
+
+        stats.dist.iter().rposition(|d| d.sides > 0).unwrap_or(0)
+    }
+
+    let mut words = Vec::new();
+
+    for a in b'a'..b'z'+1 {
+
+        words.push((a as char).to_string());
+
+        for b in b'a'..b'z'+1 {
+
+            words.push(format!("{}{}", a as char, b as char));
+        }
+    }
+
+    let mut map = Tst::new();
+
+    for w in words.iter() {
+
+        map.insert(w, w.len());
+    }
+
+    let before = map.stat();
+    let report = map.balance_report();
+
+    assert_eq!(report.sibling_trees, 27);
+    assert_eq!(report.unbalanced_trees, 27);
+    assert_eq!(report.max_height, 26);
+    assert_eq!(report.dead_nodes, 0);
+    assert!(report.is_worth_optimizing());
+
+    let keys: Vec<_> = map.iter().cloned().collect();
+
+    map.optimize();
+
+    let after = map.stat();
+
+    assert_eq!(after.count, before.count);
+    assert_eq!(max_sides(&before), 50);
+    assert!(max_sides(&after) <= 8);
+    assert!(after.dist.len() < before.dist.len());
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), keys);
+    assert_eq!(map.get("qz"), Some(&2));
+
+    let report = map.balance_report();
+
+    assert_eq!(report.unbalanced_trees, 0);
+    assert_eq!(report.max_height, 5);
+    assert!(!report.is_worth_optimizing());
+
+    //Already balanced trees stay as they are
+    let balanced = Tst::from_sorted_iter(words.iter().map(|w| (w, w.len()))).unwrap();
+
+    assert_eq!(balanced.balance_report(), report);
+    assert_eq!(balanced.stat(), after);
+
+    ////////////////////////////////////////////////////
+
+    let mut map = Tst::new();
+
+    for k in ["a", "b", "c"].iter() {
+
+        map.insert(k, *k);
+    }
+
+    map.remove("b");
+
+    assert_eq!(map.balance_report(), BalanceReport { sibling_trees: 1, unbalanced_trees: 1, extra_levels: 1, max_height: 3, dead_nodes: 1 });
+    assert!(map.balance_report().is_worth_optimizing());
+
+    map.optimize();
+
+    assert_eq!(map.stat().count.nodes, 2);
+    assert_eq!(map.balance_report(), BalanceReport { sibling_trees: 1, unbalanced_trees: 0, extra_levels: 0, max_height: 2, dead_nodes: 0 });
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), vec!["a", "c"]);
+
+    let mut empty: Tst<i32> = Tst::new();
+    empty.optimize();
+    assert_eq!(empty.balance_report(), BalanceReport::default());
+
+    let mut map = get_sample_map_abc_abc();
+    let mut v = Vec::new();
+    map.visit_values(|s| v.push(*s));
+    map.optimize();
+    let mut w = Vec::new();
+    map.visit_values(|s| w.push(*s));
+    assert_eq!(v, w);
+}
+
+
+#[test]
+fn tst_self_balancing() {
+
+    use ternary_tree::{SelfBalancing, Stats};
+
+    fn max_sides(stats: &Stats) -> usize {
+
+        stats.dist.iter().rposition(|d| d.sides > 0).unwrap_or(0)
+    }
+
+    let mut words = Vec::new();
+
+    for a in b'a'..b'z'+1 {
+
+        words.push((a as char).to_string());
+
+        for b in b'a'..b'z'+1 {
+
+            words.push(format!("{}{}", a as char, b as char));
+        }
+    }
+
+    let mut sequential = Tst::new();
+    let mut balancing = Tst::new_self_balancing();
+    let mut reversed = Tst::with_order(|a: char, b: char| a.cmp(&b)).into_self_balancing();
+
+    assert!(!sequential.is_self_balancing());
+    assert!(balancing.is_self_balancing());
+    assert!(reversed.is_self_balancing());
+
+    for w in words.iter() {
+
+        sequential.insert(w, w.len());
+        balancing.insert(w, w.len());
+    }
+
+    for w in words.iter().rev() {
+
+        reversed.insert(w, w.len());
+    }
+
+    let s1 = sequential.stat();
+    let s2 = balancing.stat();
+
+    assert_eq!(s2.count, s1.count);
+    assert_eq!(max_sides(&s1), 50);
+    assert!(max_sides(&s2) <= 16);
+    assert!(s2.dist.len() < s1.dist.len());
+    assert!(balancing.balance_report().max_height < sequential.balance_report().max_height);
+
+    //The shape only depends on the keys, not on their order
+    assert_eq!(reversed.stat(), s2);
+    assert_eq!(balancing.iter().collect::<Vec<_>>(), sequential.iter().collect::<Vec<_>>());
+    assert_eq!(balancing.get("qz"), Some(&2));
+
+    //Turning it on reshapes the tree as if it had been on from the start
+    let sequential = sequential.into_self_balancing();
+    assert!(sequential.is_self_balancing());
+    assert_eq!(sequential.stat(), s2);
+
+    //Trees built at once are not self-balancing until turned into one
+    let built: Tst<usize, _, SelfBalancing> = Tst::build_balanced(words.iter().map(|w| (w, w.len()))).into_self_balancing();
+    assert_eq!(built.stat(), s2);
+
+    ////////////////////////////////////////////////////
+
+    for (i, w) in words.iter().enumerate() {
+
+        if i % 3 != 0 {
+
+            assert_eq!(balancing.remove(w), Some(w.len()));
+        }
+    }
+
+    assert_eq!(balancing.len(), words.len() / 3);
+    assert_eq!(balancing.balance_report().dead_nodes, 0);
+
+    for (i, w) in words.iter().enumerate() {
+
+        assert_eq!(balancing.get(w).cloned(), if i % 3 == 0 { Some(w.len()) } else { None });
+    }
+
+    let mut optimized = Tst::new_self_balancing();
+
+    for (i, w) in words.iter().enumerate().rev() {
+
+        if i % 3 == 0 {
+
+            optimized.insert(w, w.len());
+        }
+    }
+
+    assert_eq!(balancing.stat(), optimized.stat());
+
+    //Optimizing keeps the treap shape
+    optimized.optimize();
+    assert_eq!(balancing.stat(), optimized.stat());
+
+    for w in words.iter() {
+
+        balancing.remove(w);
+    }
+
+    assert_eq!(balancing.len(), 0);
+    assert_eq!(balancing.stat().count.nodes, 0);
+}
+
+
+#[test]
+fn tst_write_and_read_binary() {
+
+    use std::io::{Cursor, ErrorKind};
+
+    let mut map = Tst::new();
+
+    for (i, k) in RANDOM_VEC_123.iter().enumerate() {
+
+        map.insert(k, k.to_string() + &i.to_string());
+    }
+
+    map.insert("élan→ü", "unicode".to_string());
+
+    let mut bytes = Vec::new();
+    map.write_to(&mut bytes).unwrap();
+
+    assert_eq!(&bytes[..5], b"TSTB\x01");
+
+    let loaded: Tst<String> = Tst::read_from(&mut Cursor::new(&bytes)).unwrap();
+
+    assert_eq!(loaded.len(), map.len());
+    assert_eq!(loaded.stat(), map.stat());
+    assert_eq!(loaded.iter().collect::<Vec<_>>(), map.iter().collect::<Vec<_>>());
+    assert_eq!(loaded.get("élan→ü"), Some(&"unicode".to_string()));
+
+    //Node structure is kept as it was, not rebuilt from keys
+    let mut again = Vec::new();
+    loaded.write_to(&mut again).unwrap();
+    assert_eq!(again, bytes);
+
+    ////////////////////////////////////////////////////
+
+    let reverse = |a: char, b: char| b.cmp(&a);
+
+    let mut reversed = Tst::with_order(reverse);
+
+    for k in SORTED_VEC_123.iter() {
+
+        reversed.insert(k, (k.len(), vec![k.len() as u8; k.len()]));
+    }
+
+    let mut bytes = Vec::new();
+    reversed.write_to(&mut bytes).unwrap();
+
+    let loaded: Tst<(usize, Vec<u8>), _> = Tst::read_from_with_order(&mut Cursor::new(&bytes), reverse).unwrap();
+
+    assert_eq!(loaded.iter().collect::<Vec<_>>(), reversed.iter().collect::<Vec<_>>());
+    assert_eq!(loaded.get("aab"), Some(&(3, vec![3, 3, 3])));
+
+    let empty: Tst<u32> = Tst::new();
+    let mut bytes = Vec::new();
+    empty.write_to(&mut bytes).unwrap();
+    assert_eq!(bytes, b"TSTB\x01\x00\x00");
+
+    let loaded: Tst<u32> = Tst::read_from(&mut Cursor::new(&bytes)).unwrap();
+    assert_eq!(loaded.len(), 0);
+
+    ////////////////////////////////////////////////////
+
+    let mut map = Tst::new();
+
+    for k in SORTED_VEC_123.iter() {
+
+        map.insert(k, k.len() as u32);
+    }
+
+    let mut bytes = Vec::new();
+    map.write_to(&mut bytes).unwrap();
+
+    let read = |bytes: &[u8]| Tst::<u32>::read_from(&mut Cursor::new(bytes)).err().unwrap();
+
+    let mut wrong = bytes.clone();
+    wrong[0] = b'X';
+    assert_eq!(read(&wrong).kind(), ErrorKind::InvalidData);
+    assert_eq!(read(&wrong).to_string(), "not a ternary tree file");
+
+    let mut wrong = bytes.clone();
+    wrong[4] = 2;
+    assert_eq!(read(&wrong).to_string(), "unsupported version 2");
+
+    //Header says one more value than the nodes hold
+    let mut wrong = bytes.clone();
+    wrong[6] += 1;
+    assert_eq!(read(&wrong).to_string(), "count of values does not match the header");
+
+    let truncated = &bytes[..bytes.len()-1];
+    assert_eq!(read(truncated).kind(), ErrorKind::UnexpectedEof);
+
+    //A label changed to one of its left siblings, which would hide keys from searches
+    let mut wrong = bytes.clone();
+    let last_c = bytes.iter().rposition(|&b| b == b'c').unwrap();
+    wrong[last_c] = b'a';
+    assert_eq!(read(&wrong).kind(), ErrorKind::InvalidData);
+    assert_eq!(read(&wrong).to_string(), "labels out of order");
+
+    //A tree read with another order than the one it was written with
+    let mut reversed_bytes = Vec::new();
+    reversed.write_to(&mut reversed_bytes).unwrap();
+    assert_eq!(Tst::<(usize, Vec<u8>)>::read_from(&mut Cursor::new(&reversed_bytes)).err().unwrap().to_string(), "labels out of order");
+
+    //A value read as the wrong type
+    let e = Tst::<String>::read_from(&mut Cursor::new(&bytes)).err().unwrap();
+    assert!(e.kind() == ErrorKind::InvalidData || e.kind() == ErrorKind::UnexpectedEof);
+}
+
+
+#[cfg(feature = "serde")]
+#[test]
+fn tst_serde() {
+
+    use ternary_tree::RawNodes;
+
+    let mut map = Tst::new();
+
+    for k in SORTED_VEC_123.iter() {
+
+        map.insert(k, k.len());
+    }
+
+    let json = serde_json::to_string(&map).unwrap();
+
+    assert_eq!(json.starts_with("{\"a\":1,\"aa\":2,\"aab\":3,\"ab\":2,"), true);
+
+    let loaded: Tst<usize> = serde_json::from_str(&json).unwrap();
+
+    assert_eq!(loaded.len(), map.len());
+    assert_eq!(loaded.iter().collect::<Vec<_>>(), map.iter().collect::<Vec<_>>());
+
+    let balancing = serde_json::from_str::<Tst<usize>>(&json).unwrap().into_self_balancing();
+
+    assert_eq!(balancing.is_self_balancing(), true);
+    assert_eq!(balancing.get("cbc"), Some(&3));
+
+    let loaded: Tst<usize> = serde_json::from_str("{}").unwrap();
+    assert_eq!(loaded.len(), 0);
+
+    assert_eq!(serde_json::from_str::<Tst<usize>>("[1, 2]").is_err(), true);
+
+    ////////////////////////////////////////////////////
+
+    let raw = serde_json::to_string(&RawNodes(map)).unwrap();
+    let map = serde_json::from_str::<RawNodes<usize>>(&raw).unwrap().0;
+
+    assert_eq!(map.len(), 16);
+    assert_eq!(map.get("aab"), Some(&3));
+
+    //Same nodes as the map loaded from keys, as both were built by inserting the same keys
+    let mut v = Vec::new();
+    map.write_to(&mut v).unwrap();
+    let mut w = Vec::new();
+    serde_json::from_str::<Tst<usize>>(&json).unwrap().write_to(&mut w).unwrap();
+    assert_eq!(v, w);
+
+    let raw: RawNodes<usize> = serde_json::from_str("[]").unwrap();
+    assert_eq!(raw.0.len(), 0);
+
+    assert_eq!(serde_json::from_str::<RawNodes<usize>>("[[\"a\",1,1],[\"b\",1,2]]").is_err(), true);
+    assert_eq!(serde_json::from_str::<RawNodes<usize>>("[[\"a\",5,1]]").is_err(), true);
+    assert_eq!(serde_json::from_str::<RawNodes<usize>>("[[\"a\",1,null]]").is_err(), true);
+    assert_eq!(serde_json::from_str::<RawNodes<usize>>("[[\"a\",0,null],[\"b\",5,2]]").is_ok(), true);
+    assert!(serde_json::from_str::<RawNodes<usize>>("[[\"b\",0,null],[\"a\",9,2]]").is_ok());
+    assert!(serde_json::from_str::<RawNodes<usize>>("[[\"a\",0,null],[\"b\",9,2]]").is_err());
+    assert!(serde_json::from_str::<RawNodes<usize>>("[[\"b\",0,null],[\"b\",3,2]]").is_err());
+}
+
+
+#[test]
+fn tst_frozen() {
+
+    use ternary_tree::{Codec, FrozenTst};
+
+    let mut map = Tst::new();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        map.insert(k, k.to_uppercase());
+    }
+
+    map.insert("ça→ü", "unicode".to_string());
+
+    let mut bytes = Vec::new();
+    map.write_frozen(&mut bytes).unwrap();
+
+    let frozen = FrozenTst::new(&bytes).unwrap();
+
+    let decode = |bytes: Option<&[u8]>| bytes.map(|mut b| String::decode(&mut b).unwrap());
+
+    assert_eq!(frozen.len(), map.len());
+    assert!(!frozen.is_empty());
+    assert_eq!(decode(frozen.get("aab")), Some("AAB".to_string()));
+    assert_eq!(decode(frozen.get("ça→ü")), Some("unicode".to_string()));
+    assert_eq!(frozen.get("aaa"), None);
+    assert_eq!(frozen.get(""), None);
+
+    //Same results, in the same order, as the searches of the tree it comes from
+    let same = |frozen: Vec<&[u8]>, live: Vec<&String>| {
+
+        assert_eq!(frozen.into_iter().map(|b| decode(Some(b)).unwrap()).collect::<Vec<_>>(),
+                   live.into_iter().cloned().collect::<Vec<_>>());
+    };
+
+    same(frozen.iter().collect(), map.iter().collect());
+    same(frozen.iter().rev().collect(), map.iter().rev().collect());
+    same(frozen.iter_complete("a").collect(), map.iter_complete("a").collect());
+    same(frozen.iter_complete("ab").rev().collect(), map.iter_complete("ab").rev().collect());
+    same(frozen.iter_neighbor("abc", 1).collect(), map.iter_neighbor("abc", 1).collect());
+    same(frozen.iter_neighbor("abc", 2).rev().collect(), map.iter_neighbor("abc", 2).rev().collect());
+    same(frozen.iter_crossword("a?c", '?').collect(), map.iter_crossword("a?c", '?').collect());
+    same(frozen.iter_crossword("?b?", '?').rev().collect(), map.iter_crossword("?b?", '?').rev().collect());
+
+    let mut it = frozen.iter_complete("b");
+    let mut keys = Vec::new();
+
+    while it.next().is_some() {
+
+        keys.push(it.current_key());
+    }
+
+    assert_eq!(keys, ["bac", "bc"]);
+
+    let mut it = frozen.iter();
+    it.next_back();
+    assert_eq!(it.current_key_back(), "ça→ü");
+
+    //Both ends meet without giving a value twice
+    let mut it = frozen.iter();
+    let mut count = 0;
+
+    while it.next().is_some() {
+
+        count += 1;
+
+        if it.next_back().is_some() {
+
+            count += 1;
+        }
+    }
+
+    assert_eq!(count, map.len());
+
+    //Also where a key is the prefix of the key given by the other end
+    for &(prefix, range) in [("", 3), ("a", 0), ("ab", 1), ("ca", 2)].iter() {
+
+        let mut it = frozen.iter_neighbor(prefix, range);
+        let mut front = Vec::new();
+        let mut back = Vec::new();
+
+        while it.next().is_some() {
+
+            front.push(it.current_key());
+
+            if it.next_back().is_some() {
+
+                back.push(it.current_key_back());
+            }
+        }
+
+        back.reverse();
+        front.extend(back);
+
+        let mut keys = Vec::new();
+        let mut it = frozen.iter_neighbor(prefix, range);
+
+        while it.next().is_some() {
+
+            keys.push(it.current_key());
+        }
+
+        assert_eq!(front, keys);
+    }
+
+    ////////////////////////////////////////////////////
+
+    let empty: Tst<u32> = Tst::new();
+    let mut bytes = Vec::new();
+    empty.write_frozen(&mut bytes).unwrap();
+
+    let frozen = FrozenTst::new(&bytes).unwrap();
+    assert!(frozen.is_empty());
+    assert_eq!(frozen.iter().next(), None);
+
+    //Trees with another order are frozen in code point order
+    let reverse = |a: char, b: char| b.cmp(&a);
+    let mut reversed = Tst::with_order(reverse);
+    reversed.insert("a", 1u32);
+    reversed.insert("b", 2u32);
+
+    let mut bytes = Vec::new();
+    reversed.write_frozen(&mut bytes).unwrap();
+    let frozen = FrozenTst::new(&bytes).unwrap();
+    assert_eq!(frozen.iter().map(|mut b| u32::decode(&mut b).unwrap()).collect::<Vec<_>>(), [1, 2]);
+
+    //'é' sorts between 'e' and 'f' in French, but after both by code point
+    let mut french = Tst::with_order(french_order);
+
+    for (i, k) in ["f", "e", "é", "fé", "fe", "éte", "été"].iter().enumerate() {
+
+        french.insert(k, i as u32);
+    }
+
+    let mut bytes = Vec::new();
+    french.write_frozen(&mut bytes).unwrap();
+    let frozen = FrozenTst::new(&bytes).unwrap();
+    frozen.check().unwrap();
+
+    for k in ["f", "e", "é", "fé", "fe", "éte", "été"].iter() {
+
+        assert_eq!(frozen.get(k).map(|mut b| u32::decode(&mut b).unwrap()), french.get(k).cloned());
+    }
+
+    let mut keys = Vec::new();
+    let mut it = frozen.iter();
+
+    while it.next().is_some() {
+
+        keys.push(it.current_key());
+    }
+
+    assert_eq!(keys, ["e", "f", "fe", "fé", "é", "éte", "été"]);
+
+    ////////////////////////////////////////////////////
+
+    let mut bytes = Vec::new();
+    map.write_frozen(&mut bytes).unwrap();
+
+    let open = |bytes: &[u8]| FrozenTst::new(bytes).err().unwrap().to_string();
+    let check = |bytes: &[u8]| FrozenTst::new(bytes).unwrap().check().err().unwrap().to_string();
+
+    FrozenTst::new(&bytes).unwrap().check().unwrap();
+
+    let mut wrong = bytes.clone();
+    wrong[0] = b'X';
+    assert_eq!(open(&wrong), "not a frozen ternary tree");
+
+    let mut wrong = bytes.clone();
+    wrong[4] = 9;
+    assert_eq!(open(&wrong), "unsupported version 9");
+
+    assert_eq!(open(&bytes[..bytes.len()-1]), "length does not match the header");
+
+    //Middle link of the root node pointing back to itself
+    let mut wrong = bytes.clone();
+    let root = u32::from_le_bytes([bytes[16], bytes[17], bytes[18], bytes[19]]) as usize;
+    wrong[24+root*20+8..24+root*20+12].copy_from_slice(&(root as u32).to_le_bytes());
+    assert_eq!(check(&wrong), "invalid link");
+
+    //Queries read the broken link as missing, which hides the keys going through it
+    let frozen = FrozenTst::new(&wrong).unwrap();
+    assert_eq!(decode(frozen.get("c")), Some("C".to_string()));
+    assert_eq!(frozen.get("cbc"), None);
+    assert_eq!(frozen.iter().count(), map.len() - map.iter_complete("c").count());
+
+    let mut wrong = bytes.clone();
+    wrong[24..28].copy_from_slice(&0xD800u32.to_le_bytes());
+    assert_eq!(check(&wrong), "invalid label");
+
+    let mut wrong = bytes.clone();
+    let values_len = bytes.len() - 24 - 20 * frozen.node_count();
+    wrong[24+root*20+16..24+root*20+20].copy_from_slice(&(values_len as u32 - 3).to_le_bytes());
+    assert_eq!(check(&wrong), "invalid value offset");
+
+    //Left and middle links of the root node pointing to the same node
+    let mut wrong = bytes.clone();
+    let middle = &bytes[24+root*20+8..24+root*20+12];
+    assert_ne!(middle, &[0xFF; 4]);
+    wrong[24+root*20+4..24+root*20+8].copy_from_slice(middle);
+    assert_eq!(check(&wrong), "node with several parents");
+}
+
+
+#[test]
+fn tst_minimize_to_frozen_set() {
+
+    use ternary_tree::FrozenSet;
+
+    let mut words = Vec::new();
+
+    for a in b'a'..b'z'+1 {
+
+        words.push((a as char).to_string());
+
+        for b in b'a'..b'z'+1 {
+
+            words.push(format!("{}{}", a as char, b as char));
+        }
+    }
+
+    let mut map = Tst::new();
+
+    for w in words.iter() {
+
+        map.insert(w, ());
+    }
+
+    let bytes = map.minimize().unwrap();
+    let set = FrozenSet::new(&bytes).unwrap();
+
+    //One sibling tree for the first letters, and a single one shared by all of them for the second
+    assert_eq!(map.stat().count.nodes, 702);
+    assert_eq!(set.node_count(), 52);
+    assert_eq!(set.len(), 702);
+    assert!(!set.is_empty());
+    set.check().unwrap();
+
+    assert!(set.contains("qz"));
+    assert!(set.contains("q"));
+    assert!(!set.contains("qzz"));
+    assert!(!set.contains(""));
+
+    assert_eq!(set.iter().collect::<Vec<_>>(), words);
+    assert_eq!(set.iter().rev().collect::<Vec<_>>(), words.iter().rev().cloned().collect::<Vec<_>>());
+    assert_eq!(set.iter_complete("q").count(), 26);
+    assert_eq!(set.iter_crossword("?z", '?').count(), 26);
+    assert_eq!(set.iter_crossword("q?", '?').next_back(), Some("qz".to_string()));
+
+    //Both ends meet without giving a key twice, although they go through the same nodes
+    let mut it = set.iter_complete("m");
+    let mut keys = Vec::new();
+
+    loop {
+
+        match (it.next(), it.next_back()) {
+
+            (None, None) => break,
+
+            (front, back) => keys.extend(front.into_iter().chain(back))
+        }
+    }
+
+    keys.sort();
+    assert_eq!(keys, set.iter_complete("m").collect::<Vec<_>>());
+
+    ////////////////////////////////////////////////////
+
+    let mut map = Tst::new();
+
+    for w in ["walk", "walked", "walking", "talk", "talked", "talking", "stalk", "stalked"].iter() {
+
+        map.insert(w, w.len());
+    }
+
+    let bytes = map.minimize().unwrap();
+    let set = FrozenSet::new(&bytes).unwrap();
+
+    assert!(set.node_count() < map.stat().count.nodes);
+    assert_eq!(set.iter().collect::<Vec<_>>(), ["stalk", "stalked", "talk", "talked", "talking", "walk", "walked", "walking"]);
+
+    let keys = |it: &mut ternary_tree::TstIterator<usize>| {
+
+        let mut keys = Vec::new();
+
+        while it.next().is_some() {
+
+            keys.push(it.current_key());
+        }
+
+        keys
+    };
+
+    assert_eq!(set.iter_complete("ta").collect::<Vec<_>>(), keys(&mut map.iter()).into_iter().filter(|k| k.starts_with("ta")).collect::<Vec<_>>());
+    assert_eq!(set.iter_neighbor("talkes", 1).collect::<Vec<_>>(), ["talked"]);
+    assert_eq!(set.iter_neighbor("walk", 1).collect::<Vec<_>>(), ["talk", "walk"]);
+    assert_eq!(set.iter_crossword("?alk", '?').collect::<Vec<_>>(), ["talk", "walk"]);
+
+    //Only the keys matter, not the order or shape of the tree they come from
+    let reverse = |a: char, b: char| b.cmp(&a);
+    let mut reversed = Tst::with_order(reverse);
+
+    for w in ["stalked", "talking", "walk", "talk", "walked", "stalk", "talked", "walking"].iter() {
+
+        reversed.insert(w, ());
+    }
+
+    assert_eq!(reversed.minimize().unwrap(), bytes);
+
+    let empty: Tst<()> = Tst::new();
+    let bytes = empty.minimize().unwrap();
+    let set = FrozenSet::new(&bytes).unwrap();
+
+    assert!(set.is_empty());
+    assert_eq!(set.iter().next(), None);
+}
+
+
+#[test]
+fn tst_load_and_dump_lines() {
+
+    use ternary_tree::{Duplicates, LoadLinesError};
+
+    let text = "apple\t3\nbanana\t12\r\n\ncherry\t7\n";
+
+    let mut map = Tst::new();
+    let loaded = map.load_lines(text.as_bytes(), Duplicates::Error, |s| s.parse::<u32>()).unwrap();
+
+    assert_eq!(loaded, 3);
+    assert_eq!(map.len(), 3);
+    assert_eq!(map.get("banana"), Some(&12));
+
+    let mut out = Vec::new();
+    map.dump_lines(&mut out, |n| n.to_string()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "apple\t3\nbanana\t12\ncherry\t7\n");
+
+    //Keys and values with tabs, newlines and backslashes go through escaped
+    let mut map = Tst::new();
+    map.insert("tab\there", "new\nline".to_string());
+    map.insert("back\\slash", "".to_string());
+    map.insert("plain", "a\\b\tc".to_string());
+
+    let mut out = Vec::new();
+    map.dump_lines(&mut out, |s| s.clone()).unwrap();
+
+    let text = String::from_utf8(out).unwrap();
+    assert_eq!(text, "back\\\\slash\nplain\ta\\\\b\\tc\ntab\\there\tnew\\nline\n");
+
+    let mut loaded = Tst::new();
+    loaded.load_lines(text.as_bytes(), Duplicates::Error, |s| Ok::<_, String>(s.to_string())).unwrap();
+    assert_eq!(loaded.iter().collect::<Vec<_>>(), map.iter().collect::<Vec<_>>());
+    assert_eq!(loaded.get("tab\there"), Some(&"new\nline".to_string()));
+
+    //Word lists
+    let mut words = Tst::new();
+    words.load_lines("one\ntwo\nthree\n".as_bytes(), Duplicates::Error, |_| Ok::<_, String>(())).unwrap();
+    assert_eq!(words.len(), 3);
+
+    let mut out = Vec::new();
+    words.dump_lines(&mut out, |_| String::new()).unwrap();
+    assert_eq!(String::from_utf8(out).unwrap(), "one\nthree\ntwo\n");
+
+    ////////////////////////////////////////////////////
+
+    let text = "a\t1\nb\t2\na\t3\n";
+    let parse = |s: &str| s.parse::<u32>();
+
+    let mut map = Tst::new();
+    assert_eq!(map.load_lines(text.as_bytes(), Duplicates::KeepFirst, parse).unwrap(), 2);
+    assert_eq!(map.get("a"), Some(&1));
+
+    //The value of a skipped duplicate is not parsed
+    assert_eq!(map.load_lines("a\tx\nc\t4\n".as_bytes(), Duplicates::KeepFirst, parse).unwrap(), 1);
+    assert_eq!(map.get("a"), Some(&1));
+
+    let mut map = Tst::new();
+    assert_eq!(map.load_lines(text.as_bytes(), Duplicates::KeepLast, parse).unwrap(), 3);
+    assert_eq!(map.get("a"), Some(&3));
+    assert_eq!(map.len(), 2);
+
+    let mut map = Tst::new();
+    let e = map.load_lines(text.as_bytes(), Duplicates::Error, parse).err().unwrap();
+    assert_eq!(e.to_string(), "line 3: duplicate key \"a\"");
+
+    match e {
+
+        LoadLinesError::Duplicate { line, key } => assert_eq!((line, key), (3, "a".to_string())),
+
+        _ => panic!("unexpected error")
+    }
+
+    //Lines before the error stay loaded
+    assert_eq!(map.len(), 2);
+
+    let e = Tst::new().load_lines("a\t1\n\nb\tx\n".as_bytes(), Duplicates::Error, parse).err().unwrap();
+    assert_eq!(e.to_string(), "line 3: invalid digit found in string");
+
+    //Keys already in the tree before the load count as duplicates
+    let mut map = Tst::new();
+    map.insert("b", 0);
+    assert_eq!(map.load_lines(text.as_bytes(), Duplicates::KeepFirst, parse).unwrap(), 1);
+    assert_eq!(map.get("b"), Some(&0));
+
+    let e = map.load_lines("c\t1\nb\t2\n".as_bytes(), Duplicates::Error, parse).err().unwrap();
+    assert_eq!(e.to_string(), "line 2: duplicate key \"b\"");
+    assert_eq!(map.get("c"), Some(&1));
+
+    let e = Tst::new().load_lines("a\t1\n\t2\n".as_bytes(), Duplicates::Error, parse).err().unwrap();
+    assert_eq!(e.to_string(), "line 2: empty key");
+
+    let e = Tst::new().load_lines("a\\q\t1\n".as_bytes(), Duplicates::Error, parse).err().unwrap();
+    assert_eq!(e.to_string(), "line 1: invalid escape \"\\q\"");
+
+    let e = Tst::new().load_lines("a\t1\\\n".as_bytes(), Duplicates::Error, parse).err().unwrap();
+    assert_eq!(e.to_string(), "line 1: unfinished escape at end of line");
+
+    let e = Tst::<u32>::new().load_lines(&[0xff, b'\n'][..], Duplicates::Error, parse).err().unwrap();
+
+    match e {
+
+        LoadLinesError::Io(_) => (),
+
+        _ => panic!("unexpected error")
+    }
+}
+
+
+#[test]
+fn tst_persistent_snapshots() {
+
+    use ternary_tree::PersistentTst;
+    use std::thread;
+
+    let mut map = PersistentTst::new();
+    let mut tst = Tst::new();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        map.insert(k, k.to_string());
+        tst.insert(k, k.to_string());
+    }
+
+    assert_eq!(map.len(), 16);
+    assert_eq!(map.get("abc"), Some(&"abc".to_string()));
+
+    //Same shape, and same results in the same order, as a Tst fed with the same keys
+    let s1 = map.stat();
+    let s2 = tst.stat();
+
+    assert_eq!((&s1.dist, &s1.key_len, &s1.count), (&s2.dist, &s2.key_len, &s2.count));
+
+    assert_eq!(map.iter().collect::<Vec<_>>(), tst.iter().collect::<Vec<_>>());
+    assert_eq!(map.iter().rev().collect::<Vec<_>>(), tst.iter().rev().collect::<Vec<_>>());
+    assert_eq!(map.iter_complete("a").collect::<Vec<_>>(), tst.iter_complete("a").collect::<Vec<_>>());
+    assert_eq!(map.iter_neighbor("abc", 1).collect::<Vec<_>>(), tst.iter_neighbor("abc", 1).collect::<Vec<_>>());
+    assert_eq!(map.iter_crossword("?a?", '?').rev().collect::<Vec<_>>(), tst.iter_crossword("?a?", '?').rev().collect::<Vec<_>>());
+
+    let mut v = Vec::new();
+    map.visit_neighbor_values("abc", 1, |s| v.push(s.clone()));
+    let mut w = Vec::new();
+    tst.visit_neighbor_values("abc", 1, |s| w.push(s.clone()));
+    assert_eq!(v, w);
+
+    let mut it = map.iter_complete("c");
+    let mut keys = Vec::new();
+
+    while it.next().is_some() {
+
+        keys.push(it.current_key());
+    }
+
+    assert_eq!(keys, ["caa", "cbc", "cca"]);
+
+    ////////////////////////////////////////////////////
+
+    let snapshot = map.snapshot();
+
+    assert_eq!(map.insert("abc", "ABC".to_string()), Some("abc".to_string()));
+    assert_eq!(map.insert("abcd", "abcd".to_string()), None);
+    assert_eq!(map.remove("caa"), Some("caa".to_string()));
+    assert_eq!(map.remove("zzz"), None);
+    *map.get_mut("b").unwrap() = "B".to_string();
+
+    assert_eq!(map.len(), 16);
+    assert_eq!(map.get("abc"), Some(&"ABC".to_string()));
+    assert_eq!(map.get("caa"), None);
+    assert_eq!(map.get("b"), Some(&"B".to_string()));
+
+    //The snapshot still sees the map as it was
+    assert_eq!(snapshot.len(), 16);
+    assert_eq!(snapshot.get("abc"), Some(&"abc".to_string()));
+    assert_eq!(snapshot.get("abcd"), None);
+    assert_eq!(snapshot.get("caa"), Some(&"caa".to_string()));
+    assert_eq!(snapshot.iter().collect::<Vec<_>>(), tst.iter().collect::<Vec<_>>());
+    assert_eq!(snapshot.stat().count, s2.count);
+
+    //Snapshots can be read from other threads while the map changes
+    let reader = thread::spawn(move || snapshot.iter_complete("c").cloned().collect::<Vec<_>>());
+
+    map.clear();
+    assert!(map.is_empty());
+    assert_eq!(map.iter().next(), None);
+
+    assert_eq!(reader.join().unwrap(), ["caa", "cbc", "cca"]);
+
+    ////////////////////////////////////////////////////
+
+    let mut map: PersistentTst<usize> = PersistentTst::default();
+
+    for k in SORTED_VEC_123.iter() {
+
+        map.insert(k, k.len());
+    }
+
+    let mut snapshots = Vec::new();
+
+    for k in SORTED_VEC_123.iter() {
+
+        snapshots.push(map.snapshot());
+        map.remove(k);
+    }
+
+    assert_eq!(map.len(), 0);
+    assert_eq!(map.stat().count.nodes, 0);
+
+    for (i, snapshot) in snapshots.iter().enumerate() {
+
+        assert_eq!(snapshot.len(), 16 - i);
+        assert_eq!(snapshot.iter().count(), 16 - i);
+        assert_eq!(snapshot.get(SORTED_VEC_123[i]), Some(&SORTED_VEC_123[i].len()));
+    }
+}
+
+
+#[test]
+fn tst_persistent_searches() {
+
+    use ternary_tree::PersistentTst;
+
+    let mut map = PersistentTst::new();
+
+    for k in SEARCH_VEC.iter() {
+
+        map.insert(k, *k);
+    }
+
+    let snapshot = map.snapshot();
+
+    for k in SEARCH_VEC.iter() {
+
+        map.remove(k);
+    }
+
+    assert_eq!(map.iter().next(), None);
+    assert_same_searches!(snapshot);
+
+    ////////////////////////////////////////////////////
+
+    let mut map = PersistentTst::with_order(french_order);
+
+    for k in FRENCH_VEC.iter() {
+
+        assert_eq!(map.insert(k, *k), None);
+    }
+
+    let snapshot = map.snapshot();
+
+    assert_eq!(map.remove("coté"), Some("coté"));
+    assert_eq!(map.get("côte"), Some(&"côte"));
+    assert_eq!(map.iter().cloned().collect::<Vec<_>>(), ["cote", "cotte", "côte", "côté", "cz"]);
+    assert_eq!(snapshot.iter().cloned().collect::<Vec<_>>(), ["cote", "coté", "cotte", "côte", "côté", "cz"]);
+    assert_eq!(snapshot.iter_complete("cô").rev().cloned().collect::<Vec<_>>(), ["côté", "côte"]);
+}
+
+
+#[test]
+fn tst_concurrent_shards() {
+
+    use ternary_tree::ConcurrentTst;
+    use std::sync::Arc;
+    use std::thread;
+
+    let map = ConcurrentTst::new();
+
+    assert_eq!(map.shard_count(), 8);
+    assert_eq!(map.len(), 0);
+
+    let mut tst = Tst::new();
+
+    for k in RANDOM_VEC_123.iter() {
+
+        assert_eq!(map.insert(k, k.to_string()), None);
+        tst.insert(k, k.to_string());
+    }
+
+    assert_eq!(map.len(), 16);
+    assert_eq!(map.get("abc"), Some("abc".to_string()));
+    assert_eq!(map.get("abcd"), None);
+    assert_eq!(map.get_with("cbc", |s| s.len()), Some(3));
+    assert_eq!(map.insert("abc", "ABC".to_string()), Some("abc".to_string()));
+    assert_eq!(map.remove("abc"), Some("ABC".to_string()));
+    assert_eq!(map.remove("abc"), None);
+    assert_eq!(map.insert("abc", "abc".to_string()), None);
+
+    //Shards are read in order, so that keys come sorted as from a single Tst
+    let mut expected = Vec::new();
+    let mut it = tst.iter();
+
+    while let Some(value) = it.next() {
+
+        expected.push((it.current_key(), value.clone()));
+    }
+
+    assert_eq!(map.complete(""), expected);
+
+    assert_eq!(map.complete("c"), [("caa".to_string(), "caa".to_string()), ("cbc".to_string(), "cbc".to_string()), ("cca".to_string(), "cca".to_string())]);
+    assert_eq!(map.complete("ab").len(), 3);
+    assert_eq!(map.complete("z"), []);
+
+    let mut keys = Vec::new();
+    map.visit_complete("a", |key, _| keys.push(key.to_string()));
+    assert_eq!(keys, ["aa", "aab", "ab", "aba", "abb", "abc", "ac", "aca"]);
+
+    map.clear();
+    assert!(map.is_empty());
+
+    ////////////////////////////////////////////////////
+
+    //Keys below the first boundary and above the last one
+    let map = ConcurrentTst::with_boundaries(&['m', 'a', 'm']);
+
+    assert_eq!(map.shard_count(), 3);
+
+    for k in ["zoo", "Zoo", "mat", "apple", "été", "1"].iter() {
+
+        map.insert(k, k.len());
+    }
+
+    let keys: Vec<String> = map.complete("").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["1", "Zoo", "apple", "mat", "zoo", "été"]);
+    assert_eq!(map.get("été"), Some(5));
+
+    //Default shards for digits, uppercase, lowercase and non-ASCII keys
+    let map: ConcurrentTst<usize> = ConcurrentTst::default();
+
+    assert!(map.is_empty());
+
+    for k in ["zoo", "Zoo", "Mat", "mat", "apple", "été", "1", "#"].iter() {
+
+        map.insert(k, k.len());
+    }
+
+    let keys: Vec<String> = map.complete("").into_iter().map(|(k, _)| k).collect();
+    assert_eq!(keys, ["#", "1", "Mat", "Zoo", "apple", "mat", "zoo", "été"]);
+    assert_eq!(map.complete("M"), [("Mat".to_string(), 3)]);
+    assert!(!map.is_empty());
+
+    ////////////////////////////////////////////////////
+
+    //Writers on every shard at once, with readers in between
+    let map = Arc::new(ConcurrentTst::new());
+
+    let mut threads = Vec::new();
+
+    for c1 in b'a'..=b'z' {
+
+        let map = Arc::clone(&map);
+
+        threads.push(thread::spawn(move || {
+
+            for c2 in b'a'..=b'z' {
+
+                let key = String::from_utf8(vec![c1, c2]).unwrap();
+
+                map.insert(&key, key.clone());
+                assert_eq!(map.get(&key), Some(key.clone()));
+            }
+
+            map.complete(&(c1 as char).to_string()).len()
+        }));
+    }
+
+    for t in threads {
+
+        assert_eq!(t.join().unwrap(), 26);
+    }
+
+    assert_eq!(map.len(), 676);
+    assert_eq!(map.complete("q").len(), 26);
+
+    let threads: Vec<_> = (b'a'..=b'z').map(|c1| {
+
+        let map = Arc::clone(&map);
+
+        thread::spawn(move || map.remove(&String::from_utf8(vec![c1, c1]).unwrap()))
+
+    }).collect();
+
+    for t in threads {
+
+        assert!(t.join().unwrap().is_some());
+    }
+
+    assert_eq!(map.len(), 650);
+}
+
+
+#[test]
+fn tst_snapshot_publisher() {
+
+    use ternary_tree::{PersistentTst, SnapshotPublisher};
+    use std::thread;
+
+    let mut publisher = SnapshotPublisher::default();
+    let mut reader = publisher.reader();
+
+    assert_eq!(publisher.generation(), 0);
+    assert_eq!(reader.load().len(), 0);
+
+    assert_eq!(publisher.insert("abc", 1), None);
+    assert_eq!(publisher.generation(), 1);
+
+    //Versions already loaded do not change
+    let old = reader.snapshot();
+    assert_eq!(old.len(), 1);
+
+    publisher.batch(|map| {
+
+        for (i, k) in RANDOM_VEC_123.iter().enumerate() {
+
+            map.insert(k, i);
+        }
+
+        map.remove("c");
+    });
+
+    assert_eq!(publisher.generation(), 2);
+    assert_eq!(publisher.map().len(), 15);
+    assert_eq!(old.get("abc"), Some(&1));
+    assert_eq!(old.get("aba"), None);
+
+    assert_eq!(reader.load().len(), 15);
+    assert_eq!(reader.load().get("abc"), Some(&4));
+    assert_eq!(reader.load().get("c"), None);
+    assert_eq!(reader.generation(), 2);
+
+    assert_eq!(publisher.remove("abc"), Some(4));
+    assert_eq!(publisher.remove("abc"), None);
+    assert_eq!(publisher.generation(), 4);
+
+    let mut clone = reader.clone();
+    assert_eq!(clone.generation(), 2);
+    assert_eq!(clone.load().len(), 14);
+    assert_eq!(clone.generation(), 4);
+
+    ////////////////////////////////////////////////////
+
+    let mut map = PersistentTst::new();
+    map.insert("a", 0);
+
+    let mut publisher = SnapshotPublisher::from_map(map);
+
+    //Readers on other threads only ever see whole batches
+    let readers: Vec<_> = (0..4).map(|_| {
+
+        let mut reader = publisher.reader();
+
+        thread::spawn(move || {
+
+            loop {
+
+                let map = reader.load();
+                let a = *map.get("a").unwrap();
+
+                assert_eq!(map.len(), 1 + a * 2);
+
+                for i in 0..a {
+
+                    assert_eq!(map.get(&format!("b{}", i)), Some(&i));
+                    assert_eq!(map.get(&format!("c{}", i)), Some(&i));
+                }
+
+                if a == 50 {
+
+                    break;
+                }
+            }
+        })
+
+    }).collect();
+
+    for i in 0..50 {
+
+        publisher.batch(|map| {
+
+            map.insert(&format!("b{}", i), i);
+            map.insert(&format!("c{}", i), i);
+            map.insert("a", i + 1);
+        });
+    }
+
+    for reader in readers {
+
+        reader.join().unwrap();
+    }
+
+    assert_eq!(publisher.map().len(), 101);
+}